@@ -1,21 +1,158 @@
 use anyhow::{anyhow, Context, Error, Result};
+use bytes::Bytes;
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc,
+};
 use fehler::{throw, throws};
-use rusoto_core::Region;
+use futures::{Future as _, Sink as _, Stream as _};
+#[cfg(feature = "apigw")]
+use rusoto_apigateway::{
+    ApiGateway as _, ApiGatewayClient, GetRestApisRequest,
+    GetStagesRequest as GetRestStagesRequest,
+};
+#[cfg(feature = "apigw")]
+use rusoto_apigatewayv2::{
+    ApiGatewayV2 as _, ApiGatewayV2Client, GetApisRequest,
+    GetStagesRequest as GetHttpStagesRequest,
+};
+#[cfg(feature = "cost")]
+use rusoto_budgets::{
+    Budgets as _, BudgetsClient, DescribeBudgetsRequest, Spend,
+};
+#[cfg(feature = "cost")]
+use rusoto_ce::{
+    CostExplorer as _, CostExplorerClient, DateInterval,
+    GetCostAndUsageRequest, GroupDefinition,
+};
+use rusoto_cloudtrail::{
+    CloudTrail as _, CloudTrailClient, LookupAttribute, LookupEventsRequest,
+};
+use rusoto_cloudwatch::{
+    CloudWatch as _, CloudWatchClient, DeleteAnomalyDetectorInput,
+    DescribeAnomalyDetectorsInput, Dimension, GetMetricStatisticsInput,
+    PutAnomalyDetectorInput,
+};
+use rusoto_core::credential::{AwsCredentials, ProfileProvider};
+use rusoto_core::signature::SignedRequest;
+use rusoto_core::{
+    ByteStream, Client, DefaultCredentialsProvider, HttpClient,
+    ProvideAwsCredentials, Region, RusotoError, RusotoFuture,
+};
 use rusoto_ec2::{
-    DescribeInstancesRequest, Ec2 as _, Ec2Client, Instance,
-    RebootInstancesRequest, StartInstancesRequest, StopInstancesRequest,
-    TerminateInstancesRequest,
+    AttachVolumeRequest, AttributeValue, AuthorizeSecurityGroupIngressRequest,
+    CopySnapshotRequest, CreateFlowLogsRequest, CreateImageRequest,
+    CreateKeyPairRequest, CreateSnapshotRequest, CreateTagsRequest,
+    CreateVpcEndpointRequest, DeleteSnapshotRequest, DeleteTagsRequest,
+    DeregisterImageRequest, DescribeAvailabilityZonesRequest,
+    DescribeClientVpnEndpointsRequest, DescribeImagesRequest,
+    DescribeInstanceStatusRequest, DescribeInstancesRequest,
+    DescribeKeyPairsRequest, DescribeNetworkInterfacesRequest,
+    DescribeRegionsRequest, DescribeReservedInstancesRequest,
+    DescribeRouteTablesRequest, DescribeSecurityGroupsRequest,
+    DescribeSnapshotsRequest, DescribeSpotInstanceRequestsRequest,
+    DescribeSpotPriceHistoryRequest, DescribeSubnetsRequest,
+    DescribeTransitGatewayAttachmentsRequest, DescribeTransitGatewaysRequest,
+    DescribeVolumesModificationsRequest, DescribeVolumesRequest,
+    DescribeVpcEndpointsRequest, DescribeVpcPeeringConnectionsRequest,
+    DescribeVpcsRequest, DescribeVpnConnectionsRequest, DetachVolumeRequest,
+    Ec2 as _, Ec2Client, Filter, GetConsoleOutputRequest,
+    GetConsoleScreenshotRequest,
+    GetTransitGatewayAttachmentPropagationsRequest, ImportKeyPairRequest,
+    Instance, InstanceMarketOptionsRequest, IpPermission, IpRange, Ipv6Range,
+    ModifyInstanceAttributeRequest, RebootInstancesRequest,
+    RevokeSecurityGroupIngressRequest, RunInstancesRequest, SecurityGroup,
+    SpotMarketOptions, SpotPrice, StartInstancesRequest, StopInstancesRequest,
+    Tag, TagSpecification, TerminateInstancesRequest, Volume,
+    VpcPeeringConnectionVpcInfo,
+};
+#[cfg(feature = "eb")]
+use rusoto_elasticbeanstalk::{
+    DescribeEnvironmentsMessage, DescribeEventsMessage, ElasticBeanstalk as _,
+    ElasticBeanstalkClient, UpdateEnvironmentMessage,
+};
+#[cfg(feature = "glacier")]
+use rusoto_glacier::{
+    GetJobOutputInput, Glacier as _, GlacierClient, InitiateJobInput,
+    JobParameters, ListJobsInput, ListVaultsInput,
+};
+use rusoto_iam::{
+    AttachGroupPolicyRequest, AttachRolePolicyRequest, AttachUserPolicyRequest,
+    CreateRoleRequest, DeleteRolePolicyRequest, DeleteRoleRequest,
+    DeleteServiceLinkedRoleRequest, DetachGroupPolicyRequest,
+    DetachRolePolicyRequest, DetachUserPolicyRequest, GetRoleRequest, Iam as _,
+    IamClient, ListAttachedRolePoliciesRequest, ListEntitiesForPolicyRequest,
+    ListInstanceProfilesForRoleRequest, ListRolePoliciesRequest,
+    ListRolesRequest, PutRolePolicyRequest,
+    RemoveRoleFromInstanceProfileRequest, Role,
+};
+#[cfg(feature = "lightsail")]
+use rusoto_lightsail::{
+    GetInstanceRequest as GetLightsailInstanceRequest,
+    GetInstancesRequest as GetLightsailInstancesRequest, Lightsail as _,
+    LightsailClient, StartInstanceRequest as StartLightsailInstanceRequest,
+    StopInstanceRequest as StopLightsailInstanceRequest,
 };
 use rusoto_logs::{
     CloudWatchLogs, CloudWatchLogsClient, DescribeLogGroupsRequest,
-    DescribeLogStreamsRequest,
+    DescribeLogStreamsRequest, FilterLogEventsRequest, FilteredLogEvent,
+    GetLogEventsRequest, GetQueryResultsRequest, StartQueryRequest,
+};
+#[cfg(feature = "price")]
+use rusoto_pricing::{
+    Filter as PricingFilter, GetProductsRequest, Pricing as _, PricingClient,
+};
+use rusoto_s3::{
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest,
+    CompletedMultipartUpload, CompletedPart, CreateMultipartUploadRequest,
+    DeleteMarkerReplication, DeleteObjectRequest, Destination,
+    GetBucketLocationRequest, GetBucketReplicationRequest,
+    GetBucketVersioningRequest, GetObjectLegalHoldRequest,
+    GetObjectLockConfigurationRequest, GetObjectRequest,
+    GetObjectRetentionRequest, HeadBucketRequest, ListObjectsV2Request,
+    ListPartsRequest, PutBucketReplicationRequest, PutObjectRequest,
+    ReplicationConfiguration, ReplicationRule, ReplicationRuleFilter, S3Client,
+    UploadPartRequest, S3 as _,
+};
+#[cfg(feature = "ses")]
+use rusoto_ses::{Ses as _, SesClient};
+use rusoto_ssm::{
+    ComplianceStringFilter, DescribeInstanceInformationRequest,
+    DocumentKeyValuesFilter, GetCommandInvocationRequest, InstanceInformation,
+    ListCommandInvocationsRequest, ListCommandsRequest, ListDocumentsRequest,
+    ListInventoryEntriesRequest, ListResourceComplianceSummariesRequest,
+    SendCommandRequest, Ssm as _, SsmClient, Target as SsmTarget,
+    UpdateOpsItemRequest,
+};
+use rusoto_sts::{
+    GetCallerIdentityRequest, Sts as _,
+    StsAssumeRoleSessionCredentialsProvider, StsClient,
 };
-use rusoto_s3::{S3Client, S3 as _};
-use std::{thread, time};
+#[cfg(feature = "workspaces")]
+use rusoto_workspaces::{
+    DescribeWorkspacesRequest, FailedWorkspaceChangeRequest, RebootRequest,
+    RebootWorkspacesRequest, RebuildRequest, RebuildWorkspacesRequest,
+    Workspaces as _, WorkspacesClient,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::Infallible;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::{fs, process, thread, time};
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
+use xml::reader::XmlEvent;
 
 fn get_instance_name(instance: &Instance) -> Option<String> {
-    if let Some(tags) = &instance.tags {
+    get_name_tag(&instance.tags)
+}
+
+/// Get the value of the "Name" tag from an EC2 resource's tags.
+fn get_name_tag(tags: &Option<Vec<Tag>>) -> Option<String> {
+    if let Some(tags) = tags {
         for tag in tags {
             if let Some(key) = &tag.key {
                 if key == "Name" {
@@ -38,37 +175,152 @@ fn get_instance_state_name(instance: &Instance) -> Option<String> {
     None
 }
 
+/// Check whether a string is an instance ID rather than a name.
+fn is_instance_id(s: &str) -> bool {
+    s.strip_prefix("i-").is_some_and(|rest| {
+        !rest.is_empty() && rest.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Check whether a string is the ID of a resource that can be tagged
+/// with `ec2 tag`: an instance, volume, AMI, or snapshot.
+fn is_taggable_resource_id(s: &str) -> bool {
+    s.split_once('-').is_some_and(|(prefix, rest)| {
+        ["i", "vol", "ami", "snap"].contains(&prefix)
+            && !rest.is_empty()
+            && rest.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Split arguments into the leading resource IDs and the rest.
+#[throws]
+fn split_resource_ids(mut args: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let count = args
+        .iter()
+        .take_while(|arg| is_taggable_resource_id(arg))
+        .count();
+    let rest = args.split_off(count);
+    if args.is_empty() {
+        throw!(anyhow!("expected at least one resource ID"));
+    }
+    if rest.is_empty() {
+        throw!(anyhow!("expected at least one tag"));
+    }
+    (args, rest)
+}
+
+/// Resolve instance IDs or Name tag values to instance IDs. Names can
+/// contain `*` and `?` wildcards, and may match more than one
+/// instance.
+#[throws]
+fn ec2_resolve_instance_ids(instances: Vec<String>) -> Vec<String> {
+    let mut ids = Vec::new();
+    for instance in instances {
+        if is_instance_id(&instance) {
+            ids.push(instance);
+            continue;
+        }
+        let matches = ec2_get_instances(Some(vec![
+            Filter {
+                name: Some("tag:Name".into()),
+                values: Some(vec![instance.clone()]),
+            },
+            // Terminated instances keep their tags for a while
+            Filter {
+                name: Some("instance-state-name".into()),
+                values: Some(
+                    ["pending", "running", "stopping", "stopped"]
+                        .iter()
+                        .map(|state| state.to_string())
+                        .collect(),
+                ),
+            },
+        ]))?;
+        if matches.is_empty() {
+            throw!(anyhow!("no instances match {}", instance));
+        }
+        ids.extend(matches.into_iter().filter_map(|i| i.instance_id));
+    }
+    ids
+}
+
+/// Resolve an instance ID or Name tag value that must match exactly
+/// one instance.
+#[throws]
+fn ec2_resolve_instance_id(instance: String) -> String {
+    let mut ids = ec2_resolve_instance_ids(vec![instance.clone()])?;
+    if ids.len() != 1 {
+        throw!(anyhow!("{} matches {} instances", instance, ids.len()));
+    }
+    ids.remove(0)
+}
+
+/// Get all instances matching the filters.
 #[throws]
-fn ec2_list_instances() {
+fn ec2_get_instances(filters: Option<Vec<Filter>>) -> Vec<Instance> {
     let client = Ec2Client::new(Region::default());
-    let output = client
-        .describe_instances(DescribeInstancesRequest {
-            ..Default::default()
-        })
-        .sync()
-        .context("failed to list instances")?;
-    let reservations =
-        output.reservations.context("missing reservations field")?;
+    let mut instances = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_instances(DescribeInstancesRequest {
+                filters: filters.clone(),
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list instances")?;
+        let reservations =
+            output.reservations.context("missing reservations field")?;
+        for reservation in reservations {
+            instances.extend(reservation.instances.unwrap_or_default());
+        }
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    instances
+}
+
+#[throws]
+fn ec2_list_instances(args: Ec2Instances) {
     struct Row {
         id: String,
         name: String,
         state: String,
     }
+    let mut filters: Vec<_> = args
+        .tags
+        .into_iter()
+        .map(|(key, value)| Filter {
+            name: Some(format!("tag:{}", key)),
+            values: Some(vec![value]),
+        })
+        .collect();
+    if let Some(state) = args.state {
+        filters.push(Filter {
+            name: Some("instance-state-name".into()),
+            values: Some(vec![state]),
+        });
+    }
+    let filters = if filters.is_empty() {
+        None
+    } else {
+        Some(filters)
+    };
     let mut instances = Vec::new();
-    for reservation in reservations {
-        if let Some(res_instances) = reservation.instances {
-            for instance in res_instances {
-                let id = instance
-                    .instance_id
-                    .clone()
-                    .unwrap_or_else(|| "i-?????????????????".to_string());
-                let name = get_instance_name(&instance)
-                    .unwrap_or_else(|| "<no-name>".into());
-                let state = get_instance_state_name(&instance)
-                    .unwrap_or_else(|| "unknown".into());
-                instances.push(Row { id, name, state });
-            }
-        }
+    for instance in ec2_get_instances(filters)? {
+        let id = instance
+            .instance_id
+            .clone()
+            .unwrap_or_else(|| "i-?????????????????".to_string());
+        let name =
+            get_instance_name(&instance).unwrap_or_else(|| "<no-name>".into());
+        let state = get_instance_state_name(&instance)
+            .unwrap_or_else(|| "unknown".into());
+        instances.push(Row { id, name, state });
     }
 
     // Sort the instances by name
@@ -92,8 +344,97 @@ fn ec2_list_instances() {
     }
 }
 
+/// Copy text to the system clipboard with the first clipboard tool
+/// that's installed.
+#[throws]
+fn copy_to_clipboard(text: &str) {
+    let tools: &[(&str, &[&str])] = &[
+        ("pbcopy", &[]),
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+    ];
+    let mut spawned = None;
+    for (program, args) in tools {
+        // wl-copy fails outside of a Wayland session
+        if *program == "wl-copy"
+            && std::env::var_os("WAYLAND_DISPLAY").is_none()
+        {
+            continue;
+        }
+        match process::Command::new(program)
+            .args(*args)
+            .stdin(process::Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => {
+                spawned = Some((program, child));
+                break;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => Err(err)
+                .with_context(|| format!("failed to run {}", program))?,
+        }
+    }
+    let (program, mut child) = match spawned {
+        Some(spawned) => spawned,
+        None => throw!(anyhow!(
+            "no clipboard tool found (tried pbcopy, wl-copy, xclip, and xsel)"
+        )),
+    };
+    child
+        .stdin
+        .take()
+        .context("missing clipboard tool stdin")?
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        throw!(anyhow!("{} failed: {}", program, status));
+    }
+}
+
+/// Run a local lookup tool and return its stdout.
+#[throws]
+fn run_lookup(program: &str, args: &[&str]) -> String {
+    let output = process::Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run {}", program))?;
+    if !output.status.success() {
+        throw!(anyhow!("{} failed: {}", program, output.status));
+    }
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Get the PTR names for an IP address using the local resolver.
+#[throws]
+fn reverse_dns(ip: &str) -> String {
+    let output = run_lookup("dig", &["+short", "-x", ip])?;
+    let names: Vec<_> = output.lines().map(str::trim).collect();
+    if names.is_empty() {
+        "(none)".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+/// Get the ownership and location lines from a whois lookup.
+#[throws]
+fn whois_summary(ip: &str) -> Vec<String> {
+    let keys = ["netname", "orgname", "org-name", "descr", "country", "city"];
+    run_lookup("whois", &[ip])?
+        .lines()
+        .filter(|line| {
+            line.split(':').next().is_some_and(|key| {
+                keys.contains(&key.trim().to_lowercase().as_str())
+            })
+        })
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
 #[throws]
-fn ec2_show_addresses(instance_id: String) {
+fn ec2_show_addresses(instance_id: String, args: &Ec2Addr) {
     println!("{}:", instance_id);
     let client = Ec2Client::new(Region::default());
     let output = client
@@ -108,19 +449,52 @@ fn ec2_show_addresses(instance_id: String) {
     for reservation in reservations {
         if let Some(res_instances) = reservation.instances {
             for instance in res_instances {
-                println!(
-                    "  private IP: {}",
-                    instance.private_ip_address.unwrap_or_default()
-                );
-                println!(
-                    "  public IP: {}",
-                    instance.public_ip_address.unwrap_or_default()
-                );
+                let private_ip =
+                    instance.private_ip_address.unwrap_or_default();
+                println!("  private IP: {}", private_ip);
+                let public_ip = instance.public_ip_address.unwrap_or_default();
+                println!("  public IP: {}", public_ip);
+                if args.copy {
+                    copy_to_clipboard(if public_ip.is_empty() {
+                        &private_ip
+                    } else {
+                        &public_ip
+                    })?;
+                }
+                if public_ip.is_empty() {
+                    continue;
+                }
+                if args.rdns {
+                    match reverse_dns(&public_ip) {
+                        Ok(names) => println!("  reverse DNS: {}", names),
+                        Err(err) => eprintln!("  {:#}", err),
+                    }
+                }
+                if args.whois {
+                    match whois_summary(&public_ip) {
+                        Ok(lines) => {
+                            println!("  whois:");
+                            for line in lines {
+                                println!("    {}", line);
+                            }
+                        }
+                        Err(err) => eprintln!("  {:#}", err),
+                    }
+                }
             }
         }
     }
 }
 
+#[throws]
+fn ec2_addr(args: Ec2Addr) {
+    let instance_ids = ec2_resolve_instance_ids(args.instances.clone())?;
+    if args.copy && instance_ids.len() > 1 {
+        throw!(anyhow!("--copy only works with a single instance"));
+    }
+    for_each(|id| ec2_show_addresses(id, &args), instance_ids)?;
+}
+
 #[throws]
 fn ec2_start_instance(instance_id: String) {
     let client = Ec2Client::new(Region::default());
@@ -158,161 +532,13580 @@ fn ec2_terminate_instance(instance_id: String) {
 }
 
 #[throws]
-fn ec2_reboot_instance(instance_id: String) {
+fn ec2_launch(
+    args: Ec2Launch,
+    instance_market_options: Option<InstanceMarketOptionsRequest>,
+) {
+    let user_data = match &args.user_data {
+        Some(path) => {
+            let data = fs::read(path).with_context(|| {
+                format!("failed to read {}", path.display())
+            })?;
+            Some(base64::encode(data))
+        }
+        None => None,
+    };
+    let tag_specifications = args.name.map(|name| {
+        vec![TagSpecification {
+            resource_type: Some("instance".into()),
+            tags: Some(vec![Tag {
+                key: Some("Name".into()),
+                value: Some(name),
+            }]),
+        }]
+    });
     let client = Ec2Client::new(Region::default());
-    client
-        .reboot_instances(RebootInstancesRequest {
-            instance_ids: vec![instance_id],
+    let output = client
+        .run_instances(RunInstancesRequest {
+            image_id: Some(args.ami),
+            instance_type: Some(args.instance_type),
+            key_name: args.key_name,
+            security_group_ids: if args.security_groups.is_empty() {
+                None
+            } else {
+                Some(args.security_groups)
+            },
+            subnet_id: args.subnet,
+            tag_specifications,
+            user_data,
+            instance_market_options,
+            min_count: 1,
+            max_count: 1,
             ..Default::default()
         })
         .sync()
-        .context("failed to reboot instance")?;
+        .context("failed to launch instance")?;
+    for instance in output.instances.unwrap_or_default() {
+        println!("{}", instance.instance_id.unwrap_or_default());
+    }
 }
 
-// Not using #[throws] here because of
-// github.com/withoutboats/fehler/issues/52
-fn logs_groups(args: ListLogGroups) -> Result<(), Error> {
-    let client = CloudWatchLogsClient::new(Region::default());
+#[throws]
+fn ec2_spot_requests() {
+    let client = Ec2Client::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "STATE".to_string(),
+        "STATUS".to_string(),
+        "TYPE".to_string(),
+        "MAX PRICE".to_string(),
+        "AZ".to_string(),
+        "INSTANCE".to_string(),
+    ]);
     let mut next_token = None;
     loop {
-        let resp = client
-            .describe_log_groups(DescribeLogGroupsRequest {
-                log_group_name_prefix: args.prefix.clone(),
-                next_token: next_token.clone(),
-                ..Default::default()
-            })
+        let output = client
+            .describe_spot_instance_requests(
+                DescribeSpotInstanceRequestsRequest {
+                    next_token: next_token.clone(),
+                    ..Default::default()
+                },
+            )
             .sync()
-            .context("failed to list groups")?;
-        if let Some(log_groups) = resp.log_groups {
-            for log_group in log_groups {
-                println!(
-                    "{}",
-                    log_group
-                        .log_group_name
-                        .ok_or_else(|| anyhow!("missing log group name"))?
-                );
-            }
+            .context("failed to list spot instance requests")?;
+        for request in output.spot_instance_requests.unwrap_or_default() {
+            table.push(vec![
+                request.spot_instance_request_id.unwrap_or_default(),
+                request.state.unwrap_or_default(),
+                request
+                    .status
+                    .and_then(|status| status.code)
+                    .unwrap_or_default(),
+                request
+                    .launch_specification
+                    .and_then(|spec| spec.instance_type)
+                    .unwrap_or_default(),
+                request.spot_price.unwrap_or_default(),
+                request.launched_availability_zone.unwrap_or_default(),
+                request.instance_id.unwrap_or_default(),
+            ]);
         }
+        table.flush();
         // Finish if there are no more results
-        if resp.next_token.is_none() {
-            return Ok(());
+        if output.next_token.is_none() {
+            break;
         }
-        next_token = resp.next_token;
+        next_token = output.next_token;
     }
+    table.finish();
 }
 
-// Not using #[throws] here because of
-// github.com/withoutboats/fehler/issues/52
-fn logs_recent_streams(args: RecentLogStreams) -> Result<(), Error> {
-    let client = CloudWatchLogsClient::new(Region::default());
-    let mut next_token = None;
-    let mut remaining = args.limit as i64;
-    loop {
-        // The describe-log-streams operation has a limit of five
-        // transactions per second, so attempt up to five requests and
-        // then sleep for 1 second.
-        for _ in 0..5 {
-            // 50 is the maximum for a single request
-            let limit = std::cmp::min(remaining, 50);
-            let resp = client
-                .describe_log_streams(DescribeLogStreamsRequest {
-                    log_group_name: args.log_group_name.clone(),
-                    limit: Some(limit),
-                    order_by: Some("LastEventTime".to_string()),
-                    next_token: next_token.clone(),
-                    descending: Some(true),
+#[throws]
+fn ec2_spot_prices(instance_type: String, os: String) {
+    let (_, product_description) = parse_ec2_os(&os)?;
+    let mut rows = vec![vec!["AZ".to_string(), "PRICE".to_string()]];
+    for price in
+        get_spot_prices(Region::default(), instance_type, product_description)?
+    {
+        rows.push(vec![
+            price.availability_zone.unwrap_or_default(),
+            format!("{} USD/Hrs", price.spot_price.unwrap_or_default()),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn ec2_spot_launch(args: Ec2SpotLaunch) {
+    ec2_launch(
+        args.launch,
+        Some(InstanceMarketOptionsRequest {
+            market_type: Some("spot".into()),
+            spot_options: Some(SpotMarketOptions {
+                // Without a max price, the limit is the on-demand price
+                max_price: args.max_price,
+                ..Default::default()
+            }),
+        }),
+    )?;
+}
+
+/// Guess the default SSH user of an AMI from its name and
+/// description.
+fn ec2_default_ssh_user(image: &rusoto_ec2::Image) -> &'static str {
+    let name = format!(
+        "{} {}",
+        image.name.as_deref().unwrap_or_default(),
+        image.description.as_deref().unwrap_or_default()
+    )
+    .to_lowercase();
+    let users = [
+        ("ubuntu", "ubuntu"),
+        ("debian", "admin"),
+        ("centos", "centos"),
+        ("fedora", "fedora"),
+        ("rocky", "rocky"),
+        ("almalinux", "ec2-user"),
+        ("bitnami", "bitnami"),
+    ];
+    users
+        .iter()
+        .find(|(pattern, _)| name.contains(pattern))
+        .map(|(_, user)| *user)
+        // Amazon Linux, RHEL, and SUSE all use ec2-user
+        .unwrap_or("ec2-user")
+}
+
+#[throws]
+fn ec2_ssh(args: Ec2Ssh) {
+    let instance_id = ec2_resolve_instance_id(args.instance)?;
+    let instance = ec2_get_instances(Some(vec![Filter {
+        name: Some("instance-id".into()),
+        values: Some(vec![instance_id.clone()]),
+    }]))?
+    .into_iter()
+    .next()
+    .with_context(|| format!("instance not found: {}", instance_id))?;
+    let ip = if args.private {
+        instance.private_ip_address
+    } else {
+        instance.public_ip_address
+    }
+    .with_context(|| format!("{} has no such IP address", instance_id))?;
+
+    let user = match args.user {
+        Some(user) => user,
+        None => {
+            let client = Ec2Client::new(Region::default());
+            let output = client
+                .describe_images(DescribeImagesRequest {
+                    image_ids: instance.image_id.map(|id| vec![id]),
                     ..Default::default()
                 })
                 .sync()
-                .context("failed to list streams")?;
-            if let Some(log_streams) = resp.log_streams {
-                for log_stream in log_streams {
-                    println!(
-                        "{}",
-                        log_stream.log_stream_name.ok_or_else(|| anyhow!(
-                            "missing log stream name"
-                        ))?
-                    );
-                }
-            }
-            // Finish if there are no more results
-            if resp.next_token.is_none() {
-                return Ok(());
-            }
-            remaining -= limit;
-            // Finish if the number of requested streams has already
-            // been shown
-            if remaining <= 0 {
-                return Ok(());
-            }
-            next_token = resp.next_token;
+                .context("failed to get image")?;
+            // The image may have been deregistered since the launch
+            output
+                .images
+                .unwrap_or_default()
+                .first()
+                .map(ec2_default_ssh_user)
+                .unwrap_or("ec2-user")
+                .to_string()
         }
-        thread::sleep(time::Duration::from_secs(1));
-    }
+    };
+
+    // exec only returns if running ssh failed
+    let err = process::Command::new("ssh")
+        .arg(format!("{}@{}", user, ip))
+        .args(args.ssh_args)
+        .exec();
+    Err(err).context("failed to run ssh")?;
 }
 
 #[throws]
-fn s3_list_buckets() {
-    let client = S3Client::new(Region::default());
+fn ec2_console(args: Ec2Console) {
+    let instance_id = ec2_resolve_instance_id(args.instance)?;
+    let client = Ec2Client::new(Region::default());
     let output = client
-        .list_buckets()
+        .get_console_output(GetConsoleOutputRequest {
+            instance_id: instance_id.clone(),
+            latest: Some(args.latest),
+            ..Default::default()
+        })
         .sync()
-        .context("failed to list buckets")?;
-    let buckets = output.buckets.context("missing buckets field")?;
-    for bucket in buckets {
-        let name = bucket.name.context("missing bucket name")?;
-        println!("{}", name);
+        .context("failed to get console output")?;
+    // The output is missing if the instance hasn't written any yet
+    let encoded = output
+        .output
+        .with_context(|| format!("no console output for {}", instance_id))?;
+    let decoded = base64::decode(encoded.trim())
+        .context("failed to decode console output")?;
+    if let Some(timestamp) = output.timestamp {
+        eprintln!("console output as of {}", timestamp);
     }
+    std::io::stdout().write_all(&decoded)?;
 }
 
-#[derive(Debug, StructOpt)]
-enum Ec2 {
-    /// List instances.
-    Instances,
-    /// Show an instance's IP address(es)
-    Addr { instance_ids: Vec<String> },
-    /// Start an instance.
-    Start { instance_ids: Vec<String> },
-    /// Stop an instance.
-    Stop { instance_ids: Vec<String> },
-    /// Terminate an instance.
-    Terminate { instance_ids: Vec<String> },
-    /// Reboot an instance.
-    Reboot { instance_ids: Vec<String> },
+#[throws]
+fn ec2_screenshot(args: Ec2Screenshot) {
+    let instance_id = ec2_resolve_instance_id(args.instance)?;
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .get_console_screenshot(GetConsoleScreenshotRequest {
+            instance_id: instance_id.clone(),
+            // Wake the screen in case it went to sleep
+            wake_up: Some(true),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to get console screenshot")?;
+    let data = base64::decode(output.image_data.context("missing image data")?)
+        .context("failed to decode screenshot")?;
+    let path = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{}.jpg", instance_id)));
+    fs::write(&path, data)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    println!("wrote {}", path.display());
 }
 
-#[derive(Debug, StructOpt)]
-struct ListLogGroups {
-    prefix: Option<String>,
-}
+#[throws]
+fn ec2_rightsize() {
+    let mut recommendations = Vec::new();
+    let mut payload = serde_json::json!({
+        "filters": [{ "name": "Finding", "values": ["Overprovisioned"] }],
+    });
+    loop {
+        let mut output = json_request(
+            "compute-optimizer",
+            "compute-optimizer",
+            "ComputeOptimizerService",
+            "GetEC2InstanceRecommendations",
+            payload.clone(),
+        )?;
+        if let Some(list) = output["instanceRecommendations"].as_array_mut() {
+            recommendations.append(list);
+        }
 
-#[derive(Debug, StructOpt)]
-struct RecentLogStreams {
-    log_group_name: String,
-    #[structopt(long, default_value = "10")]
-    limit: usize,
-}
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) => payload["nextToken"] = token.into(),
+            None => break,
+        }
+    }
 
-#[derive(Debug, StructOpt)]
-enum Logs {
-    /// List CloudWatch Logs groups.
-    Groups(ListLogGroups),
-    /// List recent CloudWatch Logs streams.
-    RecentStreams(RecentLogStreams),
-}
+    // Options are ranked, with the best one first
+    let best_option = |recommendation: &serde_json::Value| {
+        recommendation["recommendationOptions"]
+            .as_array()
+            .and_then(|options| {
+                options.iter().min_by_key(|o| o["rank"].as_i64())
+            })
+            .cloned()
+            .unwrap_or_default()
+    };
+    let savings = |option: &serde_json::Value| {
+        option["savingsOpportunity"]["estimatedMonthlySavings"]["value"]
+            .as_f64()
+            .unwrap_or_default()
+    };
+    recommendations.sort_by_key(|recommendation| {
+        std::cmp::Reverse(
+            (savings(&best_option(recommendation)) * 100.0) as i64,
+        )
+    });
 
-#[derive(Debug, StructOpt)]
-enum S3 {
-    /// List buckets.
-    Buckets,
+    let mut rows = vec![vec![
+        "INSTANCE".to_string(),
+        "NAME".to_string(),
+        "CURRENT".to_string(),
+        "SUGGESTED".to_string(),
+        "MAX CPU".to_string(),
+        "SAVINGS/MONTH".to_string(),
+    ]];
+    // Savings can be in different currencies, so total each separately
+    let mut totals: BTreeMap<String, f64> = BTreeMap::new();
+    for recommendation in &recommendations {
+        let option = best_option(recommendation);
+        let max_cpu = recommendation["utilizationMetrics"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|metric| {
+                metric["name"] == "CPU" && metric["statistic"] == "MAXIMUM"
+            })
+            .and_then(|metric| metric["value"].as_f64())
+            .map(|value| format!("{:.1}%", value))
+            .unwrap_or_default();
+        let currency = option["savingsOpportunity"]["estimatedMonthlySavings"]
+            ["currency"]
+            .as_str()
+            .unwrap_or("USD");
+        *totals.entry(currency.into()).or_default() += savings(&option);
+        rows.push(vec![
+            recommendation["instanceArn"]
+                .as_str()
+                .and_then(|arn| arn.rsplit('/').next())
+                .unwrap_or_default()
+                .into(),
+            recommendation["instanceName"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+            recommendation["currentInstanceType"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+            option["instanceType"].as_str().unwrap_or_default().into(),
+            max_cpu,
+            format!("{:.2} {}", savings(&option), currency),
+        ]);
+    }
+    print_table(&rows);
+    for (currency, total) in totals {
+        println!("total estimated savings: {:.2} {}/month", total, currency);
+    }
 }
 
-#[derive(Debug, StructOpt)]
-#[structopt(about = "AWS command-line tool")]
-enum Command {
-    Ec2(Ec2),
-    Logs(Logs),
-    S3(S3),
+/// Get the state of an instance, such as "running" or "stopped".
+#[throws]
+fn ec2_get_instance_state(instance_id: &str) -> String {
+    let instances = ec2_get_instances(Some(vec![Filter {
+        name: Some("instance-id".into()),
+        values: Some(vec![instance_id.into()]),
+    }]))?;
+    instances
+        .first()
+        .and_then(get_instance_state_name)
+        .with_context(|| format!("instance not found: {}", instance_id))?
+}
+
+#[throws]
+fn ec2_resize(args: Ec2Resize) {
+    let instance_id = ec2_resolve_instance_id(args.instance)?;
+    let state = ec2_get_instance_state(&instance_id)?;
+    if state != "stopped" {
+        if state != "stopping" {
+            eprintln!("stopping {}", instance_id);
+            ec2_stop_instance(instance_id.clone())?;
+        }
+        // The instance type can only be changed once it's stopped
+        ec2_wait_for_state(
+            vec![instance_id.clone()],
+            "stopped",
+            time::Duration::from_secs(10 * 60),
+        )?;
+    }
+
+    let client = Ec2Client::new(Region::default());
+    client
+        .modify_instance_attribute(ModifyInstanceAttributeRequest {
+            instance_id: instance_id.clone(),
+            instance_type: Some(AttributeValue {
+                value: Some(args.instance_type),
+            }),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to change instance type")?;
+
+    if args.start {
+        ec2_start_instance(instance_id)?;
+    }
+}
+
+#[throws]
+fn ec2_reboot_instance(instance_id: String) {
+    let client = Ec2Client::new(Region::default());
+    client
+        .reboot_instances(RebootInstancesRequest {
+            instance_ids: vec![instance_id],
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to reboot instance")?;
+}
+
+#[throws]
+fn ec2_wait(args: Ec2Wait) {
+    if !["running", "stopped", "terminated"].contains(&args.state.as_str()) {
+        throw!(anyhow!(
+            "invalid state: {} (expected running, stopped, or terminated)",
+            args.state
+        ));
+    }
+    let instance_ids = ec2_resolve_instance_ids(args.instances)?;
+    ec2_wait_for_state(instance_ids, &args.state, args.timeout.to_std()?)?;
+}
+
+/// Wait for instances to reach a state, polling with backoff until
+/// the timeout passes.
+#[throws]
+fn ec2_wait_for_state(
+    mut pending: Vec<String>,
+    target: &str,
+    timeout: time::Duration,
+) {
+    let deadline = time::Instant::now() + timeout;
+    let mut delay = time::Duration::from_secs(2);
+    loop {
+        let instances = ec2_get_instances(Some(vec![Filter {
+            name: Some("instance-id".into()),
+            values: Some(pending.clone()),
+        }]))?;
+        let states: HashMap<String, String> = instances
+            .iter()
+            .filter_map(|instance| {
+                Some((
+                    instance.instance_id.clone()?,
+                    get_instance_state_name(instance)?,
+                ))
+            })
+            .collect();
+        let mut still_pending = Vec::new();
+        for instance_id in pending {
+            let state = states.get(&instance_id).with_context(|| {
+                format!("instance not found: {}", instance_id)
+            })?;
+            if state == target {
+                continue;
+            }
+            // A terminated instance will never reach any other state
+            if target != "terminated"
+                && (state == "shutting-down" || state == "terminated")
+            {
+                throw!(anyhow!("{} was terminated", instance_id));
+            }
+            still_pending.push(instance_id);
+        }
+        pending = still_pending;
+        if pending.is_empty() {
+            break;
+        }
+
+        let now = time::Instant::now();
+        if now >= deadline {
+            throw!(anyhow!(
+                "timed out waiting for {} to be {}",
+                pending.join(", "),
+                target
+            ));
+        }
+        thread::sleep(delay.min(deadline - now));
+        // Back off up to 30 seconds between polls
+        delay = (delay * 2).min(time::Duration::from_secs(30));
+    }
+}
+
+#[throws]
+fn ec2_tag(args: Vec<String>) {
+    let (resources, tags) = split_resource_ids(args)?;
+    let tags = tags
+        .iter()
+        .map(|tag| {
+            let (key, value) = parse_key_value(tag)?;
+            Ok(Tag {
+                key: Some(key),
+                value: Some(value),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let client = Ec2Client::new(Region::default());
+    client
+        .create_tags(CreateTagsRequest {
+            resources,
+            tags,
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to create tags")?;
+}
+
+#[throws]
+fn ec2_untag(args: Vec<String>) {
+    let (resources, keys) = split_resource_ids(args)?;
+    let client = Ec2Client::new(Region::default());
+    client
+        .delete_tags(DeleteTagsRequest {
+            resources,
+            // Without a value, a tag is deleted whatever its value is
+            tags: Some(
+                keys.into_iter()
+                    .map(|key| Tag {
+                        key: Some(key),
+                        value: None,
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to delete tags")?;
+}
+
+#[throws]
+fn ec2_instance_types(args: Ec2InstanceTypes) {
+    struct Row {
+        name: String,
+        vcpus: u64,
+        memory_mib: u64,
+        network: String,
+        archs: Vec<String>,
+    }
+
+    // DescribeInstanceTypes isn't available in rusoto_ec2
+    let mut rows = Vec::new();
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut params = vec![("MaxResults", "100")];
+        if let Some(arch) = &args.arch {
+            params.push((
+                "Filter.1.Name",
+                "processor-info.supported-architecture",
+            ));
+            params.push(("Filter.1.Value.1", arch));
+        }
+        if let Some(token) = &next_token {
+            params.push(("NextToken", token));
+        }
+        let output = ec2_query("DescribeInstanceTypes", &params)?;
+        for item in output.items("instanceTypeSet") {
+            let number = |parent: &str, name: &str| -> u64 {
+                item.child(parent)
+                    .and_then(|child| child.child_text(name))
+                    .and_then(|text| text.parse().ok())
+                    .unwrap_or_default()
+            };
+            rows.push(Row {
+                name: item
+                    .child_text("instanceType")
+                    .unwrap_or_default()
+                    .into(),
+                vcpus: number("vCpuInfo", "defaultVCpus"),
+                memory_mib: number("memoryInfo", "sizeInMiB"),
+                network: item
+                    .child("networkInfo")
+                    .and_then(|info| info.child_text("networkPerformance"))
+                    .unwrap_or_default()
+                    .into(),
+                archs: item
+                    .child("processorInfo")
+                    .map(|info| info.items("supportedArchitectures"))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|arch| arch.text.clone())
+                    .collect(),
+            });
+        }
+
+        // Finish if there are no more results
+        match output.child_text("nextToken") {
+            Some(token) if !token.is_empty() => {
+                next_token = Some(token.to_string())
+            }
+            _ => break,
+        }
+    }
+
+    rows.retain(|row| {
+        args.min_vcpus.is_none_or(|min| row.vcpus >= min)
+            && args
+                .min_memory
+                .is_none_or(|min| row.memory_mib as f64 / 1024.0 >= min)
+    });
+    rows.sort_unstable_by(|a, b| {
+        (a.vcpus, a.memory_mib, &a.name).cmp(&(b.vcpus, b.memory_mib, &b.name))
+    });
+
+    let mut table = vec![vec![
+        "TYPE".to_string(),
+        "VCPUS".to_string(),
+        "MEMORY".to_string(),
+        "NETWORK".to_string(),
+        "ARCH".to_string(),
+    ]];
+    for row in rows {
+        table.push(vec![
+            row.name,
+            row.vcpus.to_string(),
+            format!("{} GiB", row.memory_mib as f64 / 1024.0),
+            row.network,
+            row.archs.join(","),
+        ]);
+    }
+    print_table(&table);
+}
+
+/// Call an action of the Savings Plans API, which has a single global
+/// endpoint.
+#[throws]
+fn savingsplans_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    let path = format!("/{}", action);
+    let mut request =
+        SignedRequest::new("POST", "savingsplans", &Region::UsEast1, &path);
+    request.set_hostname(Some("savingsplans.amazonaws.com".into()));
+    request.set_content_type("application/json".into());
+    request.set_payload(Some(serde_json::to_vec(&payload)?));
+    let body = dispatch_request(&Client::shared(), request)
+        .with_context(|| format!("{} failed", action))?;
+    serde_json::from_slice(&body)
+        .with_context(|| format!("failed to parse {} response", action))?
+}
+
+#[throws]
+fn ec2_reservations() {
+    let client = Ec2Client::new(Region::default());
+    let reserved = client
+        .describe_reserved_instances(DescribeReservedInstancesRequest {
+            filters: Some(vec![Filter {
+                name: Some("state".into()),
+                values: Some(vec!["active".into()]),
+            }]),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list reserved instances")?
+        .reserved_instances
+        .unwrap_or_default();
+
+    let mut plans = Vec::new();
+    let mut payload = serde_json::json!({ "states": ["active"] });
+    loop {
+        let output =
+            savingsplans_request("DescribeSavingsPlans", payload.clone())?;
+        plans.extend(
+            output["savingsPlans"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default(),
+        );
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) if !token.is_empty() => {
+                payload["nextToken"] = token.into()
+            }
+            _ => break,
+        }
+    }
+
+    let mut rows = vec![vec![
+        "RESERVED INSTANCE".to_string(),
+        "TYPE".to_string(),
+        "COUNT".to_string(),
+        "SCOPE".to_string(),
+        "PLATFORM".to_string(),
+        "END".to_string(),
+    ]];
+    let mut reserved_counts = BTreeMap::new();
+    for ri in reserved {
+        let instance_type = ri.instance_type.unwrap_or_default();
+        let count = ri.instance_count.unwrap_or_default();
+        *reserved_counts.entry(instance_type.clone()).or_insert(0) += count;
+        rows.push(vec![
+            ri.reserved_instances_id.unwrap_or_default(),
+            instance_type,
+            count.to_string(),
+            ri.availability_zone.or(ri.scope).unwrap_or_default(),
+            ri.product_description.unwrap_or_default(),
+            ri.end.unwrap_or_default(),
+        ]);
+    }
+    print_table(&rows);
+
+    println!();
+    let mut rows = vec![vec![
+        "SAVINGS PLAN".to_string(),
+        "TYPE".to_string(),
+        "FAMILY".to_string(),
+        "REGION".to_string(),
+        "COMMITMENT".to_string(),
+        "END".to_string(),
+    ]];
+    for plan in &plans {
+        let field = |name: &str| plan[name].as_str().unwrap_or_default();
+        rows.push(vec![
+            field("savingsPlanId").into(),
+            field("savingsPlanType").into(),
+            field("ec2InstanceFamily").into(),
+            field("region").into(),
+            format!("{} {}/hour", field("commitment"), field("currency")),
+            field("end").into(),
+        ]);
+    }
+    print_table(&rows);
+
+    // Compare the running instances with the reservations of the
+    // same type. Reservations can also apply to other sizes in the
+    // same family, so this is a quick check rather than a bill.
+    let mut running_counts = BTreeMap::new();
+    for instance in ec2_get_instances(Some(vec![Filter {
+        name: Some("instance-state-name".into()),
+        values: Some(vec!["running".into()]),
+    }]))? {
+        let instance_type = instance.instance_type.unwrap_or_default();
+        *running_counts.entry(instance_type).or_insert(0) += 1;
+    }
+    let region = Region::default();
+    let has_plan = |instance_type: &str| {
+        let family = instance_type.split('.').next().unwrap_or_default();
+        plans.iter().any(|plan| {
+            plan["savingsPlanType"] == "Compute"
+                || (plan["savingsPlanType"] == "EC2Instance"
+                    && plan["ec2InstanceFamily"] == family
+                    && plan["region"] == region.name())
+        })
+    };
+
+    println!();
+    let mut rows = vec![vec![
+        "INSTANCE TYPE".to_string(),
+        "RUNNING".to_string(),
+        "RESERVED".to_string(),
+        "SAVINGS PLAN".to_string(),
+        "GAP".to_string(),
+    ]];
+    let instance_types: BTreeSet<_> = running_counts
+        .keys()
+        .chain(reserved_counts.keys())
+        .collect();
+    for instance_type in instance_types {
+        let running = running_counts.get(instance_type).copied().unwrap_or(0);
+        let reserved = reserved_counts.get(instance_type).copied().unwrap_or(0);
+        let plan = has_plan(instance_type);
+        let gap = if running > reserved && !plan {
+            format!("{} on demand", running - reserved)
+        } else if reserved > running {
+            format!("{} unused", reserved - running)
+        } else {
+            String::new()
+        };
+        rows.push(vec![
+            instance_type.clone(),
+            running.to_string(),
+            reserved.to_string(),
+            if plan { "yes" } else { "" }.into(),
+            gap,
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn ec2_status(instances: Vec<String>) {
+    let instance_ids = if instances.is_empty() {
+        None
+    } else {
+        Some(ec2_resolve_instance_ids(instances)?)
+    };
+    let client = Ec2Client::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "STATE".to_string(),
+        "SYSTEM".to_string(),
+        "INSTANCE".to_string(),
+        "SCHEDULED EVENTS".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_instance_status(DescribeInstanceStatusRequest {
+                instance_ids: instance_ids.clone(),
+                // Include stopped instances too, they can still have
+                // scheduled events
+                include_all_instances: Some(true),
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get instance status")?;
+        for status in output.instance_statuses.unwrap_or_default() {
+            // Past events stay in the list with the description
+            // prefixed by "[Completed]" or "[Canceled]"
+            let events: Vec<String> = status
+                .events
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|event| {
+                    !event
+                        .description
+                        .as_deref()
+                        .unwrap_or_default()
+                        .starts_with('[')
+                })
+                .map(|event| {
+                    format!(
+                        "{} after {}",
+                        event.code.unwrap_or_default(),
+                        event.not_before.unwrap_or_default()
+                    )
+                })
+                .collect();
+            table.push(vec![
+                status.instance_id.unwrap_or_default(),
+                status
+                    .instance_state
+                    .and_then(|state| state.name)
+                    .unwrap_or_default(),
+                status
+                    .system_status
+                    .and_then(|summary| summary.status)
+                    .unwrap_or_default(),
+                status
+                    .instance_status
+                    .and_then(|summary| summary.status)
+                    .unwrap_or_default(),
+                events.join(", "),
+            ]);
+        }
+        table.flush();
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+#[cfg(feature = "glacier")]
+#[throws]
+fn glacier_list_vaults() {
+    let client = GlacierClient::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "ARCHIVES".to_string(),
+        "SIZE".to_string(),
+        "LAST INVENTORY".to_string(),
+    ]);
+    let mut marker = None;
+    loop {
+        let output = client
+            .list_vaults(ListVaultsInput {
+                // "-" is the account of the credentials
+                account_id: "-".into(),
+                marker,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list vaults")?;
+        for vault in output.vault_list.unwrap_or_default() {
+            table.push(vec![
+                vault.vault_name.unwrap_or_default(),
+                vault.number_of_archives.unwrap_or_default().to_string(),
+                format_bytes(vault.size_in_bytes.unwrap_or_default() as f64),
+                vault.last_inventory_date.unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.marker.is_none() {
+            break;
+        }
+        marker = output.marker;
+    }
+    table.finish();
+}
+
+#[cfg(feature = "glacier")]
+#[throws]
+fn glacier_start_inventory(vault_name: String) {
+    let client = GlacierClient::new(Region::default());
+    let output = client
+        .initiate_job(InitiateJobInput {
+            account_id: "-".into(),
+            vault_name,
+            job_parameters: Some(JobParameters {
+                type_: Some("inventory-retrieval".into()),
+                format: Some("JSON".into()),
+                ..Default::default()
+            }),
+        })
+        .sync()
+        .context("failed to start inventory retrieval")?;
+    // Inventory jobs usually take several hours
+    println!("{}", output.job_id.unwrap_or_default());
+}
+
+#[cfg(feature = "glacier")]
+#[throws]
+fn glacier_list_jobs(vault_name: String) {
+    let client = GlacierClient::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "ACTION".to_string(),
+        "STATUS".to_string(),
+        "CREATED".to_string(),
+        "COMPLETED".to_string(),
+    ]);
+    let mut marker = None;
+    loop {
+        let output = client
+            .list_jobs(ListJobsInput {
+                account_id: "-".into(),
+                vault_name: vault_name.clone(),
+                marker,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list jobs")?;
+        for job in output.job_list.unwrap_or_default() {
+            table.push(vec![
+                job.job_id.unwrap_or_default(),
+                job.action.unwrap_or_default(),
+                job.status_code.unwrap_or_default(),
+                job.creation_date.unwrap_or_default(),
+                job.completion_date.unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.marker.is_none() {
+            break;
+        }
+        marker = output.marker;
+    }
+    table.finish();
+}
+
+#[cfg(feature = "glacier")]
+#[throws]
+fn glacier_get_inventory(args: GlacierInventoryGet) {
+    let client = GlacierClient::new(Region::default());
+    let output = client
+        .get_job_output(GetJobOutputInput {
+            account_id: "-".into(),
+            vault_name: args.vault_name,
+            job_id: args.job_id,
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to get inventory")?;
+    let body = output.body.unwrap_or_default();
+    match args.output {
+        Some(path) => fs::write(&path, &body)
+            .with_context(|| format!("failed to write {}", path.display()))?,
+        None => std::io::stdout().write_all(&body)?,
+    }
+}
+
+/// IAM is a global service with its endpoint in us-east-1.
+fn iam_client() -> IamClient {
+    IamClient::new(Region::UsEast1)
+}
+
+/// An IAM identity that policies can be attached to.
+#[derive(Debug)]
+enum IamEntity {
+    Group(String),
+    Role(String),
+    User(String),
+}
+
+#[throws]
+fn parse_iam_entity(s: &str) -> IamEntity {
+    match s.split_once('/') {
+        Some(("group", name)) => IamEntity::Group(name.into()),
+        Some(("role", name)) => IamEntity::Role(name.into()),
+        Some(("user", name)) => IamEntity::User(name.into()),
+        _ => throw!(anyhow!(
+            "expected role/<name>, user/<name>, or group/<name>: {}",
+            s
+        )),
+    }
+}
+
+#[throws]
+fn iam_attach(args: IamAttachment) {
+    let client = iam_client();
+    let policy_arn = args.policy_arn;
+    match args.entity {
+        IamEntity::Group(group_name) => client
+            .attach_group_policy(AttachGroupPolicyRequest {
+                group_name,
+                policy_arn,
+            })
+            .sync()
+            .context("failed to attach policy")?,
+        IamEntity::Role(role_name) => client
+            .attach_role_policy(AttachRolePolicyRequest {
+                role_name,
+                policy_arn,
+            })
+            .sync()
+            .context("failed to attach policy")?,
+        IamEntity::User(user_name) => client
+            .attach_user_policy(AttachUserPolicyRequest {
+                user_name,
+                policy_arn,
+            })
+            .sync()
+            .context("failed to attach policy")?,
+    }
+}
+
+#[throws]
+fn iam_detach(args: IamAttachment) {
+    let client = iam_client();
+    let policy_arn = args.policy_arn;
+    match args.entity {
+        IamEntity::Group(group_name) => client
+            .detach_group_policy(DetachGroupPolicyRequest {
+                group_name,
+                policy_arn,
+            })
+            .sync()
+            .context("failed to detach policy")?,
+        IamEntity::Role(role_name) => client
+            .detach_role_policy(DetachRolePolicyRequest {
+                role_name,
+                policy_arn,
+            })
+            .sync()
+            .context("failed to detach policy")?,
+        IamEntity::User(user_name) => client
+            .detach_user_policy(DetachUserPolicyRequest {
+                user_name,
+                policy_arn,
+            })
+            .sync()
+            .context("failed to detach policy")?,
+    }
+}
+
+#[throws]
+fn iam_entities_for_policy(policy_arn: String) {
+    let client = iam_client();
+    let mut table = TableStream::new(vec!["TYPE".into(), "NAME".into()]);
+    let mut marker = None;
+    loop {
+        let output = client
+            .list_entities_for_policy(ListEntitiesForPolicyRequest {
+                policy_arn: policy_arn.clone(),
+                marker,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list entities for policy")?;
+        for group in output.policy_groups.unwrap_or_default() {
+            table.push(vec![
+                "group".to_string(),
+                group.group_name.unwrap_or_default(),
+            ]);
+        }
+        for role in output.policy_roles.unwrap_or_default() {
+            table.push(vec![
+                "role".to_string(),
+                role.role_name.unwrap_or_default(),
+            ]);
+        }
+        for user in output.policy_users.unwrap_or_default() {
+            table.push(vec![
+                "user".to_string(),
+                user.user_name.unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.is_truncated != Some(true) {
+            break;
+        }
+        marker = output.marker;
+    }
+    table.finish();
+}
+
+/// Get the age in days of a credential report timestamp, or None if
+/// the field isn't a timestamp (such as "N/A").
+fn credential_report_age(value: &str) -> Option<i64> {
+    let time = DateTime::parse_from_rfc3339(value).ok()?;
+    Some((Utc::now() - time.with_timezone(&Utc)).num_days())
+}
+
+#[throws]
+fn iam_credential_report(args: IamCredentialReport) {
+    let client = iam_client();
+    // Generating the report is asynchronous, poll until it's ready. It
+    // normally takes seconds, so give up if it's taking much longer.
+    let deadline = time::Instant::now() + time::Duration::from_secs(5 * 60);
+    loop {
+        let output = client
+            .generate_credential_report()
+            .sync()
+            .context("failed to generate credential report")?;
+        if output.state.as_deref() == Some("COMPLETE") {
+            break;
+        }
+        if time::Instant::now() >= deadline {
+            throw!(anyhow!("timed out waiting for the credential report"));
+        }
+        thread::sleep(time::Duration::from_secs(2));
+    }
+    let output = client
+        .get_credential_report()
+        .sync()
+        .context("failed to get credential report")?;
+    let content = output.content.context("missing report content")?;
+    let content = base64::decode(&content)
+        .context("failed to decode credential report")?;
+    let content = String::from_utf8_lossy(&content);
+
+    let mut lines = content.lines();
+    let header: Vec<_> = lines.next().unwrap_or_default().split(',').collect();
+    let mut rows = vec![vec![
+        "USER".to_string(),
+        "MFA".to_string(),
+        "PASSWORD AGE".to_string(),
+        "KEY 1 AGE".to_string(),
+        "KEY 2 AGE".to_string(),
+        "ISSUES".to_string(),
+    ]];
+    let mut warn = vec![false];
+    for line in lines {
+        let record: HashMap<String, &str> = header
+            .iter()
+            .map(|name| name.to_string())
+            .zip(line.split(','))
+            .collect();
+        let field = |name: &str| record.get(name).copied().unwrap_or_default();
+        let age = |name: &str| credential_report_age(field(name));
+        let format_age = |age: Option<i64>| {
+            age.map(|days| format!("{}d", days)).unwrap_or_default()
+        };
+
+        let mut issues = Vec::new();
+        let password_enabled = field("password_enabled") == "true";
+        if password_enabled && field("mfa_active") != "true" {
+            issues.push("no MFA".to_string());
+        }
+        let password_age = if password_enabled {
+            age("password_last_changed")
+        } else {
+            None
+        };
+        if password_age.is_some_and(|days| days > args.max_age) {
+            issues.push("old password".to_string());
+        }
+        let mut key_ages = Vec::new();
+        for key in 1..=2 {
+            let key_age =
+                if field(&format!("access_key_{}_active", key)) == "true" {
+                    age(&format!("access_key_{}_last_rotated", key))
+                } else {
+                    None
+                };
+            // A key is stale if it hasn't been rotated or used recently
+            let last_used = age(&format!("access_key_{}_last_used_date", key));
+            if key_age.is_some_and(|days| days > args.max_age)
+                || (key_age.is_some()
+                    && last_used.is_none_or(|days| days > args.max_age))
+            {
+                issues.push(format!("stale key {}", key));
+            }
+            key_ages.push(key_age);
+        }
+
+        warn.push(!issues.is_empty());
+        rows.push(vec![
+            field("user").to_string(),
+            field("mfa_active").to_string(),
+            format_age(password_age),
+            format_age(key_ages[0]),
+            format_age(key_ages[1]),
+            issues.join(", "),
+        ]);
+    }
+
+    let color = std::io::stdout().is_terminal();
+    for (line, warn) in format_table(&rows).into_iter().zip(warn) {
+        if warn && color {
+            // Yellow
+            println!("\x1b[33m{}\x1b[0m", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+#[throws]
+fn iam_who_assumed(args: IamWhoAssumed) {
+    let role_arn = if args.role.starts_with("arn:") {
+        args.role
+    } else {
+        format!("arn:aws:iam::{}:role/{}", get_account_id()?, args.role)
+    };
+    let start_time = Utc::now() - parse_duration(&args.since)?;
+    let client = CloudTrailClient::new(parse_region(&args.region)?);
+    let mut table = TableStream::new(vec![
+        "TIME".to_string(),
+        "PRINCIPAL".to_string(),
+        "SOURCE IP".to_string(),
+        "SESSION NAME".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .lookup_events(LookupEventsRequest {
+                lookup_attributes: Some(vec![LookupAttribute {
+                    attribute_key: "ResourceName".into(),
+                    attribute_value: role_arn.clone(),
+                }]),
+                start_time: Some(start_time.timestamp() as f64),
+                next_token,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to look up CloudTrail events")?;
+        for event in output.events.unwrap_or_default() {
+            // Also matches AssumeRoleWithSAML and
+            // AssumeRoleWithWebIdentity
+            if !event
+                .event_name
+                .as_deref()
+                .unwrap_or_default()
+                .starts_with("AssumeRole")
+            {
+                continue;
+            }
+            let detail: serde_json::Value = serde_json::from_str(
+                event.cloud_trail_event.as_deref().unwrap_or("{}"),
+            )
+            .context("failed to parse CloudTrail event")?;
+            let identity = &detail["userIdentity"];
+            let principal = identity["arn"]
+                .as_str()
+                .or_else(|| identity["principalId"].as_str())
+                .or_else(|| identity["type"].as_str())
+                .unwrap_or_default();
+            table.push(vec![
+                format_timestamp_secs(event.event_time.unwrap_or_default()),
+                principal.into(),
+                detail["sourceIPAddress"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                detail["requestParameters"]["roleSessionName"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+/// Ask the user a yes/no question, defaulting to no.
+#[throws]
+fn confirm(prompt: &str) -> bool {
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+#[throws]
+fn iam_role_pages(
+    client: &IamClient,
+    mut each_page: impl FnMut(Vec<Role>) -> Result<()>,
+) {
+    let mut marker = None;
+    loop {
+        let output = client
+            .list_roles(ListRolesRequest {
+                marker,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list roles")?;
+        each_page(output.roles)?;
+
+        // Finish if there are no more results
+        if output.is_truncated != Some(true) {
+            break;
+        }
+        marker = output.marker;
+    }
+}
+
+/// Get when a role was last used. This isn't included in rusoto's
+/// Role type.
+#[throws]
+fn iam_get_role_last_used(role_name: &str) -> Option<DateTime<Utc>> {
+    let output = iam_query("GetRole", &[("RoleName", role_name)])?;
+    output
+        .child("GetRoleResult")
+        .and_then(|result| result.child("Role"))
+        .and_then(|role| role.child("RoleLastUsed"))
+        .and_then(|last_used| last_used.child_text("LastUsedDate"))
+        .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// Service-linked roles are managed by AWS services and have to be
+/// deleted through a separate API.
+fn is_service_linked_role(role: &Role) -> bool {
+    role.path.starts_with("/aws-service-role/")
+}
+
+/// Get the policies and instance profiles that have to be removed from
+/// a role before it can be deleted.
+#[throws]
+fn iam_get_role_dependencies(
+    client: &IamClient,
+    role_name: &str,
+) -> Vec<RoleDependency> {
+    let mut dependencies = Vec::new();
+    let output = client
+        .list_attached_role_policies(ListAttachedRolePoliciesRequest {
+            role_name: role_name.into(),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list attached policies")?;
+    for policy in output.attached_policies.unwrap_or_default() {
+        if let Some(arn) = policy.policy_arn {
+            dependencies.push(RoleDependency::AttachedPolicy(arn));
+        }
+    }
+    let output = client
+        .list_role_policies(ListRolePoliciesRequest {
+            role_name: role_name.into(),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list inline policies")?;
+    for name in output.policy_names {
+        dependencies.push(RoleDependency::InlinePolicy(name));
+    }
+    let output = client
+        .list_instance_profiles_for_role(ListInstanceProfilesForRoleRequest {
+            role_name: role_name.into(),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list instance profiles")?;
+    for profile in output.instance_profiles {
+        dependencies.push(RoleDependency::InstanceProfile(
+            profile.instance_profile_name,
+        ));
+    }
+    dependencies
+}
+
+#[derive(Debug)]
+enum RoleDependency {
+    AttachedPolicy(String),
+    InlinePolicy(String),
+    InstanceProfile(String),
+}
+
+#[throws]
+fn iam_delete_role(client: &IamClient, role: &Role) {
+    let role_name = role.role_name.clone();
+    if is_service_linked_role(role) {
+        let output = client
+            .delete_service_linked_role(DeleteServiceLinkedRoleRequest {
+                role_name,
+            })
+            .sync()
+            .context("failed to delete service-linked role")?;
+        // Deletion fails later if the service is still using the role
+        println!("deletion task: {}", output.deletion_task_id);
+        return;
+    }
+
+    for dependency in iam_get_role_dependencies(client, &role_name)? {
+        println!("  removing {:?}", dependency);
+        match dependency {
+            RoleDependency::AttachedPolicy(policy_arn) => client
+                .detach_role_policy(DetachRolePolicyRequest {
+                    role_name: role_name.clone(),
+                    policy_arn,
+                })
+                .sync()
+                .context("failed to detach policy")?,
+            RoleDependency::InlinePolicy(policy_name) => client
+                .delete_role_policy(DeleteRolePolicyRequest {
+                    role_name: role_name.clone(),
+                    policy_name,
+                })
+                .sync()
+                .context("failed to delete inline policy")?,
+            RoleDependency::InstanceProfile(instance_profile_name) => client
+                .remove_role_from_instance_profile(
+                    RemoveRoleFromInstanceProfileRequest {
+                        role_name: role_name.clone(),
+                        instance_profile_name,
+                    },
+                )
+                .sync()
+                .context("failed to remove role from instance profile")?,
+        }
+    }
+    client
+        .delete_role(DeleteRoleRequest { role_name })
+        .sync()
+        .context("failed to delete role")?;
+}
+
+#[throws]
+fn iam_roles(args: IamRoles) {
+    if args.delete && args.unused.is_none() {
+        throw!(anyhow!("--delete requires --unused"));
+    }
+    let cutoff = match &args.unused {
+        Some(unused) => Some(Utc::now() - parse_duration(unused)?),
+        None => None,
+    };
+    let client = iam_client();
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "LAST USED".to_string(),
+        "CREATED".to_string(),
+        "SERVICE-LINKED".to_string(),
+    ]);
+    let mut candidates = Vec::new();
+    iam_role_pages(&client, |roles| {
+        for role in roles {
+            let last_used = iam_get_role_last_used(&role.role_name)?;
+            if let Some(cutoff) = cutoff {
+                // Roles that have never been used count from their
+                // creation date
+                let last_activity = last_used.or_else(|| {
+                    DateTime::parse_from_rfc3339(&role.create_date)
+                        .ok()
+                        .map(|date| date.with_timezone(&Utc))
+                });
+                if last_activity.is_some_and(|date| date > cutoff) {
+                    continue;
+                }
+            }
+            table.push(vec![
+                role.role_name.clone(),
+                last_used
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "never".into()),
+                role.create_date.get(..10).unwrap_or_default().into(),
+                if is_service_linked_role(&role) {
+                    "yes".into()
+                } else {
+                    "no".into()
+                },
+            ]);
+            candidates.push(role);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+
+    if !args.delete {
+        return;
+    }
+    for role in candidates {
+        println!();
+        if !is_service_linked_role(&role) {
+            for dependency in
+                iam_get_role_dependencies(&client, &role.role_name)?
+            {
+                println!("{}: {:?}", role.role_name, dependency);
+            }
+        }
+        if confirm(&format!("delete role {}?", role.role_name))? {
+            iam_delete_role(&client, &role)?;
+        }
+    }
+}
+
+/// Send an Image Builder request, which isn't available in rusoto
+/// 0.42, and parse the response.
+#[throws]
+fn imagebuilder_request(
+    method: &str,
+    path: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    let output = send_signed_request(
+        method,
+        "imagebuilder",
+        "imagebuilder",
+        path,
+        &[],
+        Some(payload),
+    )?;
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+#[throws]
+fn imagebuilder_pipeline_pages(
+    mut each_page: impl FnMut(Vec<serde_json::Value>) -> Result<()>,
+) {
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut payload = serde_json::json!({});
+        if let Some(token) = &next_token {
+            payload["nextToken"] = token.as_str().into();
+        }
+        let mut output =
+            imagebuilder_request("POST", "/ListImagePipelines", payload)?;
+        each_page(
+            output["imagePipelineList"]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        )?;
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+}
+
+#[throws]
+fn imagebuilder_get_pipelines() -> Vec<serde_json::Value> {
+    let mut pipelines = Vec::new();
+    imagebuilder_pipeline_pages(|mut page| {
+        pipelines.append(&mut page);
+        Ok(())
+    })?;
+    pipelines
+}
+
+/// Get the most recent image built by a pipeline.
+#[throws]
+fn imagebuilder_get_last_image(
+    pipeline_arn: &str,
+) -> Option<serde_json::Value> {
+    let output = imagebuilder_request(
+        "POST",
+        "/ListImagePipelineImages",
+        serde_json::json!({ "imagePipelineArn": pipeline_arn }),
+    )?;
+    output["imageSummaryList"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .max_by_key(|image| image["dateCreated"].as_str().unwrap_or_default())
+        .cloned()
+}
+
+#[throws]
+fn imagebuilder_list_pipelines() {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "STATUS".to_string(),
+        "LAST RUN".to_string(),
+        "LAST IMAGE".to_string(),
+        "NEXT RUN".to_string(),
+    ]);
+    imagebuilder_pipeline_pages(|pipelines| {
+        for pipeline in pipelines {
+            let arn = pipeline["arn"].as_str().unwrap_or_default();
+            let last_image = imagebuilder_get_last_image(arn)?;
+            let last_status = last_image
+                .as_ref()
+                .and_then(|image| image["state"]["status"].as_str())
+                .unwrap_or_default();
+            table.push(vec![
+                pipeline["name"].as_str().unwrap_or_default().into(),
+                pipeline["status"].as_str().unwrap_or_default().into(),
+                pipeline["dateLastRun"].as_str().unwrap_or_default().into(),
+                last_status.into(),
+                pipeline["dateNextRun"].as_str().unwrap_or_default().into(),
+            ]);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+}
+
+#[throws]
+fn imagebuilder_run(pipeline: String) {
+    let pipeline_arn = if pipeline.starts_with("arn:") {
+        pipeline
+    } else {
+        imagebuilder_get_pipelines()?
+            .into_iter()
+            .find(|p| p["name"].as_str() == Some(pipeline.as_str()))
+            .and_then(|p| p["arn"].as_str().map(String::from))
+            .with_context(|| format!("pipeline not found: {}", pipeline))?
+    };
+    let output = imagebuilder_request(
+        "PUT",
+        "/StartImagePipelineExecution",
+        serde_json::json!({
+            "imagePipelineArn": pipeline_arn,
+            // Required for idempotency
+            "clientToken": format!("claws-{}", Utc::now().timestamp_millis()),
+        }),
+    )
+    .context("failed to start pipeline")?;
+    println!(
+        "{}",
+        output["imageBuildVersionArn"].as_str().unwrap_or_default()
+    );
+}
+
+#[cfg(feature = "lightsail")]
+#[throws]
+fn lightsail_list_instances() {
+    let client = LightsailClient::new(Region::default());
+    let mut instances = Vec::new();
+    let mut page_token = None;
+    loop {
+        let output = client
+            .get_instances(GetLightsailInstancesRequest {
+                page_token: page_token.clone(),
+            })
+            .sync()
+            .context("failed to list instances")?;
+        for instance in output.instances.unwrap_or_default() {
+            instances.push(vec![
+                instance.name.unwrap_or_default(),
+                instance
+                    .state
+                    .and_then(|state| state.name)
+                    .unwrap_or_else(|| "unknown".into()),
+                instance.bundle_id.unwrap_or_default(),
+                instance.blueprint_id.unwrap_or_default(),
+            ]);
+        }
+        // Finish if there are no more results
+        if output.next_page_token.is_none() {
+            break;
+        }
+        page_token = output.next_page_token;
+    }
+    instances.sort_unstable();
+
+    let mut rows = vec![vec![
+        "NAME".to_string(),
+        "STATE".to_string(),
+        "BUNDLE".to_string(),
+        "BLUEPRINT".to_string(),
+    ]];
+    rows.extend(instances);
+    print_table(&rows);
+}
+
+#[cfg(feature = "lightsail")]
+#[throws]
+fn lightsail_show_addresses(instance_name: String) {
+    println!("{}:", instance_name);
+    let client = LightsailClient::new(Region::default());
+    let output = client
+        .get_instance(GetLightsailInstanceRequest { instance_name })
+        .sync()
+        .context("failed to get instance details")?;
+    let instance = output.instance.context("missing instance field")?;
+    println!(
+        "  private IP: {}",
+        instance.private_ip_address.unwrap_or_default()
+    );
+    println!(
+        "  public IP: {}",
+        instance.public_ip_address.unwrap_or_default()
+    );
+}
+
+#[cfg(feature = "lightsail")]
+#[throws]
+fn lightsail_start_instance(instance_name: String) {
+    let client = LightsailClient::new(Region::default());
+    client
+        .start_instance(StartLightsailInstanceRequest { instance_name })
+        .sync()
+        .context("failed to start instance")?;
+}
+
+#[cfg(feature = "lightsail")]
+#[throws]
+fn lightsail_stop_instance(instance_name: String) {
+    let client = LightsailClient::new(Region::default());
+    client
+        .stop_instance(StopLightsailInstanceRequest {
+            instance_name,
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to stop instance")?;
+}
+
+/// Parse a "KEY=VALUE" argument.
+#[throws]
+fn parse_key_value(s: &str) -> (String, String) {
+    let (key, value) = s
+        .split_once('=')
+        .with_context(|| format!("expected KEY=VALUE: {}", s))?;
+    (key.to_string(), value.to_string())
+}
+
+/// Whether an SSM command or command invocation status is final.
+fn is_ssm_command_done(status: &str) -> bool {
+    matches!(status, "Success" | "Cancelled" | "Failed" | "TimedOut")
+}
+
+/// Wait for an SSM command to finish, printing the output from each
+/// instance as it completes.
+#[throws]
+fn ssm_wait_for_command(client: &SsmClient, command_id: &str) {
+    let mut finished = HashSet::new();
+    let mut failures = 0;
+    loop {
+        thread::sleep(time::Duration::from_secs(2));
+
+        // Get the overall status before the per-instance status, so
+        // that once the command is done all its invocations are too
+        let output = client
+            .list_commands(ListCommandsRequest {
+                command_id: Some(command_id.into()),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get command status")?;
+        let command_status = output
+            .commands
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .and_then(|command| command.status)
+            .unwrap_or_default();
+
+        let mut next_token = None;
+        loop {
+            let output = client
+                .list_command_invocations(ListCommandInvocationsRequest {
+                    command_id: Some(command_id.into()),
+                    next_token: next_token.clone(),
+                    ..Default::default()
+                })
+                .sync()
+                .context("failed to list command invocations")?;
+            for invocation in output.command_invocations.unwrap_or_default() {
+                let instance_id = invocation.instance_id.unwrap_or_default();
+                let status = invocation.status.unwrap_or_default();
+                if finished.contains(&instance_id)
+                    || !is_ssm_command_done(&status)
+                {
+                    continue;
+                }
+                let result = client
+                    .get_command_invocation(GetCommandInvocationRequest {
+                        command_id: command_id.into(),
+                        instance_id: instance_id.clone(),
+                        ..Default::default()
+                    })
+                    .sync()
+                    .context("failed to get command output")?;
+                println!(
+                    "== {}: {} (exit code {})",
+                    instance_id,
+                    status,
+                    result.response_code.unwrap_or(-1)
+                );
+                print!(
+                    "{}",
+                    result.standard_output_content.unwrap_or_default()
+                );
+                eprint!(
+                    "{}",
+                    result.standard_error_content.unwrap_or_default()
+                );
+                if status != "Success" {
+                    failures += 1;
+                }
+                finished.insert(instance_id);
+            }
+            // Finish if there are no more results
+            if output.next_token.is_none() {
+                break;
+            }
+            next_token = output.next_token;
+        }
+
+        if is_ssm_command_done(&command_status) {
+            break;
+        }
+    }
+    if finished.is_empty() {
+        throw!(anyhow!("no instances matched the targets"));
+    }
+    if failures > 0 {
+        throw!(anyhow!(
+            "command failed on {} of {} instances",
+            failures,
+            finished.len()
+        ));
+    }
+}
+
+#[throws]
+fn ec2_list_amis() {
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .describe_images(DescribeImagesRequest {
+            owners: Some(vec!["self".into()]),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list images")?;
+    let mut images = output.images.unwrap_or_default();
+    // Creation dates are ISO 8601, so they sort chronologically
+    images.sort_unstable_by_key(|image| image.creation_date.clone());
+
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "CREATED".to_string(),
+        "SNAPSHOTS".to_string(),
+    ]];
+    for image in images {
+        let snapshots: Vec<_> = image
+            .block_device_mappings
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|mapping| mapping.ebs.and_then(|ebs| ebs.snapshot_id))
+            .collect();
+        rows.push(vec![
+            image.image_id.unwrap_or_default(),
+            image.name.unwrap_or_default(),
+            image.creation_date.unwrap_or_default(),
+            snapshots.join(", "),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn ec2_create_image(args: Ec2CreateImage) {
+    let instance_id = ec2_resolve_instance_id(args.instance)?;
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .create_image(CreateImageRequest {
+            instance_id,
+            name: args.name,
+            no_reboot: Some(args.no_reboot),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to create image")?;
+    let image_id = output.image_id.context("missing image ID")?;
+    println!("{}", image_id);
+    if !args.wait {
+        return;
+    }
+
+    let deadline = time::Instant::now() + args.timeout.to_std()?;
+    loop {
+        let output = client
+            .describe_images(DescribeImagesRequest {
+                image_ids: Some(vec![image_id.clone()]),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get image")?;
+        let state = output
+            .images
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .and_then(|image| image.state);
+        match state.as_deref() {
+            Some("available") => break,
+            Some("failed") | Some("error") | Some("invalid") => {
+                throw!(anyhow!("image creation failed"))
+            }
+            _ => {}
+        }
+        let now = time::Instant::now();
+        if now >= deadline {
+            throw!(anyhow!(
+                "timed out waiting for {} to be available",
+                image_id
+            ));
+        }
+        thread::sleep(time::Duration::from_secs(10).min(deadline - now));
+    }
+}
+
+#[throws]
+fn ec2_deregister_image(args: Ec2DeregisterImage) {
+    let client = Ec2Client::new(Region::default());
+
+    // Look up the snapshots first, they can't be found once the
+    // image is deregistered
+    let mut snapshot_ids = Vec::new();
+    if args.delete_snapshots {
+        let output = client
+            .describe_images(DescribeImagesRequest {
+                image_ids: Some(vec![args.image_id.clone()]),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get image")?;
+        let image = output
+            .images
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .with_context(|| format!("image not found: {}", args.image_id))?;
+        snapshot_ids = image
+            .block_device_mappings
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|mapping| mapping.ebs.and_then(|ebs| ebs.snapshot_id))
+            .collect();
+    }
+
+    client
+        .deregister_image(DeregisterImageRequest {
+            image_id: args.image_id.clone(),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to deregister image")?;
+    println!("deregistered {}", args.image_id);
+
+    for snapshot_id in snapshot_ids {
+        client
+            .delete_snapshot(DeleteSnapshotRequest {
+                snapshot_id: snapshot_id.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .with_context(|| {
+                format!("failed to delete snapshot {}", snapshot_id)
+            })?;
+        println!("deleted {}", snapshot_id);
+    }
+}
+
+#[throws]
+fn ec2_security_group_pages(
+    mut each_page: impl FnMut(Vec<SecurityGroup>) -> Result<()>,
+) {
+    let client = Ec2Client::new(Region::default());
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_security_groups(DescribeSecurityGroupsRequest {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list security groups")?;
+        each_page(output.security_groups.unwrap_or_default())?;
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+}
+
+/// Expand a permission into one (protocol, ports, peer) row per
+/// source or destination.
+fn ec2_permission_rules(permission: &IpPermission) -> Vec<[String; 3]> {
+    let protocol = match permission.ip_protocol.as_deref() {
+        Some("-1") | None => "all".to_string(),
+        Some(protocol) => protocol.to_string(),
+    };
+    let ports = match (permission.from_port, permission.to_port) {
+        (Some(-1), _) | (None, _) => "all".to_string(),
+        (Some(from), Some(to)) if from != to => format!("{}-{}", from, to),
+        (Some(from), _) => from.to_string(),
+    };
+
+    let mut peers = Vec::new();
+    for range in permission.ip_ranges.iter().flatten() {
+        peers.extend(range.cidr_ip.clone());
+    }
+    for range in permission.ipv_6_ranges.iter().flatten() {
+        peers.extend(range.cidr_ipv_6.clone());
+    }
+    for prefix_list in permission.prefix_list_ids.iter().flatten() {
+        peers.extend(prefix_list.prefix_list_id.clone());
+    }
+    for pair in permission.user_id_group_pairs.iter().flatten() {
+        peers.extend(pair.group_id.clone());
+    }
+
+    peers
+        .into_iter()
+        .map(|peer| [protocol.clone(), ports.clone(), peer])
+        .collect()
+}
+
+#[throws]
+fn ec2_security_groups(args: Ec2SecurityGroups) {
+    if args.rules {
+        let mut table = TableStream::new(vec![
+            "GROUP".to_string(),
+            "DIRECTION".to_string(),
+            "PROTOCOL".to_string(),
+            "PORTS".to_string(),
+            "PEER".to_string(),
+        ]);
+        ec2_security_group_pages(|groups| {
+            for group in groups {
+                let group_id = group.group_id.unwrap_or_default();
+                let directions = [
+                    ("in", group.ip_permissions.unwrap_or_default()),
+                    ("out", group.ip_permissions_egress.unwrap_or_default()),
+                ];
+                for (direction, permissions) in &directions {
+                    for permission in permissions {
+                        for [protocol, ports, peer] in
+                            ec2_permission_rules(permission)
+                        {
+                            table.push(vec![
+                                group_id.clone(),
+                                direction.to_string(),
+                                protocol,
+                                ports,
+                                peer,
+                            ]);
+                        }
+                    }
+                }
+            }
+            table.flush();
+            Ok(())
+        })?;
+        table.finish();
+        return;
+    }
+
+    let count_rules = |permissions: Option<Vec<IpPermission>>| {
+        permissions
+            .unwrap_or_default()
+            .iter()
+            .map(|permission| ec2_permission_rules(permission).len())
+            .sum::<usize>()
+            .to_string()
+    };
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "VPC".to_string(),
+        "INBOUND".to_string(),
+        "OUTBOUND".to_string(),
+    ]);
+    ec2_security_group_pages(|groups| {
+        for group in groups {
+            table.push(vec![
+                group.group_id.unwrap_or_default(),
+                group.group_name.unwrap_or_default(),
+                group.vpc_id.unwrap_or_default(),
+                count_rules(group.ip_permissions),
+                count_rules(group.ip_permissions_egress),
+            ]);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+}
+
+/// Get the ID of a security group identified by name or ID.
+#[throws]
+fn ec2_resolve_security_group_id(group: &str) -> String {
+    if group.starts_with("sg-") {
+        return group.into();
+    }
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .describe_security_groups(DescribeSecurityGroupsRequest {
+            filters: Some(vec![Filter {
+                name: Some("group-name".into()),
+                values: Some(vec![group.into()]),
+            }]),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list security groups")?;
+    let ids: Vec<_> = output
+        .security_groups
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|group| group.group_id)
+        .collect();
+    match ids.as_slice() {
+        [id] => id.clone(),
+        [] => throw!(anyhow!("security group not found: {}", group)),
+        _ => throw!(anyhow!(
+            "multiple security groups named {}: {}",
+            group,
+            ids.join(", ")
+        )),
+    }
+}
+
+/// Get the caller's public IP address as seen from the internet.
+#[throws]
+fn get_my_ip() -> String {
+    let output = run_lookup(
+        "dig",
+        &["+short", "myip.opendns.com", "@resolver1.opendns.com"],
+    )?;
+    output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .context("failed to detect public IP")?
+        .to_string()
+}
+
+#[throws]
+fn ec2_sg_permission(args: &Ec2SgRule) -> IpPermission {
+    let cidr = match (&args.cidr, args.my_ip) {
+        (Some(cidr), false) => cidr.clone(),
+        (None, true) => {
+            let ip = get_my_ip()?;
+            if ip.contains(':') {
+                format!("{}/128", ip)
+            } else {
+                format!("{}/32", ip)
+            }
+        }
+        _ => throw!(anyhow!("exactly one of --cidr or --my-ip is required")),
+    };
+    let parse_port = |port: &str| -> Result<i64> {
+        port.parse()
+            .with_context(|| format!("invalid port: {}", args.port))
+    };
+    let (from_port, to_port) = match args.port.split_once('-') {
+        Some((from, to)) => (parse_port(from)?, parse_port(to)?),
+        None => {
+            let port = parse_port(&args.port)?;
+            (port, port)
+        }
+    };
+    if from_port > to_port {
+        throw!(anyhow!("invalid port range: {}", args.port));
+    }
+    let mut permission = IpPermission {
+        ip_protocol: Some(args.protocol.clone()),
+        from_port: Some(from_port),
+        to_port: Some(to_port),
+        ..Default::default()
+    };
+    if cidr.contains(':') {
+        permission.ipv_6_ranges = Some(vec![Ipv6Range {
+            cidr_ipv_6: Some(cidr),
+            description: args.description.clone(),
+        }]);
+    } else {
+        permission.ip_ranges = Some(vec![IpRange {
+            cidr_ip: Some(cidr),
+            description: args.description.clone(),
+        }]);
+    }
+    permission
+}
+
+fn ec2_describe_permission(permission: &IpPermission) -> String {
+    ec2_permission_rules(permission)
+        .into_iter()
+        .map(|[protocol, ports, peer]| {
+            format!("{} {} from {}", protocol, ports, peer)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[throws]
+fn ec2_sg_allow(args: Ec2SgRule) {
+    let group_id = ec2_resolve_security_group_id(&args.group)?;
+    let permission = ec2_sg_permission(&args)?;
+    let rule = ec2_describe_permission(&permission);
+    let client = Ec2Client::new(Region::default());
+    client
+        .authorize_security_group_ingress(
+            AuthorizeSecurityGroupIngressRequest {
+                group_id: Some(group_id.clone()),
+                ip_permissions: Some(vec![permission]),
+                ..Default::default()
+            },
+        )
+        .sync()
+        .context("failed to authorize ingress")?;
+    println!("allowed {} in {}", rule, group_id);
+}
+
+#[throws]
+fn ec2_sg_revoke(args: Ec2SgRule) {
+    let group_id = ec2_resolve_security_group_id(&args.group)?;
+    let permission = ec2_sg_permission(&args)?;
+    let rule = ec2_describe_permission(&permission);
+    let client = Ec2Client::new(Region::default());
+    client
+        .revoke_security_group_ingress(RevokeSecurityGroupIngressRequest {
+            group_id: Some(group_id.clone()),
+            ip_permissions: Some(vec![permission]),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to revoke ingress")?;
+    println!("revoked {} in {}", rule, group_id);
+}
+
+#[throws]
+fn ec2_list_key_pairs() {
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .describe_key_pairs(DescribeKeyPairsRequest::default())
+        .sync()
+        .context("failed to list key pairs")?;
+    let mut rows = vec![vec!["NAME".to_string(), "FINGERPRINT".to_string()]];
+    for key_pair in output.key_pairs.unwrap_or_default() {
+        rows.push(vec![
+            key_pair.key_name.unwrap_or_default(),
+            key_pair.key_fingerprint.unwrap_or_default(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn ec2_create_key_pair(name: String, output: Option<PathBuf>) {
+    let path = output.unwrap_or_else(|| PathBuf::from(format!("{}.pem", name)));
+    // Open the file first so that an existing key isn't overwritten
+    // and no key pair is created if the file can't be written
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+
+    let client = Ec2Client::new(Region::default());
+    let key_pair = match client
+        .create_key_pair(CreateKeyPairRequest {
+            key_name: name,
+            ..Default::default()
+        })
+        .sync()
+    {
+        Ok(key_pair) => key_pair,
+        Err(err) => {
+            fs::remove_file(&path)?;
+            throw!(anyhow!(err).context("failed to create key pair"));
+        }
+    };
+    let material = key_pair.key_material.context("missing key material")?;
+    file.write_all(material.as_bytes())?;
+    println!(
+        "wrote {} ({})",
+        path.display(),
+        key_pair.key_fingerprint.unwrap_or_default()
+    );
+}
+
+#[throws]
+fn ec2_import_key_pair(name: String, public_key: PathBuf) {
+    let material = fs::read(&public_key)
+        .with_context(|| format!("failed to read {}", public_key.display()))?;
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .import_key_pair(ImportKeyPairRequest {
+            key_name: name,
+            // Rusoto sends the material as-is, but the API expects it
+            // to be base64 encoded
+            public_key_material: base64::encode(material).into_bytes().into(),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to import key pair")?;
+    println!(
+        "imported {} ({})",
+        output.key_name.unwrap_or_default(),
+        output.key_fingerprint.unwrap_or_default()
+    );
+}
+
+fn ec2_volume_row(volume: Volume) -> Vec<String> {
+    let attachments = volume.attachments.unwrap_or_default();
+    let instances: Vec<_> = attachments
+        .iter()
+        .filter_map(|attachment| attachment.instance_id.clone())
+        .collect();
+    let devices: Vec<_> = attachments
+        .iter()
+        .filter_map(|attachment| attachment.device.clone())
+        .collect();
+    vec![
+        volume.volume_id.unwrap_or_default(),
+        format!("{} GiB", volume.size.unwrap_or_default()),
+        volume.volume_type.unwrap_or_default(),
+        volume.state.unwrap_or_default(),
+        instances.join(", "),
+        devices.join(", "),
+    ]
+}
+
+#[throws]
+fn ec2_volumes(sort_by_size: bool) {
+    let client = Ec2Client::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "SIZE".to_string(),
+        "TYPE".to_string(),
+        "STATE".to_string(),
+        "INSTANCE".to_string(),
+        "DEVICE".to_string(),
+    ]);
+    // Sorting by size needs every volume before anything is printed
+    let mut sorted = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_volumes(DescribeVolumesRequest {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list volumes")?;
+        let volumes = output.volumes.unwrap_or_default();
+        if sort_by_size {
+            sorted.extend(volumes);
+        } else {
+            for volume in volumes {
+                table.push(ec2_volume_row(volume));
+            }
+            table.flush();
+        }
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    sorted.sort_unstable_by_key(|volume| {
+        std::cmp::Reverse(volume.size.unwrap_or_default())
+    });
+    for volume in sorted {
+        table.push(ec2_volume_row(volume));
+    }
+    table.finish();
+}
+
+#[throws]
+fn ec2_list_vpcs() {
+    let client = Ec2Client::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "CIDR".to_string(),
+        "DEFAULT".to_string(),
+        "STATE".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_vpcs(DescribeVpcsRequest {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list VPCs")?;
+        for vpc in output.vpcs.unwrap_or_default() {
+            table.push(vec![
+                vpc.vpc_id.unwrap_or_default(),
+                get_name_tag(&vpc.tags).unwrap_or_default(),
+                vpc.cidr_block.unwrap_or_default(),
+                if vpc.is_default == Some(true) {
+                    "yes"
+                } else {
+                    ""
+                }
+                .into(),
+                vpc.state.unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+#[throws]
+fn ec2_list_subnets() {
+    let client = Ec2Client::new(Region::default());
+    let mut subnets = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_subnets(DescribeSubnetsRequest {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list subnets")?;
+        subnets.extend(output.subnets.unwrap_or_default());
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+
+    let mut route_tables = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_route_tables(DescribeRouteTablesRequest {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list route tables")?;
+        route_tables.extend(output.route_tables.unwrap_or_default());
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+
+    // A subnet is public if its route table has a route to an
+    // internet gateway. Subnets without an explicit association use
+    // their VPC's main route table.
+    let mut public_by_subnet = HashMap::new();
+    let mut public_by_vpc = HashMap::new();
+    for table in &route_tables {
+        let public = table.routes.iter().flatten().any(|route| {
+            route
+                .gateway_id
+                .as_deref()
+                .is_some_and(|id| id.starts_with("igw-"))
+        });
+        for association in table.associations.iter().flatten() {
+            if let Some(subnet_id) = &association.subnet_id {
+                public_by_subnet.insert(subnet_id.clone(), public);
+            }
+            if association.main == Some(true) {
+                if let Some(vpc_id) = &table.vpc_id {
+                    public_by_vpc.insert(vpc_id.clone(), public);
+                }
+            }
+        }
+    }
+
+    subnets.sort_unstable_by_key(|subnet| {
+        (subnet.vpc_id.clone(), subnet.availability_zone.clone())
+    });
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "VPC".to_string(),
+        "CIDR".to_string(),
+        "AZ".to_string(),
+        "FREE IPS".to_string(),
+        "TYPE".to_string(),
+    ]];
+    for subnet in subnets {
+        let subnet_id = subnet.subnet_id.unwrap_or_default();
+        let vpc_id = subnet.vpc_id.unwrap_or_default();
+        let public = public_by_subnet
+            .get(&subnet_id)
+            .or_else(|| public_by_vpc.get(&vpc_id))
+            .copied()
+            .unwrap_or_default();
+        rows.push(vec![
+            subnet_id,
+            get_name_tag(&subnet.tags).unwrap_or_default(),
+            vpc_id,
+            subnet.cidr_block.unwrap_or_default(),
+            subnet.availability_zone.unwrap_or_default(),
+            subnet
+                .available_ip_address_count
+                .unwrap_or_default()
+                .to_string(),
+            if public { "public" } else { "private" }.into(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn ec2_list_enis(args: Ec2Enis) {
+    let filters = if let Some(instance) = args.instance {
+        Some(vec![Filter {
+            name: Some("attachment.instance-id".into()),
+            values: Some(vec![ec2_resolve_instance_id(instance)?]),
+        }])
+    } else {
+        None
+    };
+
+    let client = Ec2Client::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "ATTACHMENT".to_string(),
+        "PRIVATE IPS".to_string(),
+        "SECURITY GROUPS".to_string(),
+        "DESCRIPTION".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_network_interfaces(DescribeNetworkInterfacesRequest {
+                filters: filters.clone(),
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list network interfaces")?;
+        for interface in output.network_interfaces.unwrap_or_default() {
+            // Unattached interfaces are shown as "available" so that
+            // orphans stand out
+            let attachment =
+                match interface.attachment.and_then(|a| a.instance_id) {
+                    Some(instance_id) => instance_id,
+                    None => interface.status.unwrap_or_default(),
+                };
+            let ips: Vec<String> = interface
+                .private_ip_addresses
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|ip| ip.private_ip_address)
+                .collect();
+            let groups: Vec<String> = interface
+                .groups
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|group| group.group_id)
+                .collect();
+            table.push(vec![
+                interface.network_interface_id.unwrap_or_default(),
+                attachment,
+                ips.join(","),
+                groups.join(","),
+                interface.description.unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+#[throws]
+fn ec2_list_azs(args: Ec2Azs) {
+    let client = Ec2Client::new(parse_region(&args.region)?);
+    let output = client
+        .describe_availability_zones(DescribeAvailabilityZonesRequest {
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list availability zones")?;
+
+    let mut rows = vec![vec![
+        "NAME".to_string(),
+        "ID".to_string(),
+        "STATE".to_string(),
+        "MESSAGES".to_string(),
+    ]];
+    for zone in output.availability_zones.unwrap_or_default() {
+        let messages: Vec<_> = zone
+            .messages
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|message| message.message)
+            .collect();
+        rows.push(vec![
+            zone.zone_name.unwrap_or_default(),
+            zone.zone_id.unwrap_or_default(),
+            zone.state.unwrap_or_default(),
+            messages.join("; "),
+        ]);
+    }
+    print_table(&rows);
+
+    println!(
+        "\nnote: zone names are mapped to physical zones independently \
+         for each account, so use zone IDs when coordinating subnets \
+         across accounts."
+    );
+}
+
+#[throws]
+fn ec2_reachability(args: Ec2Reachability) {
+    let (dest_ip, dest_port) = args
+        .destination
+        .rsplit_once(':')
+        .context("destination must be in the form <ip>:<port>")?;
+    let source = ec2_resolve_instance_id(args.source_instance)?;
+    let path = ec2_query(
+        "CreateNetworkInsightsPath",
+        &[
+            ("Source", &source),
+            ("DestinationIp", dest_ip),
+            ("DestinationPort", dest_port),
+            ("Protocol", "tcp"),
+        ],
+    )?;
+    let path_id = path
+        .child("networkInsightsPath")
+        .and_then(|path| path.child_text("networkInsightsPathId"))
+        .context("missing path ID")?
+        .to_string();
+
+    let result = ec2_analyze_path(&path_id);
+
+    // Clean up the path regardless of whether the analysis worked
+    if let Err(err) = ec2_query(
+        "DeleteNetworkInsightsPath",
+        &[("NetworkInsightsPathId", &path_id)],
+    ) {
+        eprintln!("failed to delete path {}: {:#}", path_id, err);
+    }
+    result?;
+}
+
+/// Analyze a network insights path, print the result, and delete the
+/// analysis.
+#[throws]
+fn ec2_analyze_path(path_id: &str) {
+    let analysis = ec2_query(
+        "StartNetworkInsightsAnalysis",
+        &[("NetworkInsightsPathId", path_id)],
+    )?;
+    let analysis_id = analysis
+        .child("networkInsightsAnalysis")
+        .and_then(|analysis| analysis.child_text("networkInsightsAnalysisId"))
+        .context("missing analysis ID")?
+        .to_string();
+
+    println!("analyzing {}...", analysis_id);
+    let analysis = loop {
+        let output = ec2_query(
+            "DescribeNetworkInsightsAnalyses",
+            &[("NetworkInsightsAnalysisId.1", &analysis_id)],
+        )?;
+        let analysis = output
+            .children
+            .into_iter()
+            .find(|child| child.name == "networkInsightsAnalysisSet")
+            .and_then(|set| set.children.into_iter().next())
+            .context("analysis not found")?;
+        if analysis.child_text("status") != Some("running") {
+            break analysis;
+        }
+        thread::sleep(time::Duration::from_secs(2));
+    };
+
+    let status = analysis.child_text("status").unwrap_or_default();
+    if status != "succeeded" {
+        throw!(anyhow!(
+            "analysis {}: {}",
+            status,
+            analysis.child_text("statusMessage").unwrap_or_default()
+        ));
+    }
+    if analysis.child_text("networkPathFound") == Some("true") {
+        println!("reachable");
+    } else {
+        println!("not reachable");
+        for explanation in analysis.items("explanationSet") {
+            // Show the IDs of the components involved in the hop
+            let components: Vec<_> = explanation
+                .children
+                .iter()
+                .filter_map(|child| child.child_text("id"))
+                .collect();
+            println!(
+                "  {}: {}",
+                explanation
+                    .child_text("explanationCode")
+                    .unwrap_or_default(),
+                components.join(", ")
+            );
+        }
+    }
+
+    ec2_query(
+        "DeleteNetworkInsightsAnalysis",
+        &[("NetworkInsightsAnalysisId", &analysis_id)],
+    )?;
+}
+
+#[throws]
+fn ec2_flow_logs_enable(args: Ec2FlowLogsEnable) {
+    let resource_type = if args.resource_id.starts_with("vpc-") {
+        "VPC"
+    } else if args.resource_id.starts_with("subnet-") {
+        "Subnet"
+    } else if args.resource_id.starts_with("eni-") {
+        "NetworkInterface"
+    } else {
+        throw!(anyhow!(
+            "unsupported resource (expected a VPC, subnet, or ENI ID): {}",
+            args.resource_id
+        ));
+    };
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .create_flow_logs(CreateFlowLogsRequest {
+            resource_ids: vec![args.resource_id.clone()],
+            resource_type: resource_type.into(),
+            traffic_type: args.traffic_type,
+            log_destination_type: Some("cloud-watch-logs".into()),
+            log_group_name: Some(args.to_cw),
+            deliver_logs_permission_arn: Some(args.role_arn),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to create flow log")?;
+    if let Some(item) = output.unsuccessful.unwrap_or_default().first() {
+        let message = item
+            .error
+            .as_ref()
+            .and_then(|error| error.message.clone())
+            .unwrap_or_default();
+        throw!(anyhow!("failed to create flow log: {}", message));
+    }
+    for id in output.flow_log_ids.unwrap_or_default() {
+        println!("{}", id);
+    }
+}
+
+/// Fields of the default flow log record format.
+const FLOW_LOG_FIELDS: &[&str] = &[
+    "version",
+    "account-id",
+    "interface-id",
+    "srcaddr",
+    "dstaddr",
+    "srcport",
+    "dstport",
+    "protocol",
+    "packets",
+    "bytes",
+    "start",
+    "end",
+    "action",
+    "log-status",
+];
+
+/// Convert a filter like "dstport=443 action=REJECT" into a
+/// CloudWatch Logs filter pattern for the default flow log format.
+#[throws]
+fn flow_log_filter_pattern(filter: &str) -> String {
+    let mut conditions = HashMap::new();
+    for condition in filter.split_whitespace() {
+        let (field, value) = parse_key_value(condition)?;
+        if !FLOW_LOG_FIELDS.contains(&field.as_str()) {
+            throw!(anyhow!("unknown flow log field: {}", field));
+        }
+        conditions.insert(field, value);
+    }
+    let fields: Vec<_> = FLOW_LOG_FIELDS
+        .iter()
+        .map(|field| {
+            // Field names in patterns can't contain dashes
+            let name = field.replace('-', "_");
+            match conditions.get(*field) {
+                Some(value) if value.parse::<f64>().is_ok() => {
+                    format!("{}={}", name, value)
+                }
+                Some(value) => format!("{}=\"{}\"", name, value),
+                None => name,
+            }
+        })
+        .collect();
+    format!("[{}]", fields.join(", "))
+}
+
+#[throws]
+fn ec2_flow_logs_query(args: Ec2FlowLogsQuery) {
+    let filter_pattern = match &args.filter {
+        Some(filter) => Some(flow_log_filter_pattern(filter)?),
+        None => None,
+    };
+    tail_log_group(
+        &args.log_group_name,
+        &args.since,
+        filter_pattern,
+        args.follow,
+        format_log_event,
+    )?;
+}
+
+#[throws]
+fn ec2_list_transit_gateways() {
+    let client = Ec2Client::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "STATE".to_string(),
+        "OWNER".to_string(),
+        "DESCRIPTION".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_transit_gateways(DescribeTransitGatewaysRequest {
+                next_token,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list transit gateways")?;
+        for tgw in output.transit_gateways.unwrap_or_default() {
+            table.push(vec![
+                tgw.transit_gateway_id.unwrap_or_default(),
+                get_name_tag(&tgw.tags).unwrap_or_default(),
+                tgw.state.unwrap_or_default(),
+                tgw.owner_id.unwrap_or_default(),
+                tgw.description.unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+/// Get the route tables an attachment propagates routes to, along
+/// with the propagation state.
+#[throws]
+fn get_attachment_propagations(
+    client: &Ec2Client,
+    attachment_id: &str,
+) -> Vec<String> {
+    let mut propagations = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .get_transit_gateway_attachment_propagations(
+                GetTransitGatewayAttachmentPropagationsRequest {
+                    transit_gateway_attachment_id: attachment_id.into(),
+                    next_token,
+                    ..Default::default()
+                },
+            )
+            .sync()
+            .context("failed to get attachment propagations")?;
+        for propagation in output
+            .transit_gateway_attachment_propagations
+            .unwrap_or_default()
+        {
+            propagations.push(format!(
+                "{} ({})",
+                propagation
+                    .transit_gateway_route_table_id
+                    .unwrap_or_default(),
+                propagation.state.unwrap_or_default()
+            ));
+        }
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    propagations
+}
+
+#[throws]
+fn ec2_list_transit_gateway_attachments(transit_gateway_id: String) {
+    let client = Ec2Client::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "TYPE".to_string(),
+        "RESOURCE".to_string(),
+        "STATE".to_string(),
+        "ASSOCIATION".to_string(),
+        "PROPAGATION".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_transit_gateway_attachments(
+                DescribeTransitGatewayAttachmentsRequest {
+                    filters: Some(vec![Filter {
+                        name: Some("transit-gateway-id".into()),
+                        values: Some(vec![transit_gateway_id.clone()]),
+                    }]),
+                    next_token,
+                    ..Default::default()
+                },
+            )
+            .sync()
+            .context("failed to list transit gateway attachments")?;
+        for attachment in output.transit_gateway_attachments.unwrap_or_default()
+        {
+            let id =
+                attachment.transit_gateway_attachment_id.unwrap_or_default();
+            let association = attachment
+                .association
+                .map(|association| {
+                    format!(
+                        "{} ({})",
+                        association
+                            .transit_gateway_route_table_id
+                            .unwrap_or_default(),
+                        association.state.unwrap_or_default()
+                    )
+                })
+                .unwrap_or_default();
+            let propagations = get_attachment_propagations(&client, &id)?;
+            table.push(vec![
+                id,
+                attachment.resource_type.unwrap_or_default(),
+                attachment.resource_id.unwrap_or_default(),
+                attachment.state.unwrap_or_default(),
+                association,
+                propagations.join(", "),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+fn format_peering_vpc(info: Option<VpcPeeringConnectionVpcInfo>) -> String {
+    let info = info.unwrap_or_default();
+    format!(
+        "{} {} {} {}",
+        info.vpc_id.unwrap_or_default(),
+        info.cidr_block.unwrap_or_default(),
+        info.owner_id.unwrap_or_default(),
+        info.region.unwrap_or_default()
+    )
+}
+
+#[throws]
+fn ec2_list_peering_connections() {
+    let client = Ec2Client::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "STATUS".to_string(),
+        "REQUESTER".to_string(),
+        "ACCEPTER".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_vpc_peering_connections(
+                DescribeVpcPeeringConnectionsRequest {
+                    next_token,
+                    ..Default::default()
+                },
+            )
+            .sync()
+            .context("failed to list peering connections")?;
+        for peering in output.vpc_peering_connections.unwrap_or_default() {
+            table.push(vec![
+                peering.vpc_peering_connection_id.unwrap_or_default(),
+                get_name_tag(&peering.tags).unwrap_or_default(),
+                peering
+                    .status
+                    .and_then(|status| status.code)
+                    .unwrap_or_default(),
+                format_peering_vpc(peering.requester_vpc_info),
+                format_peering_vpc(peering.accepter_vpc_info),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+#[throws]
+fn ec2_list_vpc_endpoints(vpc_id: Option<String>) {
+    let client = Ec2Client::new(Region::default());
+    let filters = vpc_id.map(|vpc_id| {
+        vec![Filter {
+            name: Some("vpc-id".into()),
+            values: Some(vec![vpc_id]),
+        }]
+    });
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "TYPE".to_string(),
+        "VPC".to_string(),
+        "STATE".to_string(),
+        "SERVICE".to_string(),
+        "DNS".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_vpc_endpoints(DescribeVpcEndpointsRequest {
+                filters: filters.clone(),
+                next_token,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list VPC endpoints")?;
+        for endpoint in output.vpc_endpoints.unwrap_or_default() {
+            let dns_names: Vec<_> = endpoint
+                .dns_entries
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|entry| entry.dns_name)
+                .collect();
+            table.push(vec![
+                endpoint.vpc_endpoint_id.unwrap_or_default(),
+                endpoint.vpc_endpoint_type.unwrap_or_default(),
+                endpoint.vpc_id.unwrap_or_default(),
+                endpoint.state.unwrap_or_default(),
+                endpoint.service_name.unwrap_or_default(),
+                dns_names.join(", "),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+#[throws]
+fn ec2_create_vpc_endpoint(args: Ec2EndpointsCreate) {
+    let region = Region::default();
+    // Allow short service names such as "s3"
+    let service_name = if args.service.contains('.') {
+        args.service
+    } else {
+        format!("com.amazonaws.{}.{}", region.name(), args.service)
+    };
+    // Interface endpoints live in subnets, gateway endpoints are
+    // attached to route tables
+    let endpoint_type = if args.subnets.is_empty() {
+        "Gateway"
+    } else {
+        "Interface"
+    };
+    let client = Ec2Client::new(region);
+    let output = client
+        .create_vpc_endpoint(CreateVpcEndpointRequest {
+            vpc_id: args.vpc_id,
+            service_name,
+            vpc_endpoint_type: Some(endpoint_type.into()),
+            subnet_ids: Some(args.subnets).filter(|ids| !ids.is_empty()),
+            route_table_ids: Some(args.route_tables)
+                .filter(|ids| !ids.is_empty()),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to create VPC endpoint")?;
+    let endpoint = output.vpc_endpoint.context("missing endpoint")?;
+    println!("{}", endpoint.vpc_endpoint_id.unwrap_or_default());
+    for entry in endpoint.dns_entries.unwrap_or_default() {
+        println!("  {}", entry.dns_name.unwrap_or_default());
+    }
+}
+
+#[throws]
+fn ec2_list_vpn_connections() {
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .describe_vpn_connections(DescribeVpnConnectionsRequest {
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list VPN connections")?;
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "STATE".to_string(),
+        "GATEWAY".to_string(),
+        "TUNNELS".to_string(),
+    ]];
+    for vpn in output.vpn_connections.unwrap_or_default() {
+        let tunnels: Vec<_> = vpn
+            .vgw_telemetry
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tunnel| tunnel.status.unwrap_or_default())
+            .collect();
+        rows.push(vec![
+            vpn.vpn_connection_id.unwrap_or_default(),
+            get_name_tag(&vpn.tags).unwrap_or_default(),
+            vpn.state.unwrap_or_default(),
+            vpn.transit_gateway_id
+                .or(vpn.vpn_gateway_id)
+                .unwrap_or_default(),
+            tunnels.join("/"),
+        ]);
+    }
+    print_table(&rows);
+
+    let mut table = TableStream::new(vec![
+        "CLIENT VPN ENDPOINT".to_string(),
+        "NAME".to_string(),
+        "STATUS".to_string(),
+        "CLIENT CIDR".to_string(),
+        "DNS".to_string(),
+    ]);
+    let mut any_endpoints = false;
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_client_vpn_endpoints(DescribeClientVpnEndpointsRequest {
+                next_token,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list client VPN endpoints")?;
+        let endpoints = output.client_vpn_endpoints.unwrap_or_default();
+        // Only show the endpoints table if there are any
+        if !endpoints.is_empty() && !any_endpoints {
+            any_endpoints = true;
+            println!();
+        }
+        for endpoint in endpoints {
+            table.push(vec![
+                endpoint.client_vpn_endpoint_id.unwrap_or_default(),
+                get_name_tag(&endpoint.tags).unwrap_or_default(),
+                endpoint
+                    .status
+                    .and_then(|status| status.code)
+                    .unwrap_or_default(),
+                endpoint.client_cidr_block.unwrap_or_default(),
+                endpoint.dns_name.unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+}
+
+#[throws]
+fn ec2_vpn_telemetry(vpn_connection_id: String) {
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .describe_vpn_connections(DescribeVpnConnectionsRequest {
+            vpn_connection_ids: Some(vec![vpn_connection_id.clone()]),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to get VPN connection")?;
+    let vpn = output
+        .vpn_connections
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .with_context(|| {
+            format!("VPN connection not found: {}", vpn_connection_id)
+        })?;
+    let mut rows = vec![vec![
+        "OUTSIDE IP".to_string(),
+        "STATUS".to_string(),
+        "LAST CHANGE".to_string(),
+        "ROUTES".to_string(),
+        "MESSAGE".to_string(),
+    ]];
+    for tunnel in vpn.vgw_telemetry.unwrap_or_default() {
+        rows.push(vec![
+            tunnel.outside_ip_address.unwrap_or_default(),
+            tunnel.status.unwrap_or_default(),
+            tunnel.last_status_change.unwrap_or_default(),
+            tunnel.accepted_route_count.unwrap_or_default().to_string(),
+            tunnel.status_message.unwrap_or_default(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn ec2_modify_volume(args: Ec2VolumesModify) {
+    let size = args.size.map(|size| size.to_string());
+    let iops = args.iops.map(|iops| iops.to_string());
+    let throughput = args.throughput.map(|throughput| throughput.to_string());
+    let mut params = vec![("VolumeId", args.volume_id.as_str())];
+    if let Some(size) = &size {
+        params.push(("Size", size));
+    }
+    if let Some(volume_type) = &args.volume_type {
+        params.push(("VolumeType", volume_type));
+    }
+    if let Some(iops) = &iops {
+        params.push(("Iops", iops));
+    }
+    // Throughput isn't supported by rusoto_ec2, so the request is
+    // sent directly
+    if let Some(throughput) = &throughput {
+        params.push(("Throughput", throughput));
+    }
+    if params.len() == 1 {
+        throw!(anyhow!(
+            "nothing to modify (use --size, --type, --iops, or --throughput)"
+        ));
+    }
+    let output = ec2_query("ModifyVolume", &params)?;
+    let state = output
+        .child("volumeModification")
+        .and_then(|modification| modification.child_text("modificationState"))
+        .unwrap_or_default();
+    println!("{}: {}", args.volume_id, state);
+
+    if args.wait {
+        let client = Ec2Client::new(Region::default());
+        let deadline = time::Instant::now() + args.timeout.to_std()?;
+        loop {
+            let now = time::Instant::now();
+            if now >= deadline {
+                throw!(anyhow!(
+                    "timed out waiting for the modification of {}",
+                    args.volume_id
+                ));
+            }
+            thread::sleep(time::Duration::from_secs(10).min(deadline - now));
+            let output = client
+                .describe_volumes_modifications(
+                    DescribeVolumesModificationsRequest {
+                        volume_ids: Some(vec![args.volume_id.clone()]),
+                        ..Default::default()
+                    },
+                )
+                .sync()
+                .context("failed to get volume modification")?;
+            let modification = output
+                .volumes_modifications
+                .unwrap_or_default()
+                .into_iter()
+                .next()
+                .context("volume modification not found")?;
+            let state = modification.modification_state.unwrap_or_default();
+            println!(
+                "{}: {} ({}%)",
+                args.volume_id,
+                state,
+                modification.progress.unwrap_or_default()
+            );
+            if state == "completed" {
+                break;
+            }
+            if state == "failed" {
+                throw!(anyhow!(
+                    "volume modification failed: {}",
+                    modification.status_message.unwrap_or_default()
+                ));
+            }
+        }
+    }
+
+    if size.is_some() {
+        println!(
+            "note: grow the partition and filesystem on the instance to use \
+             the new size (for example with growpart and resize2fs or \
+             xfs_growfs)"
+        );
+    }
+}
+
+#[throws]
+fn ec2_attach_volume(args: Ec2VolumesAttach) {
+    let instance_id = ec2_resolve_instance_id(args.instance)?;
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .attach_volume(AttachVolumeRequest {
+            device: args.device,
+            instance_id,
+            volume_id: args.volume_id.clone(),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to attach volume")?;
+    println!("{}: {}", args.volume_id, output.state.unwrap_or_default());
+}
+
+#[throws]
+fn ec2_detach_volume(volume_id: String, force: bool) {
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .detach_volume(DetachVolumeRequest {
+            volume_id: volume_id.clone(),
+            force: Some(force),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to detach volume")?;
+    println!("{}: {}", volume_id, output.state.unwrap_or_default());
+}
+
+#[throws]
+fn ec2_list_snapshots() {
+    let client = Ec2Client::new(Region::default());
+    let mut snapshots = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_snapshots(DescribeSnapshotsRequest {
+                owner_ids: Some(vec!["self".into()]),
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list snapshots")?;
+        snapshots.extend(output.snapshots.unwrap_or_default());
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    // Start times are ISO 8601, so they sort chronologically
+    snapshots.sort_unstable_by_key(|snapshot| snapshot.start_time.clone());
+
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "SIZE".to_string(),
+        "AGE".to_string(),
+        "STATE".to_string(),
+        "VOLUME".to_string(),
+        "DESCRIPTION".to_string(),
+    ]];
+    for snapshot in snapshots {
+        let age = snapshot
+            .start_time
+            .as_deref()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| {
+                format!(
+                    "{}d",
+                    (Utc::now() - time.with_timezone(&Utc)).num_days()
+                )
+            })
+            .unwrap_or_default();
+        rows.push(vec![
+            snapshot.snapshot_id.unwrap_or_default(),
+            format!("{} GiB", snapshot.volume_size.unwrap_or_default()),
+            age,
+            snapshot.state.unwrap_or_default(),
+            snapshot.volume_id.unwrap_or_default(),
+            snapshot.description.unwrap_or_default(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn ec2_create_snapshot(volume_id: String, description: Option<String>) {
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .create_snapshot(CreateSnapshotRequest {
+            volume_id,
+            description,
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to create snapshot")?;
+    println!("{}", output.snapshot_id.unwrap_or_default());
+}
+
+#[throws]
+fn ec2_delete_snapshot(snapshot_id: String) {
+    let client = Ec2Client::new(Region::default());
+    client
+        .delete_snapshot(DeleteSnapshotRequest {
+            snapshot_id: snapshot_id.clone(),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to delete snapshot")?;
+    println!("deleted {}", snapshot_id);
+}
+
+#[throws]
+fn ec2_copy_snapshot(args: Ec2SnapshotsCopy) {
+    let source_region = Region::default();
+    // The copy is requested from the destination region
+    let client = Ec2Client::new(parse_region(&Some(args.to_region))?);
+    let output = client
+        .copy_snapshot(CopySnapshotRequest {
+            source_region: source_region.name().into(),
+            source_snapshot_id: args.snapshot_id.clone(),
+            description: Some(format!(
+                "Copy of {} from {}",
+                args.snapshot_id,
+                source_region.name()
+            )),
+            encrypted: Some(args.encrypt || args.kms_key.is_some()),
+            kms_key_id: args.kms_key,
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to copy snapshot")?;
+    println!("{}", output.snapshot_id.unwrap_or_default());
+}
+
+#[throws]
+fn ec2_enable_fast_snapshot_restore(args: Ec2SnapshotsEnableFsr) {
+    let zone_keys: Vec<_> = (1..=args.azs.len())
+        .map(|i| format!("AvailabilityZone.{}", i))
+        .collect();
+    let mut params = vec![("SourceSnapshotId.1", args.snapshot_id.as_str())];
+    for (key, zone) in zone_keys.iter().zip(&args.azs) {
+        params.push((key, zone));
+    }
+    // Fast snapshot restore isn't supported by rusoto_ec2, so the
+    // request is sent directly
+    let output = ec2_query("EnableFastSnapshotRestores", &params)?;
+    for item in output.items("successful") {
+        println!(
+            "{}: {}",
+            item.child_text("availabilityZone").unwrap_or_default(),
+            item.child_text("state").unwrap_or_default()
+        );
+    }
+    let mut any_errors = false;
+    for item in output.items("unsuccessful") {
+        for error in item.items("fastSnapshotRestoreStateErrorSet") {
+            let error = error.child("error");
+            eprintln!(
+                "{}: {}",
+                error
+                    .and_then(|error| error.child_text("code"))
+                    .unwrap_or_default(),
+                error
+                    .and_then(|error| error.child_text("message"))
+                    .unwrap_or_default()
+            );
+            any_errors = true;
+        }
+    }
+    if any_errors {
+        throw!(anyhow!("failed to enable fast snapshot restore"));
+    }
+}
+
+#[throws]
+fn ec2_dlm_list() {
+    // Data Lifecycle Manager isn't available in rusoto 0.42
+    let output =
+        send_signed_request("GET", "dlm", "dlm", "/policies", &[], None)?;
+    let output: serde_json::Value = serde_json::from_slice(&output)
+        .context("failed to parse lifecycle policies")?;
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "STATE".to_string(),
+        "TYPE".to_string(),
+        "DESCRIPTION".to_string(),
+    ]];
+    for policy in output["Policies"].as_array().into_iter().flatten() {
+        rows.push(vec![
+            policy["PolicyId"].as_str().unwrap_or_default().into(),
+            policy["State"].as_str().unwrap_or_default().into(),
+            policy["PolicyType"].as_str().unwrap_or_default().into(),
+            policy["Description"].as_str().unwrap_or_default().into(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+/// Convert a schedule such as "daily", "weekly", or "12h" into a
+/// lifecycle policy create rule. Snapshots are started at 03:00 UTC.
+#[throws]
+fn dlm_create_rule(schedule: &str) -> serde_json::Value {
+    match schedule {
+        "daily" => serde_json::json!({
+            "Interval": 24,
+            "IntervalUnit": "HOURS",
+            "Times": ["03:00"],
+        }),
+        "weekly" => serde_json::json!({
+            "CronExpression": "cron(0 3 ? * SUN *)",
+        }),
+        _ => {
+            let hours: u32 = schedule
+                .strip_suffix('h')
+                .and_then(|hours| hours.parse().ok())
+                .with_context(|| format!("invalid schedule: {}", schedule))?;
+            serde_json::json!({
+                "Interval": hours,
+                "IntervalUnit": "HOURS",
+            })
+        }
+    }
+}
+
+#[throws]
+fn ec2_dlm_create(args: Ec2DlmCreate) {
+    let (tag_key, tag_value) = args.target_tag;
+    let role_arn = match args.role_arn {
+        Some(role_arn) => role_arn,
+        None => format!(
+            "arn:aws:iam::{}:role/AWSDataLifecycleManagerDefaultRole",
+            get_account_id()?
+        ),
+    };
+    let payload = serde_json::json!({
+        "Description": format!(
+            "{} snapshots of volumes tagged {}={}",
+            args.schedule, tag_key, tag_value
+        ),
+        "ExecutionRoleArn": role_arn,
+        "State": "ENABLED",
+        "PolicyDetails": {
+            "PolicyType": "EBS_SNAPSHOT_MANAGEMENT",
+            "ResourceTypes": ["VOLUME"],
+            "TargetTags": [{"Key": tag_key, "Value": tag_value}],
+            "Schedules": [{
+                "Name": args.schedule,
+                "CopyTags": true,
+                "CreateRule": dlm_create_rule(&args.schedule)?,
+                "RetainRule": {"Count": args.retain},
+            }],
+        },
+    });
+    let output = send_signed_request(
+        "POST",
+        "dlm",
+        "dlm",
+        "/policies",
+        &[],
+        Some(payload),
+    )
+    .context("failed to create lifecycle policy")?;
+    let output: serde_json::Value = serde_json::from_slice(&output)
+        .context("failed to parse lifecycle policy")?;
+    println!("{}", output["PolicyId"].as_str().unwrap_or_default());
+}
+
+#[throws]
+fn ec2_run_command(args: Ec2RunCommand) {
+    let client = SsmClient::new(Region::default());
+    let targets = args
+        .tags
+        .into_iter()
+        .map(|(key, value)| SsmTarget {
+            key: Some(format!("tag:{}", key)),
+            values: Some(vec![value]),
+        })
+        .collect();
+    let mut parameters = HashMap::new();
+    parameters.insert("commands".to_string(), vec![args.command.join(" ")]);
+    let output = client
+        .send_command(SendCommandRequest {
+            document_name: "AWS-RunShellScript".into(),
+            targets: Some(targets),
+            parameters: Some(parameters),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to send command")?;
+    let command_id = output
+        .command
+        .and_then(|command| command.command_id)
+        .context("missing command ID")?;
+    ssm_wait_for_command(&client, &command_id)?;
+}
+
+#[throws]
+fn ssm_get_managed_instances(client: &SsmClient) -> Vec<InstanceInformation> {
+    let mut managed = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_instance_information(DescribeInstanceInformationRequest {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list managed instances")?;
+        managed.extend(output.instance_information_list.unwrap_or_default());
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    managed
+}
+
+#[throws]
+fn ssm_compliance() {
+    let client = SsmClient::new(Region::default());
+
+    let managed = ssm_get_managed_instances(&client)?;
+
+    // Map from instance ID to patch compliance status
+    let mut patch_status = HashMap::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .list_resource_compliance_summaries(
+                ListResourceComplianceSummariesRequest {
+                    filters: Some(vec![ComplianceStringFilter {
+                        key: Some("ComplianceType".into()),
+                        type_: Some("EQUAL".into()),
+                        values: Some(vec!["Patch".into()]),
+                    }]),
+                    next_token: next_token.clone(),
+                    ..Default::default()
+                },
+            )
+            .sync()
+            .context("failed to list patch compliance")?;
+        for item in output.resource_compliance_summary_items.unwrap_or_default()
+        {
+            if let (Some(id), Some(status)) = (item.resource_id, item.status) {
+                patch_status.insert(id, status);
+            }
+        }
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+
+    let mut rows = vec![vec![
+        "INSTANCE".to_string(),
+        "AGENT".to_string(),
+        "LAST PING".to_string(),
+        "PING".to_string(),
+        "PATCHES".to_string(),
+        "PROBLEMS".to_string(),
+    ]];
+    let mut managed_ids = HashSet::new();
+    for info in managed {
+        let id = info.instance_id.unwrap_or_default();
+        let ping = info.ping_status.unwrap_or_default();
+        let patches =
+            patch_status.remove(&id).unwrap_or_else(|| "UNKNOWN".into());
+
+        let mut problems = Vec::new();
+        if info.is_latest_version == Some(false) {
+            problems.push("outdated agent");
+        }
+        if ping != "Online" {
+            problems.push("not connected");
+        }
+        if patches != "COMPLIANT" {
+            problems.push("patches not compliant");
+        }
+
+        rows.push(vec![
+            id.clone(),
+            info.agent_version.unwrap_or_default(),
+            info.last_ping_date_time
+                .map(format_timestamp_secs)
+                .unwrap_or_default(),
+            ping,
+            patches,
+            problems.join(", "),
+        ]);
+        managed_ids.insert(id);
+    }
+
+    // Running EC2 instances that SSM doesn't know about
+    let running = ec2_get_instances(Some(vec![Filter {
+        name: Some("instance-state-name".into()),
+        values: Some(vec!["running".into()]),
+    }]))?;
+    for instance in running {
+        let id = instance.instance_id.unwrap_or_default();
+        if !managed_ids.contains(&id) {
+            rows.push(vec![
+                id,
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                "unmanaged".into(),
+            ]);
+        }
+    }
+
+    print_table(&rows);
+}
+
+#[throws]
+fn ssm_inventory(args: SsmInventory) {
+    let client = SsmClient::new(Region::default());
+    let instance_ids = if args.instances.is_empty() {
+        ssm_get_managed_instances(&client)?
+            .into_iter()
+            .filter_map(|info| info.instance_id)
+            .collect()
+    } else {
+        ec2_resolve_instance_ids(args.instances)?
+    };
+
+    // Filters are matched locally so that they can be prefixes and
+    // case insensitive
+    let filters: Vec<_> = args
+        .filters
+        .iter()
+        .map(|(key, value)| (key.to_lowercase(), value.to_lowercase()))
+        .collect();
+    let matches = |entry: &HashMap<String, String>| {
+        filters.iter().all(|(key, value)| {
+            entry.iter().any(|(k, v)| {
+                k.to_lowercase() == *key && v.to_lowercase().starts_with(value)
+            })
+        })
+    };
+
+    let mut table = TableStream::new(vec![
+        "INSTANCE".to_string(),
+        "NAME".to_string(),
+        "VERSION".to_string(),
+        "PUBLISHER".to_string(),
+    ]);
+    for instance_id in instance_ids {
+        let mut next_token = None;
+        loop {
+            let output = client
+                .list_inventory_entries(ListInventoryEntriesRequest {
+                    instance_id: instance_id.clone(),
+                    type_name: args.type_name.clone(),
+                    next_token: next_token.clone(),
+                    ..Default::default()
+                })
+                .sync()
+                .with_context(|| {
+                    format!("failed to list inventory of {}", instance_id)
+                })?;
+            for entry in output.entries.unwrap_or_default() {
+                if !matches(&entry) {
+                    continue;
+                }
+                let field = |name: &str| -> String {
+                    entry.get(name).cloned().unwrap_or_default()
+                };
+                table.push(vec![
+                    instance_id.clone(),
+                    field("Name"),
+                    field("Version"),
+                    field("Publisher"),
+                ]);
+            }
+            table.flush();
+            // Finish if there are no more results
+            if output.next_token.is_none() {
+                break;
+            }
+            next_token = output.next_token;
+        }
+    }
+    table.finish();
+}
+
+#[throws]
+fn ssm_list_ops_items(status: Option<String>) {
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "STATUS".to_string(),
+        "SEVERITY".to_string(),
+        "PRIORITY".to_string(),
+        "SOURCE".to_string(),
+        "CREATED".to_string(),
+        "TITLE".to_string(),
+    ]);
+    let mut next_token: Option<String> = None;
+    loop {
+        // Severity is missing from rusoto's OpsItemSummary, so call
+        // the API directly
+        let mut payload = serde_json::json!({});
+        if let Some(status) = &status {
+            payload["OpsItemFilters"] = serde_json::json!([{
+                "Key": "Status",
+                "Operator": "Equal",
+                "Values": [status],
+            }]);
+        }
+        if let Some(token) = &next_token {
+            payload["NextToken"] = token.as_str().into();
+        }
+        let output = json_request(
+            "ssm",
+            "ssm",
+            "AmazonSSM",
+            "DescribeOpsItems",
+            payload,
+        )?;
+        for item in output["OpsItemSummaries"].as_array().into_iter().flatten()
+        {
+            table.push(vec![
+                item["OpsItemId"].as_str().unwrap_or_default().into(),
+                item["Status"].as_str().unwrap_or_default().into(),
+                item["Severity"].as_str().unwrap_or_default().into(),
+                item["Priority"]
+                    .as_i64()
+                    .map(|priority| priority.to_string())
+                    .unwrap_or_default(),
+                item["Source"].as_str().unwrap_or_default().into(),
+                item["CreatedTime"]
+                    .as_f64()
+                    .map(format_timestamp_secs)
+                    .unwrap_or_default(),
+                item["Title"].as_str().unwrap_or_default().into(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    table.finish();
+}
+
+#[throws]
+fn ssm_resolve_ops_item(ops_item_id: String) {
+    let client = SsmClient::new(Region::default());
+    client
+        .update_ops_item(UpdateOpsItemRequest {
+            ops_item_id,
+            status: Some("Resolved".into()),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to resolve OpsItem")?;
+}
+
+#[throws]
+fn ssm_list_documents(args: SsmDocuments) {
+    let client = SsmClient::new(Region::default());
+    let filters = args.owner.map(|owner| {
+        // The API expects owners like "Self" and "Amazon"
+        let mut chars = owner.chars();
+        let owner = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        };
+        vec![DocumentKeyValuesFilter {
+            key: Some("Owner".into()),
+            values: Some(vec![owner]),
+        }]
+    });
+    let mut rows = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .list_documents(ListDocumentsRequest {
+                filters: filters.clone(),
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list documents")?;
+        for doc in output.document_identifiers.unwrap_or_default() {
+            rows.push(vec![
+                doc.name.unwrap_or_default(),
+                doc.document_type.unwrap_or_default(),
+                doc.owner.unwrap_or_default(),
+            ]);
+        }
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    rows.sort_unstable();
+    print_table(&rows);
+}
+
+#[throws]
+fn ssm_run_document(args: SsmRunDocument) {
+    let client = SsmClient::new(Region::default());
+    let mut parameters: HashMap<String, Vec<String>> = HashMap::new();
+    for (key, value) in args.params {
+        parameters.entry(key).or_default().push(value);
+    }
+    let output = client
+        .send_command(SendCommandRequest {
+            document_name: args.name,
+            instance_ids: Some(ec2_resolve_instance_ids(args.instances)?),
+            parameters: Some(parameters),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to send command")?;
+    let command_id = output
+        .command
+        .and_then(|command| command.command_id)
+        .context("missing command ID")?;
+    ssm_wait_for_command(&client, &command_id)?;
+}
+
+// Not using #[throws] here because of
+// github.com/withoutboats/fehler/issues/52
+fn logs_groups(args: ListLogGroups) -> Result<(), Error> {
+    let client = CloudWatchLogsClient::new(Region::default());
+    let mut next_token = None;
+    loop {
+        let resp = client
+            .describe_log_groups(DescribeLogGroupsRequest {
+                log_group_name_prefix: args.prefix.clone(),
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list groups")?;
+        if let Some(log_groups) = resp.log_groups {
+            for log_group in log_groups {
+                println!(
+                    "{}",
+                    log_group
+                        .log_group_name
+                        .ok_or_else(|| anyhow!("missing log group name"))?
+                );
+            }
+        }
+        // Finish if there are no more results
+        if resp.next_token.is_none() {
+            return Ok(());
+        }
+        next_token = resp.next_token;
+    }
+}
+
+// Not using #[throws] here because of
+// github.com/withoutboats/fehler/issues/52
+fn logs_recent_streams(args: RecentLogStreams) -> Result<(), Error> {
+    let client = CloudWatchLogsClient::new(Region::default());
+    let mut next_token = None;
+    let mut remaining = args.limit as i64;
+    loop {
+        // The describe-log-streams operation has a limit of five
+        // transactions per second, so attempt up to five requests and
+        // then sleep for 1 second.
+        for _ in 0..5 {
+            // 50 is the maximum for a single request
+            let limit = std::cmp::min(remaining, 50);
+            let resp = client
+                .describe_log_streams(DescribeLogStreamsRequest {
+                    log_group_name: args.log_group_name.clone(),
+                    limit: Some(limit),
+                    order_by: Some("LastEventTime".to_string()),
+                    next_token: next_token.clone(),
+                    descending: Some(true),
+                    ..Default::default()
+                })
+                .sync()
+                .context("failed to list streams")?;
+            if let Some(log_streams) = resp.log_streams {
+                for log_stream in log_streams {
+                    println!(
+                        "{}",
+                        log_stream.log_stream_name.ok_or_else(|| anyhow!(
+                            "missing log stream name"
+                        ))?
+                    );
+                }
+            }
+            // Finish if there are no more results
+            if resp.next_token.is_none() {
+                return Ok(());
+            }
+            remaining -= limit;
+            // Finish if the number of requested streams has already
+            // been shown
+            if remaining <= 0 {
+                return Ok(());
+            }
+            next_token = resp.next_token;
+        }
+        thread::sleep(time::Duration::from_secs(1));
+    }
+}
+
+/// Format a timestamp in milliseconds since the epoch.
+fn format_timestamp_millis(millis: i64) -> String {
+    match Utc.timestamp_millis_opt(millis).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => millis.to_string(),
+    }
+}
+
+/// Format a timestamp in seconds since the epoch.
+fn format_timestamp_secs(secs: f64) -> String {
+    format_timestamp_millis((secs * 1000.0) as i64)
+}
+
+fn format_log_event(event: &FilteredLogEvent) -> String {
+    format!(
+        "{} {}",
+        format_timestamp_millis(event.timestamp.unwrap_or_default()),
+        event.message.as_deref().unwrap_or_default().trim_end()
+    )
+}
+
+/// Print events from a log group, starting from `since` ago. If
+/// `follow` is true, keep polling for new events.
+#[throws]
+fn tail_log_group<F: Fn(&FilteredLogEvent) -> String>(
+    log_group_name: &str,
+    since: &str,
+    filter_pattern: Option<String>,
+    follow: bool,
+    format: F,
+) {
+    let client = CloudWatchLogsClient::new(Region::default());
+    let mut start_time =
+        (Utc::now() - parse_duration(since)?).timestamp_millis();
+    // IDs of events at the start time that have already been
+    // printed. The start time is inclusive, so these events will be
+    // returned again by the next poll.
+    let mut seen = HashSet::new();
+    loop {
+        let mut next_token = None;
+        loop {
+            let resp = client
+                .filter_log_events(FilterLogEventsRequest {
+                    log_group_name: log_group_name.into(),
+                    filter_pattern: filter_pattern.clone(),
+                    start_time: Some(start_time),
+                    next_token: next_token.clone(),
+                    ..Default::default()
+                })
+                .sync()
+                .context("failed to get log events")?;
+            for event in resp.events.unwrap_or_default() {
+                if let Some(id) = &event.event_id {
+                    if seen.contains(id) {
+                        continue;
+                    }
+                }
+                println!("{}", format(&event));
+                let timestamp = event.timestamp.unwrap_or(start_time);
+                if timestamp > start_time {
+                    start_time = timestamp;
+                    seen.clear();
+                }
+                if let Some(id) = event.event_id {
+                    seen.insert(id);
+                }
+            }
+            // Finish if there are no more results
+            if resp.next_token.is_none() {
+                break;
+            }
+            next_token = resp.next_token;
+        }
+        if !follow {
+            break;
+        }
+        thread::sleep(time::Duration::from_secs(2));
+    }
+}
+
+#[throws]
+fn logs_tail(args: TailLogs) {
+    tail_log_group(
+        &args.log_group_name,
+        &args.since,
+        args.filter,
+        args.follow,
+        format_log_event,
+    )?;
+}
+
+#[throws]
+fn logs_insights(args: LogsInsights) {
+    let query = match (args.query, &args.saved) {
+        (Some(query), None) => query,
+        (None, Some(name)) => {
+            read_claws_config_section(&format!("insights.{}", name))?
+                .remove("query")
+                .with_context(|| format!("saved query not found: {}", name))?
+        }
+        _ => throw!(anyhow!("specify either --query or --saved")),
+    };
+    if let Some(name) = &args.save {
+        // Config values are one line
+        let query = query.replace('\n', " ");
+        write_claws_config_section(
+            &format!("insights.{}", name),
+            &[("query", &query)],
+        )?;
+    }
+
+    let client = CloudWatchLogsClient::new(Region::default());
+    let end = Utc::now();
+    let start = end - parse_duration(&args.since)?;
+    let output = client
+        .start_query(StartQueryRequest {
+            log_group_names: Some(args.log_group_names),
+            query_string: query,
+            start_time: start.timestamp(),
+            end_time: end.timestamp(),
+            limit: args.limit,
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to start query")?;
+    let query_id = output.query_id.context("missing query ID")?;
+
+    let results = loop {
+        let output = client
+            .get_query_results(GetQueryResultsRequest {
+                query_id: query_id.clone(),
+            })
+            .sync()
+            .context("failed to get query results")?;
+        match output.status.as_deref() {
+            Some("Scheduled") | Some("Running") => {
+                thread::sleep(time::Duration::from_secs(1))
+            }
+            Some("Complete") => break output.results.unwrap_or_default(),
+            status => throw!(anyhow!(
+                "query did not complete: {}",
+                status.unwrap_or_default()
+            )),
+        }
+    };
+
+    // Use the fields of the first result as the columns, skipping the
+    // internal @ptr field
+    let columns: Vec<String> = results
+        .first()
+        .into_iter()
+        .flatten()
+        .filter_map(|field| field.field.clone())
+        .filter(|name| name != "@ptr")
+        .collect();
+    let mut rows = vec![columns.clone()];
+    for result in results {
+        let values: HashMap<_, _> = result
+            .into_iter()
+            .filter_map(|field| {
+                Some((field.field?, field.value.unwrap_or_default()))
+            })
+            .collect();
+        rows.push(
+            columns
+                .iter()
+                .map(|column| values.get(column).cloned().unwrap_or_default())
+                .collect(),
+        );
+    }
+    print_table(&rows);
+}
+
+/// Make a bar proportional to `count`, with `max` being full width.
+fn histogram_bar(count: usize, max: usize) -> String {
+    const WIDTH: usize = 40;
+    let len = if max == 0 {
+        0
+    } else {
+        // Round up so that non-zero counts are always visible
+        (count * WIDTH).div_ceil(max)
+    };
+    "#".repeat(len)
+}
+
+#[throws]
+fn logs_stats(args: LogsStats) {
+    let client = CloudWatchLogsClient::new(Region::default());
+    let start_time =
+        (Utc::now() - parse_duration(&args.since)?).timestamp_millis();
+    let bucket_millis = parse_duration(&args.bucket)?.num_milliseconds().max(1);
+
+    let mut by_stream: BTreeMap<String, usize> = BTreeMap::new();
+    let mut by_time: BTreeMap<i64, usize> = BTreeMap::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .filter_log_events(FilterLogEventsRequest {
+                log_group_name: args.log_group_name.clone(),
+                filter_pattern: Some(args.pattern.clone()),
+                start_time: Some(start_time),
+                next_token,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get log events")?;
+        for event in output.events.unwrap_or_default() {
+            *by_stream
+                .entry(event.log_stream_name.unwrap_or_default())
+                .or_default() += 1;
+            let timestamp = event.timestamp.unwrap_or_default();
+            *by_time
+                .entry(timestamp - timestamp % bucket_millis)
+                .or_default() += 1;
+        }
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+
+    let total: usize = by_stream.values().sum();
+    println!("{} matching events", total);
+    if total == 0 {
+        return;
+    }
+
+    // Streams with the most matches first
+    let mut streams: Vec<_> = by_stream.into_iter().collect();
+    streams.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let max = streams.first().map(|(_, count)| *count).unwrap_or(0);
+    let mut rows = vec![vec![
+        "STREAM".to_string(),
+        "COUNT".to_string(),
+        String::new(),
+    ]];
+    for (stream, count) in &streams {
+        rows.push(vec![
+            stream.clone(),
+            count.to_string(),
+            histogram_bar(*count, max),
+        ]);
+    }
+    println!();
+    print_table(&rows);
+
+    let max = by_time.values().copied().max().unwrap_or(0);
+    let mut rows =
+        vec![vec!["TIME".to_string(), "COUNT".to_string(), String::new()]];
+    for (bucket, count) in by_time {
+        rows.push(vec![
+            format_timestamp_millis(bucket),
+            count.to_string(),
+            histogram_bar(count, max),
+        ]);
+    }
+    println!();
+    print_table(&rows);
+}
+
+/// Call a CloudWatch action that isn't available in rusoto_cloudwatch.
+#[throws]
+fn cloudwatch_query(action: &str, params: &[(&str, &str)]) -> XmlElement {
+    query_request(
+        "monitoring",
+        &Region::default(),
+        "2010-08-01",
+        action,
+        params,
+    )?
+}
+
+#[throws]
+fn cw_list_insight_rules() {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "STATE".to_string(),
+        "SCHEMA".to_string(),
+    ]);
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(token) = &next_token {
+            params.push(("NextToken", token.as_str()));
+        }
+        let output = cloudwatch_query("DescribeInsightRules", &params)?;
+        let result = output
+            .child("DescribeInsightRulesResult")
+            .context("missing result")?;
+        for rule in result.items("InsightRules") {
+            table.push(vec![
+                rule.child_text("Name").unwrap_or_default().into(),
+                rule.child_text("State").unwrap_or_default().into(),
+                rule.child_text("Schema").unwrap_or_default().into(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match result.child_text("NextToken") {
+            Some(token) if !token.is_empty() => next_token = Some(token.into()),
+            _ => break,
+        }
+    }
+    table.finish();
+}
+
+/// Enable or disable Contributor Insights rules.
+#[throws]
+fn cw_set_insight_rules(action: &str, rule_names: Vec<String>) {
+    let keys: Vec<_> = (1..=rule_names.len())
+        .map(|i| format!("RuleNames.member.{}", i))
+        .collect();
+    let params: Vec<_> = keys
+        .iter()
+        .map(String::as_str)
+        .zip(rule_names.iter().map(String::as_str))
+        .collect();
+    let output = cloudwatch_query(action, &params)?;
+    let failures = output
+        .child(&format!("{}Result", action))
+        .map(|result| result.items("Failures"))
+        .unwrap_or_default();
+    for failure in &failures {
+        eprintln!(
+            "{}: {}",
+            failure.child_text("FailureResource").unwrap_or_default(),
+            failure.child_text("FailureDescription").unwrap_or_default()
+        );
+    }
+    if !failures.is_empty() {
+        throw!(anyhow!("one or more rules failed"));
+    }
+}
+
+fn parse_dimensions(
+    dimensions: Vec<(String, String)>,
+) -> Option<Vec<Dimension>> {
+    if dimensions.is_empty() {
+        return None;
+    }
+    Some(
+        dimensions
+            .into_iter()
+            .map(|(name, value)| Dimension { name, value })
+            .collect(),
+    )
+}
+
+#[throws]
+fn cw_list_anomaly_detectors() {
+    let client = CloudWatchClient::new(Region::default());
+    let mut table = TableStream::new(vec![
+        "NAMESPACE".to_string(),
+        "METRIC".to_string(),
+        "STAT".to_string(),
+        "DIMENSIONS".to_string(),
+    ]);
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_anomaly_detectors(DescribeAnomalyDetectorsInput {
+                next_token,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list anomaly detectors")?;
+        for detector in output.anomaly_detectors.unwrap_or_default() {
+            let dimensions: Vec<_> = detector
+                .dimensions
+                .unwrap_or_default()
+                .into_iter()
+                .map(|dimension| {
+                    format!("{}={}", dimension.name, dimension.value)
+                })
+                .collect();
+            table.push(vec![
+                detector.namespace.unwrap_or_default(),
+                detector.metric_name.unwrap_or_default(),
+                detector.stat.unwrap_or_default(),
+                dimensions.join(","),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    table.finish();
+}
+
+#[throws]
+fn cw_enable_anomaly_detector(args: CwAnomalyDetector) {
+    let client = CloudWatchClient::new(Region::default());
+    client
+        .put_anomaly_detector(PutAnomalyDetectorInput {
+            namespace: args.namespace,
+            metric_name: args.metric_name,
+            stat: args.stat,
+            dimensions: parse_dimensions(args.dimensions),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to create anomaly detector")?;
+}
+
+#[throws]
+fn cw_disable_anomaly_detector(args: CwAnomalyDetector) {
+    let client = CloudWatchClient::new(Region::default());
+    client
+        .delete_anomaly_detector(DeleteAnomalyDetectorInput {
+            namespace: args.namespace,
+            metric_name: args.metric_name,
+            stat: args.stat,
+            dimensions: parse_dimensions(args.dimensions),
+        })
+        .sync()
+        .context("failed to delete anomaly detector")?;
+}
+
+#[throws]
+fn s3_list_buckets(args: S3Buckets) {
+    let client = S3Client::new(Region::default());
+    let output = client
+        .list_buckets()
+        .sync()
+        .context("failed to list buckets")?;
+    let buckets = output.buckets.context("missing buckets field")?;
+    if !args.long && !args.size {
+        for bucket in buckets {
+            let name = bucket.name.context("missing bucket name")?;
+            println!("{}", name);
+        }
+        return;
+    }
+
+    let mut rows = vec![vec![
+        "NAME".to_string(),
+        "REGION".to_string(),
+        "CREATED".to_string(),
+    ]];
+    if args.size {
+        rows[0].push("SIZE".into());
+        rows[0].push("OBJECTS".into());
+    }
+    // Fetch the details for a batch of buckets at a time
+    for chunk in buckets.chunks(16) {
+        let handles: Vec<_> = chunk
+            .iter()
+            .map(|bucket| {
+                let name = bucket.name.clone().unwrap_or_default();
+                let created = bucket.creation_date.clone().unwrap_or_default();
+                let size = args.size;
+                thread::spawn(move || s3_bucket_details(name, created, size))
+            })
+            .collect();
+        for handle in handles {
+            let row = handle
+                .join()
+                .map_err(|_| anyhow!("failed to get bucket details"))??;
+            rows.push(row);
+        }
+    }
+    print_table(&rows);
+}
+
+/// Get the region that a bucket is in.
+#[throws]
+fn s3_get_bucket_region(bucket: &str) -> Region {
+    let client = S3Client::new(Region::default());
+    let output = match client
+        .get_bucket_location(GetBucketLocationRequest {
+            bucket: bucket.into(),
+        })
+        .sync()
+    {
+        Ok(output) => output,
+        // GetBucketLocation needs its own permission, so fall back to
+        // the region that S3 redirects requests for the bucket to
+        Err(err) => {
+            return s3_get_redirect_region(bucket)?.with_context(|| {
+                format!("failed to get location of {}: {}", bucket, err)
+            })?;
+        }
+    };
+    // Buckets in us-east-1 have no location, and some old buckets in
+    // eu-west-1 have the location "EU"
+    match output.location_constraint.as_deref() {
+        None | Some("") => Region::UsEast1,
+        Some("EU") => Region::EuWest1,
+        Some(location) => location.parse().with_context(|| {
+            format!("invalid bucket location: {}", location)
+        })?,
+    }
+}
+
+/// Get the region of a bucket with HeadBucket, or None if S3 didn't
+/// say. Requests sent to the wrong region fail with a 301
+/// PermanentRedirect that names the bucket's region in the
+/// x-amz-bucket-region header.
+#[throws]
+fn s3_get_redirect_region(bucket: &str) -> Option<Region> {
+    let region = Region::default();
+    let client = S3Client::new(region.clone());
+    let response = match client
+        .head_bucket(HeadBucketRequest {
+            bucket: bucket.into(),
+        })
+        .sync()
+    {
+        Ok(()) => return Some(region),
+        Err(RusotoError::Unknown(response)) => response,
+        Err(err) => {
+            Err(err).with_context(|| format!("failed to get {}", bucket))?
+        }
+    };
+    match response.headers.get("x-amz-bucket-region") {
+        Some(region) => {
+            Some(region.parse().with_context(|| {
+                format!("invalid bucket region: {}", region)
+            })?)
+        }
+        None => None,
+    }
+}
+
+/// Create a client for the region that a bucket is in.
+#[throws]
+fn s3_client_for_bucket(bucket: &str) -> S3Client {
+    S3Client::new(s3_get_bucket_region(bucket)?)
+}
+
+/// Upload a local file under `prefix` for a service to read, returning
+/// the key.
+#[throws]
+fn s3_upload_temp_file(bucket: &str, prefix: &str, path: &Path) -> String {
+    let data = fs::read(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .context("invalid file name")?
+        .to_string_lossy();
+    let key = format!("{}/{}", prefix, file_name);
+    s3_client_for_bucket(bucket)?
+        .put_object(PutObjectRequest {
+            bucket: bucket.into(),
+            key: key.clone(),
+            content_length: Some(data.len() as i64),
+            body: Some(data.into()),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to upload file")?;
+    key
+}
+
+/// Get a bucket's replication configuration, or None if replication
+/// isn't configured.
+#[throws]
+fn s3_get_replication(
+    client: &S3Client,
+    bucket: &str,
+) -> Option<ReplicationConfiguration> {
+    match client
+        .get_bucket_replication(GetBucketReplicationRequest {
+            bucket: bucket.into(),
+        })
+        .sync()
+    {
+        Ok(output) => output.replication_configuration,
+        Err(RusotoError::Unknown(response)) if response.status == 404 => None,
+        Err(err) => Err(err).with_context(|| {
+            format!("failed to get replication configuration of {}", bucket)
+        })?,
+    }
+}
+
+#[throws]
+fn s3_replication_get(bucket: String) {
+    let client = s3_client_for_bucket(&bucket)?;
+    let config = match s3_get_replication(&client, &bucket)? {
+        Some(config) => config,
+        None => {
+            println!("replication is not configured");
+            return;
+        }
+    };
+    println!("role: {}", config.role);
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "STATUS".to_string(),
+        "PRIORITY".to_string(),
+        "PREFIX".to_string(),
+        "DESTINATION".to_string(),
+    ]];
+    for rule in config.rules {
+        let prefix = rule
+            .filter
+            .and_then(|filter| filter.prefix)
+            .unwrap_or_default();
+        rows.push(vec![
+            rule.id.unwrap_or_default(),
+            rule.status,
+            rule.priority.map(|p| p.to_string()).unwrap_or_default(),
+            prefix,
+            rule.destination.bucket,
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn s3_require_versioning(bucket: &str) {
+    let client = s3_client_for_bucket(bucket)?;
+    let output = client
+        .get_bucket_versioning(GetBucketVersioningRequest {
+            bucket: bucket.into(),
+        })
+        .sync()
+        .with_context(|| format!("failed to get versioning of {}", bucket))?;
+    if output.status.as_deref() != Some("Enabled") {
+        throw!(anyhow!(
+            "versioning must be enabled on {} for replication",
+            bucket
+        ));
+    }
+}
+
+/// Get or create the role that S3 uses to replicate from `src` to
+/// `dst`, returning its ARN.
+#[throws]
+fn s3_replication_role(src: &str, dst: &str) -> String {
+    let client = iam_client();
+    let mut role_name = format!("s3-replication-{}", src);
+    // Role names are limited to 64 characters
+    role_name.truncate(64);
+    let role = match client
+        .get_role(GetRoleRequest {
+            role_name: role_name.clone(),
+        })
+        .sync()
+    {
+        Ok(output) => output.role,
+        Err(_) => {
+            let trust_policy = serde_json::json!({
+                "Version": "2012-10-17",
+                "Statement": [{
+                    "Effect": "Allow",
+                    "Principal": {"Service": "s3.amazonaws.com"},
+                    "Action": "sts:AssumeRole",
+                }],
+            });
+            let output = client
+                .create_role(CreateRoleRequest {
+                    role_name: role_name.clone(),
+                    assume_role_policy_document: trust_policy.to_string(),
+                    description: Some(format!(
+                        "Replication from {} (created by claws)",
+                        src
+                    )),
+                    ..Default::default()
+                })
+                .sync()
+                .context("failed to create replication role")?;
+            println!("created role {}", role_name);
+            output.role
+        }
+    };
+
+    // Each destination gets its own statement so the role can
+    // replicate to several buckets
+    let policy = serde_json::json!({
+        "Version": "2012-10-17",
+        "Statement": [
+            {
+                "Effect": "Allow",
+                "Action": [
+                    "s3:GetReplicationConfiguration",
+                    "s3:ListBucket",
+                ],
+                "Resource": format!("arn:aws:s3:::{}", src),
+            },
+            {
+                "Effect": "Allow",
+                "Action": [
+                    "s3:GetObjectVersionForReplication",
+                    "s3:GetObjectVersionAcl",
+                    "s3:GetObjectVersionTagging",
+                ],
+                "Resource": format!("arn:aws:s3:::{}/*", src),
+            },
+            {
+                "Effect": "Allow",
+                "Action": [
+                    "s3:ReplicateObject",
+                    "s3:ReplicateDelete",
+                    "s3:ReplicateTags",
+                ],
+                "Resource": format!("arn:aws:s3:::{}/*", dst),
+            },
+        ],
+    });
+    client
+        .put_role_policy(PutRolePolicyRequest {
+            role_name,
+            policy_name: format!("replicate-to-{}", dst),
+            policy_document: policy.to_string(),
+        })
+        .sync()
+        .context("failed to set replication role policy")?;
+    role.arn
+}
+
+#[throws]
+fn s3_replication_add(args: S3ReplicationAdd) {
+    s3_require_versioning(&args.src)?;
+    s3_require_versioning(&args.dst)?;
+    let role_arn = s3_replication_role(&args.src, &args.dst)?;
+
+    let client = s3_client_for_bucket(&args.src)?;
+    // Add to any existing rules rather than replacing them
+    let mut rules = s3_get_replication(&client, &args.src)?
+        .map(|config| config.rules)
+        .unwrap_or_default();
+    let priority = rules
+        .iter()
+        .filter_map(|rule| rule.priority)
+        .max()
+        .map_or(0, |priority| priority + 1);
+    rules.push(ReplicationRule {
+        id: Some(format!("replicate-to-{}", args.dst)),
+        status: "Enabled".into(),
+        priority: Some(priority),
+        filter: Some(ReplicationRuleFilter {
+            prefix: Some(args.prefix.unwrap_or_default()),
+            ..Default::default()
+        }),
+        delete_marker_replication: Some(DeleteMarkerReplication {
+            status: Some("Disabled".into()),
+        }),
+        destination: Destination {
+            bucket: format!("arn:aws:s3:::{}", args.dst),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    // A newly created role can take a few seconds to be usable
+    let mut attempts = 0;
+    loop {
+        let result = client
+            .put_bucket_replication(PutBucketReplicationRequest {
+                bucket: args.src.clone(),
+                replication_configuration: ReplicationConfiguration {
+                    role: role_arn.clone(),
+                    rules: rules.clone(),
+                },
+                ..Default::default()
+            })
+            .sync();
+        match result {
+            Ok(_) => break,
+            Err(_) if attempts < 5 => {
+                attempts += 1;
+                thread::sleep(time::Duration::from_secs(5));
+            }
+            Err(err) => {
+                Err(err).context("failed to set replication configuration")?
+            }
+        }
+    }
+    println!("replicating {} to {}", args.src, args.dst);
+}
+
+/// Split a path such as "bucket/key" or "s3://bucket/key" into the
+/// bucket and key. The key is None if the path is just a bucket.
+fn parse_s3_path(path: &str) -> (String, Option<String>) {
+    let path = path.strip_prefix("s3://").unwrap_or(path);
+    match path.split_once('/') {
+        Some((bucket, key)) if !key.is_empty() => {
+            (bucket.into(), Some(key.into()))
+        }
+        Some((bucket, _)) => (bucket.into(), None),
+        None => (path.into(), None),
+    }
+}
+
+#[throws]
+fn parse_s3_object_path(path: &str) -> (String, String) {
+    match parse_s3_path(path) {
+        (bucket, Some(key)) => (bucket, key),
+        (_, None) => throw!(anyhow!("expected <bucket>/<key>: {}", path)),
+    }
+}
+
+/// Put an object subresource such as "retention" or "legal-hold".
+/// These requests need a Content-MD5 header, which rusoto 0.42
+/// doesn't set for them.
+#[throws]
+fn s3_put_object_subresource(
+    path: &str,
+    version_id: &Option<String>,
+    subresource: &str,
+    headers: &[(&str, &str)],
+    body: String,
+) {
+    let (bucket, key) = parse_s3_object_path(path)?;
+    let region = s3_get_bucket_region(&bucket)?;
+    let mut request = SignedRequest::new(
+        "PUT",
+        "s3",
+        &region,
+        &format!("/{}/{}", bucket, key),
+    );
+    request.add_param(subresource, "");
+    if let Some(version_id) = version_id {
+        request.add_param("versionId", version_id);
+    }
+    for (name, value) in headers {
+        request.add_header(*name, value);
+    }
+    request.set_payload(Some(body.into_bytes()));
+    request.set_content_md5_header();
+    dispatch_request(&Client::shared(), request).with_context(|| {
+        format!("failed to set {} of {}", subresource, path)
+    })?;
+}
+
+#[throws]
+fn s3_lock_status(args: S3LockStatus) {
+    let (bucket, key) = parse_s3_path(&args.path);
+    let client = s3_client_for_bucket(&bucket)?;
+    let key = match key {
+        Some(key) => key,
+        None => {
+            // Show the bucket's default retention
+            let output = client
+                .get_object_lock_configuration(
+                    GetObjectLockConfigurationRequest {
+                        bucket: bucket.clone(),
+                    },
+                )
+                .sync()
+                .with_context(|| {
+                    format!(
+                        "failed to get object lock configuration of {}",
+                        bucket
+                    )
+                })?;
+            let config = output.object_lock_configuration.unwrap_or_default();
+            println!(
+                "object lock: {}",
+                config.object_lock_enabled.unwrap_or_default()
+            );
+            if let Some(retention) =
+                config.rule.and_then(|rule| rule.default_retention)
+            {
+                let period = match (retention.days, retention.years) {
+                    (Some(days), _) => format!("{} days", days),
+                    (_, Some(years)) => format!("{} years", years),
+                    _ => String::new(),
+                };
+                println!(
+                    "default retention: {} {}",
+                    retention.mode.unwrap_or_default(),
+                    period
+                );
+            }
+            return;
+        }
+    };
+
+    // Objects without retention or a legal hold return an error
+    // rather than an empty value
+    let retention = client
+        .get_object_retention(GetObjectRetentionRequest {
+            bucket: bucket.clone(),
+            key: key.clone(),
+            version_id: args.version_id.clone(),
+            ..Default::default()
+        })
+        .sync()
+        .ok()
+        .and_then(|output| output.retention);
+    match retention {
+        Some(retention) => println!(
+            "retention: {} until {}",
+            retention.mode.unwrap_or_default(),
+            retention.retain_until_date.unwrap_or_default()
+        ),
+        None => println!("retention: none"),
+    }
+    let legal_hold = client
+        .get_object_legal_hold(GetObjectLegalHoldRequest {
+            bucket,
+            key,
+            version_id: args.version_id,
+            ..Default::default()
+        })
+        .sync()
+        .ok()
+        .and_then(|output| output.legal_hold)
+        .and_then(|legal_hold| legal_hold.status);
+    println!("legal hold: {}", legal_hold.unwrap_or_else(|| "OFF".into()));
+}
+
+#[throws]
+fn s3_lock_set(args: S3LockSet) {
+    let mode = args.mode.to_uppercase();
+    if mode != "COMPLIANCE" && mode != "GOVERNANCE" {
+        throw!(anyhow!("mode must be COMPLIANCE or GOVERNANCE"));
+    }
+    let retain_until = if let Ok(date) =
+        NaiveDate::parse_from_str(&args.retain_until, "%Y-%m-%d")
+    {
+        date.format("%Y-%m-%dT00:00:00Z").to_string()
+    } else {
+        (Utc::now() + parse_duration(&args.retain_until)?)
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string()
+    };
+    let body = format!(
+        "<Retention xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+         <Mode>{}</Mode><RetainUntilDate>{}</RetainUntilDate></Retention>",
+        mode, retain_until
+    );
+    let mut headers = Vec::new();
+    if args.bypass_governance {
+        headers.push(("x-amz-bypass-governance-retention", "true"));
+    }
+    s3_put_object_subresource(
+        &args.path,
+        &args.version_id,
+        "retention",
+        &headers,
+        body,
+    )?;
+    println!("{}: {} until {}", args.path, mode, retain_until);
+}
+
+#[throws]
+fn s3_lock_legal_hold(args: S3LockLegalHold) {
+    let status = match args.status.as_str() {
+        "on" | "ON" => "ON",
+        "off" | "OFF" => "OFF",
+        _ => throw!(anyhow!("legal hold status must be on or off")),
+    };
+    let body = format!(
+        "<LegalHold xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+         <Status>{}</Status></LegalHold>",
+        status
+    );
+    s3_put_object_subresource(
+        &args.path,
+        &args.version_id,
+        "legal-hold",
+        &[],
+        body,
+    )?;
+    println!("{}: legal hold {}", args.path, status);
+}
+
+/// Parse a transfer rate such as "10MB/s" or "500K" into bytes per
+/// second.
+#[throws]
+fn parse_rate(s: &str) -> u64 {
+    let rate = s.strip_suffix("/s").unwrap_or(s);
+    let rate = rate.strip_suffix('B').unwrap_or(rate);
+    let (number, multiplier) = match rate.chars().last() {
+        Some('K') | Some('k') => (&rate[..rate.len() - 1], 1024),
+        Some('M') | Some('m') => (&rate[..rate.len() - 1], 1024 * 1024),
+        Some('G') | Some('g') => (&rate[..rate.len() - 1], 1024 * 1024 * 1024),
+        _ => (rate, 1),
+    };
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("invalid rate: {}", s))?;
+    if number <= 0.0 {
+        throw!(anyhow!("rate must be positive: {}", s));
+    }
+    (number * multiplier as f64) as u64
+}
+
+/// Limits the average rate of a transfer by sleeping before each
+/// chunk is sent.
+struct RateLimiter {
+    bytes_per_sec: u64,
+    start: time::Instant,
+    sent: u64,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_sec: u64) -> RateLimiter {
+        RateLimiter {
+            bytes_per_sec,
+            start: time::Instant::now(),
+            sent: 0,
+        }
+    }
+
+    /// Wait until `bytes` more can be sent without going over the
+    /// limit.
+    fn wait(&mut self, bytes: u64) {
+        let target = time::Duration::from_secs_f64(
+            self.sent as f64 / self.bytes_per_sec as f64,
+        );
+        if let Some(delay) = target.checked_sub(self.start.elapsed()) {
+            thread::sleep(delay);
+        }
+        self.sent += bytes;
+    }
+}
+
+/// Size of the chunks that a rate-limited upload body is sent in.
+const RATE_LIMIT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Make an upload body, limiting the rate at which it's sent if there
+/// is a limiter. The data is fed to the request from another thread,
+/// so that sleeping doesn't block the HTTP client.
+fn upload_body(
+    data: Vec<u8>,
+    limiter: &Option<Arc<Mutex<RateLimiter>>>,
+) -> ByteStream {
+    let limiter = match limiter {
+        Some(limiter) => limiter.clone(),
+        None => return data.into(),
+    };
+    let (mut sender, receiver) = futures::sync::mpsc::channel(1);
+    thread::spawn(move || {
+        for chunk in data.chunks(RATE_LIMIT_CHUNK_SIZE) {
+            limiter.lock().unwrap().wait(chunk.len() as u64);
+            sender = match sender.send(Bytes::from(chunk)).wait() {
+                Ok(sender) => sender,
+                // The request was dropped
+                Err(_) => return,
+            };
+        }
+    });
+    ByteStream::new(
+        receiver.map_err(|()| std::io::Error::other("upload body failed")),
+    )
+}
+
+/// Files at least this large are uploaded in parts.
+const S3_MULTIPART_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// S3 allows at most this many parts per upload.
+const S3_MAX_PARTS: u64 = 10_000;
+
+/// Path of the file that records an in-progress multipart upload, so
+/// that it can be resumed.
+#[throws]
+fn s3_upload_state_path(bucket: &str, key: &str) -> PathBuf {
+    let name: String = format!("{}/{}", bucket, key)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    home_dir()?
+        .join(".cache/claws/uploads")
+        .join(format!("{}.json", name))
+}
+
+/// Size of each part of a multipart upload, large enough that the
+/// file fits in S3_MAX_PARTS parts.
+fn s3_part_size(size: u64) -> u64 {
+    size.div_ceil(S3_MAX_PARTS).max(8 * 1024 * 1024)
+}
+
+/// Get the upload ID and part size from the saved state of an
+/// interrupted upload, checking that the file hasn't changed since the
+/// upload started.
+#[throws]
+fn s3_resume_state(
+    state: &serde_json::Value,
+    src: &Path,
+    size: u64,
+    modified: u64,
+) -> (String, u64) {
+    if state["size"].as_u64() != Some(size)
+        || state["modified"].as_u64() != Some(modified)
+    {
+        throw!(anyhow!(
+            "{} has changed since the upload started",
+            src.display()
+        ));
+    }
+    let upload_id = state["upload_id"]
+        .as_str()
+        .context("invalid upload state")?
+        .to_string();
+    let part_size = state["part_size"]
+        .as_u64()
+        .context("invalid upload state")?;
+    (upload_id, part_size)
+}
+
+/// Get the parts of a multipart upload that have already been
+/// uploaded.
+#[throws]
+fn s3_get_uploaded_parts(
+    client: &S3Client,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+) -> Vec<CompletedPart> {
+    let mut parts = Vec::new();
+    let mut part_number_marker = None;
+    loop {
+        let output = client
+            .list_parts(ListPartsRequest {
+                bucket: bucket.into(),
+                key: key.into(),
+                upload_id: upload_id.into(),
+                part_number_marker,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list uploaded parts")?;
+        for part in output.parts.unwrap_or_default() {
+            parts.push(CompletedPart {
+                e_tag: part.e_tag,
+                part_number: part.part_number,
+            });
+        }
+
+        // Finish if there are no more results
+        if output.is_truncated != Some(true) {
+            break;
+        }
+        part_number_marker = output.next_part_number_marker;
+    }
+    parts
+}
+
+#[throws]
+fn s3_cp(args: S3Cp) {
+    let (bucket, key) = parse_s3_path(&args.dst);
+    // Copying to a bucket or "directory" keeps the file name
+    let key = match key {
+        Some(key) if !key.ends_with('/') => key,
+        prefix => {
+            let file_name = args
+                .src
+                .file_name()
+                .context("source is not a file")?
+                .to_string_lossy();
+            format!("{}{}", prefix.unwrap_or_default(), file_name)
+        }
+    };
+    let metadata = fs::metadata(&args.src)
+        .with_context(|| format!("failed to read {}", args.src.display()))?;
+    let size = metadata.len();
+    let modified = metadata
+        .modified()?
+        .duration_since(time::UNIX_EPOCH)?
+        .as_secs();
+    let client = s3_client_for_bucket(&bucket)?;
+    let limiter = args
+        .limit_rate
+        .map(|rate| Arc::new(Mutex::new(RateLimiter::new(rate))));
+
+    if size < S3_MULTIPART_THRESHOLD {
+        let data = fs::read(&args.src)?;
+        client
+            .put_object(PutObjectRequest {
+                bucket: bucket.clone(),
+                key: key.clone(),
+                content_length: Some(size as i64),
+                body: Some(upload_body(data, &limiter)),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to upload file")?;
+        println!("uploaded to s3://{}/{}", bucket, key);
+        return;
+    }
+
+    let state_path = s3_upload_state_path(&bucket, &key)?;
+    let saved: Option<serde_json::Value> = fs::read(&state_path)
+        .ok()
+        .and_then(|state| serde_json::from_slice(&state).ok());
+
+    let (upload_id, part_size, mut parts) = match saved {
+        Some(state) if args.resume => {
+            let (upload_id, part_size) =
+                s3_resume_state(&state, &args.src, size, modified)?;
+            let parts =
+                s3_get_uploaded_parts(&client, &bucket, &key, &upload_id)?;
+            println!("resuming upload with {} parts done", parts.len());
+            (upload_id, part_size, parts)
+        }
+        saved => {
+            if args.resume {
+                throw!(anyhow!("no interrupted upload to resume"));
+            }
+            // Starting over, so the old upload's parts aren't needed
+            if let Some(upload_id) =
+                saved.as_ref().and_then(|state| state["upload_id"].as_str())
+            {
+                println!("discarding interrupted upload");
+                let _ = client
+                    .abort_multipart_upload(AbortMultipartUploadRequest {
+                        bucket: bucket.clone(),
+                        key: key.clone(),
+                        upload_id: upload_id.into(),
+                        ..Default::default()
+                    })
+                    .sync();
+            }
+            let output = client
+                .create_multipart_upload(CreateMultipartUploadRequest {
+                    bucket: bucket.clone(),
+                    key: key.clone(),
+                    ..Default::default()
+                })
+                .sync()
+                .context("failed to start upload")?;
+            let upload_id = output.upload_id.context("missing upload ID")?;
+            let part_size = s3_part_size(size);
+            fs::create_dir_all(state_path.parent().context("invalid path")?)?;
+            let state = serde_json::json!({
+                "upload_id": upload_id,
+                "source": args.src,
+                "size": size,
+                "modified": modified,
+                "part_size": part_size,
+            });
+            fs::write(&state_path, state.to_string())?;
+            (upload_id, part_size, Vec::new())
+        }
+    };
+
+    let num_parts = size.div_ceil(part_size);
+    let done: HashSet<i64> =
+        parts.iter().filter_map(|part| part.part_number).collect();
+    let mut file = fs::File::open(&args.src)?;
+    for part_number in 1..=num_parts as i64 {
+        if done.contains(&part_number) {
+            continue;
+        }
+        let offset = (part_number as u64 - 1) * part_size;
+        let len = part_size.min(size - offset);
+        let mut data = vec![0; len as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        let output = client
+            .upload_part(UploadPartRequest {
+                bucket: bucket.clone(),
+                key: key.clone(),
+                upload_id: upload_id.clone(),
+                part_number,
+                content_length: Some(len as i64),
+                body: Some(upload_body(data, &limiter)),
+                ..Default::default()
+            })
+            .sync()
+            .with_context(|| {
+                format!(
+                    "failed to upload part {} (continue with --resume)",
+                    part_number
+                )
+            })?;
+        parts.push(CompletedPart {
+            e_tag: output.e_tag,
+            part_number: Some(part_number),
+        });
+        println!("uploaded part {}/{}", part_number, num_parts);
+    }
+
+    parts.sort_unstable_by_key(|part| part.part_number);
+    client
+        .complete_multipart_upload(CompleteMultipartUploadRequest {
+            bucket: bucket.clone(),
+            key: key.clone(),
+            upload_id,
+            multipart_upload: Some(CompletedMultipartUpload {
+                parts: Some(parts),
+            }),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to complete upload")?;
+    fs::remove_file(&state_path)?;
+    println!("uploaded to s3://{}/{}", bucket, key);
+}
+
+/// Get the latest daily value of a bucket's S3 storage metric, which
+/// is only published in the bucket's region.
+#[throws]
+fn s3_get_storage_metric(
+    region: &Region,
+    bucket: &str,
+    metric_name: &str,
+    storage_type: &str,
+) -> Option<f64> {
+    let client = CloudWatchClient::new(region.clone());
+    let now = Utc::now();
+    let output = client
+        .get_metric_statistics(GetMetricStatisticsInput {
+            namespace: "AWS/S3".into(),
+            metric_name: metric_name.into(),
+            dimensions: Some(vec![
+                Dimension {
+                    name: "BucketName".into(),
+                    value: bucket.into(),
+                },
+                Dimension {
+                    name: "StorageType".into(),
+                    value: storage_type.into(),
+                },
+            ]),
+            start_time: (now - Duration::days(3)).to_rfc3339(),
+            end_time: now.to_rfc3339(),
+            period: 86400,
+            statistics: Some(vec!["Average".into()]),
+            ..Default::default()
+        })
+        .sync()
+        .with_context(|| {
+            format!("failed to get {} for {}", metric_name, bucket)
+        })?;
+    output
+        .datapoints
+        .unwrap_or_default()
+        .into_iter()
+        .max_by(|a, b| a.timestamp.cmp(&b.timestamp))
+        .and_then(|point| point.average)
+}
+
+/// Format a number of bytes using binary units.
+fn format_bytes(bytes: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", value, units[unit])
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+#[throws]
+fn s3_bucket_details(name: String, created: String, size: bool) -> Vec<String> {
+    let region = s3_get_bucket_region(&name)?;
+    let mut row = vec![
+        name.clone(),
+        region.name().to_string(),
+        created.get(..10).unwrap_or_default().to_string(),
+    ];
+    if size {
+        let bytes = s3_get_storage_metric(
+            &region,
+            &name,
+            "BucketSizeBytes",
+            "StandardStorage",
+        )?;
+        let objects = s3_get_storage_metric(
+            &region,
+            &name,
+            "NumberOfObjects",
+            "AllStorageTypes",
+        )?;
+        row.push(bytes.map(format_bytes).unwrap_or_default());
+        row.push(
+            objects
+                .map(|objects| (objects as u64).to_string())
+                .unwrap_or_default(),
+        );
+    }
+    row
+}
+
+#[throws]
+fn get_account_id() -> String {
+    let client = StsClient::new(Region::default());
+    let output = client
+        .get_caller_identity(GetCallerIdentityRequest {})
+        .sync()
+        .context("failed to get caller identity")?;
+    output.account.context("missing account field")?
+}
+
+#[cfg(feature = "cost")]
+fn format_spend(spend: &Spend) -> String {
+    match spend.amount.parse::<f64>() {
+        Ok(amount) => format!("{:.2} {}", amount, spend.unit),
+        Err(_) => format!("{} {}", spend.amount, spend.unit),
+    }
+}
+
+/// Get the spend as a fraction of the limit, or None if either amount
+/// can't be parsed.
+#[cfg(feature = "cost")]
+fn spend_fraction(spend: &Spend, limit: &Spend) -> Option<f64> {
+    let spend = spend.amount.parse::<f64>().ok()?;
+    let limit = limit.amount.parse::<f64>().ok()?;
+    if limit > 0.0 {
+        Some(spend / limit)
+    } else {
+        None
+    }
+}
+
+#[cfg(feature = "cost")]
+#[throws]
+fn cost_list_budgets() {
+    let account_id = get_account_id()?;
+    // The Budgets API is only available in us-east-1
+    let client = BudgetsClient::new(Region::UsEast1);
+    struct Row {
+        name: String,
+        limit: String,
+        actual: String,
+        forecast: String,
+        warn: bool,
+    }
+    let color = std::io::stdout().is_terminal();
+    // Columns only grow, so each page lines up with the ones before
+    let mut name_width = 0;
+    let mut limit_width = 0;
+    let mut actual_width = 0;
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_budgets(DescribeBudgetsRequest {
+                account_id: account_id.clone(),
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list budgets")?;
+        let mut rows = Vec::new();
+        for budget in output.budgets.unwrap_or_default() {
+            let limit = budget.budget_limit;
+            let spend = budget.calculated_spend;
+            let actual = spend.as_ref().map(|s| &s.actual_spend);
+            let forecast =
+                spend.as_ref().and_then(|s| s.forecasted_spend.as_ref());
+
+            // Warn if either the actual or forecasted spend is over
+            // 80% of the limit
+            let warn = limit.as_ref().is_some_and(|limit| {
+                actual
+                    .into_iter()
+                    .chain(forecast)
+                    .filter_map(|spend| spend_fraction(spend, limit))
+                    .any(|fraction| fraction > 0.8)
+            });
+
+            rows.push(Row {
+                name: budget.budget_name,
+                limit: limit.as_ref().map(format_spend).unwrap_or_default(),
+                actual: actual.map(format_spend).unwrap_or_default(),
+                forecast: forecast.map(format_spend).unwrap_or_default(),
+                warn,
+            });
+        }
+
+        for row in &rows {
+            name_width = name_width.max(row.name.len());
+            limit_width = limit_width.max(row.limit.len());
+            actual_width = actual_width.max(row.actual.len());
+        }
+        for row in rows {
+            let line = format!(
+                "{:name_width$} limit: {:limit_width$} actual: \
+                 {:actual_width$} forecast: {}",
+                row.name,
+                row.limit,
+                row.actual,
+                row.forecast,
+                name_width = name_width,
+                limit_width = limit_width,
+                actual_width = actual_width
+            );
+            if row.warn && color {
+                // Yellow
+                println!("\x1b[33m{}\x1b[0m", line);
+            } else {
+                println!("{}", line);
+            }
+        }
+
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+}
+
+/// Parse a duration such as "30s", "10m", "1h", or "7d".
+#[throws]
+fn parse_duration(s: &str) -> Duration {
+    let split = s.len() - s.chars().last().map_or(0, char::len_utf8);
+    let (num, unit) = s.split_at(split);
+    let num: i64 = num
+        .parse()
+        .with_context(|| format!("invalid duration: {}", s))?;
+    match unit {
+        "s" => Duration::seconds(num),
+        "m" => Duration::minutes(num),
+        "h" => Duration::hours(num),
+        "d" => Duration::days(num),
+        "w" => Duration::weeks(num),
+        _ => throw!(anyhow!("invalid duration unit: {}", s)),
+    }
+}
+
+/// Parse the start date of a cost report. This can be
+/// "month-to-date", a duration such as "30d", or a date in YYYY-MM-DD
+/// format.
+#[cfg(feature = "cost")]
+#[throws]
+fn parse_cost_start(s: &str) -> NaiveDate {
+    let today = Utc::now().date_naive();
+    if s == "month-to-date" {
+        today.with_day(1).context("invalid date")?
+    } else if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        date
+    } else {
+        today - parse_duration(s)?
+    }
+}
+
+#[cfg(feature = "cost")]
+#[throws]
+fn cost_by_tag(args: CostByTag) {
+    let start = parse_cost_start(&args.since)?;
+    // The end date is exclusive, so use tomorrow to include today
+    let end = Utc::now().date_naive() + Duration::days(1);
+    // The Cost Explorer API is only available in us-east-1
+    let client = CostExplorerClient::new(Region::UsEast1);
+    let metric = "UnblendedCost";
+
+    let mut costs: Vec<(String, f64)> = Vec::new();
+    let mut unit = String::new();
+    let mut next_page_token = None;
+    loop {
+        let output = client
+            .get_cost_and_usage(GetCostAndUsageRequest {
+                granularity: Some("MONTHLY".into()),
+                group_by: Some(vec![GroupDefinition {
+                    key: Some(args.tag.clone()),
+                    type_: Some("TAG".into()),
+                }]),
+                metrics: Some(vec![metric.into()]),
+                next_page_token: next_page_token.clone(),
+                time_period: DateInterval {
+                    start: start.format("%Y-%m-%d").to_string(),
+                    end: end.format("%Y-%m-%d").to_string(),
+                },
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get cost and usage")?;
+        for result in output.results_by_time.unwrap_or_default() {
+            for group in result.groups.unwrap_or_default() {
+                // Tag group keys are formatted as "<tag-key>$<value>"
+                let key = group
+                    .keys
+                    .and_then(|keys| keys.into_iter().next())
+                    .unwrap_or_default();
+                let value = match key.split_once('$') {
+                    Some((_, "")) | None => "<untagged>".to_string(),
+                    Some((_, value)) => value.to_string(),
+                };
+                let metric = group
+                    .metrics
+                    .and_then(|mut metrics| metrics.remove(metric))
+                    .unwrap_or_default();
+                let amount: f64 = metric
+                    .amount
+                    .unwrap_or_default()
+                    .parse()
+                    .context("invalid cost amount")?;
+                if let Some(metric_unit) = metric.unit {
+                    unit = metric_unit;
+                }
+                // Combine the amounts from each time period
+                if let Some(cost) =
+                    costs.iter_mut().find(|(name, _)| *name == value)
+                {
+                    cost.1 += amount;
+                } else {
+                    costs.push((value, amount));
+                }
+            }
+        }
+        // Finish if there are no more results
+        if output.next_page_token.is_none() {
+            break;
+        }
+        next_page_token = output.next_page_token;
+    }
+
+    // Sort by cost, highest first
+    costs.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+
+    let total: f64 = costs.iter().map(|(_, amount)| amount).sum();
+    let name_width =
+        costs.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let amount_width = format!("{:.2}", total).len();
+
+    for (name, amount) in &costs {
+        let percent = if total > 0.0 {
+            amount / total * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "{:name_width$} {:>amount_width$.2} {} {:>5.1}%",
+            name,
+            amount,
+            unit,
+            percent,
+            name_width = name_width,
+            amount_width = amount_width
+        );
+    }
+    println!(
+        "{:name_width$} {:>amount_width$.2} {}",
+        "total",
+        total,
+        unit,
+        name_width = name_width,
+        amount_width = amount_width
+    );
+}
+
+#[throws]
+fn parse_region(region: &Option<String>) -> Region {
+    match region {
+        Some(region) => region
+            .parse()
+            .with_context(|| format!("invalid region: {}", region))?,
+        None => Region::default(),
+    }
+}
+
+/// Get products from the Pricing API, with each filter being an exact
+/// match on a product attribute.
+#[cfg(feature = "price")]
+#[throws]
+fn get_price_list(
+    service_code: &str,
+    filters: &[(&str, &str)],
+) -> Vec<serde_json::Value> {
+    // The Pricing API is only available in a couple regions
+    let client = PricingClient::new(Region::UsEast1);
+    let filters: Vec<_> = filters
+        .iter()
+        .map(|(field, value)| PricingFilter {
+            field: field.to_string(),
+            type_: "TERM_MATCH".into(),
+            value: value.to_string(),
+        })
+        .collect();
+    let mut products = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .get_products(GetProductsRequest {
+                filters: Some(filters.clone()),
+                format_version: Some("aws_v1".into()),
+                next_token: next_token.clone(),
+                service_code: Some(service_code.into()),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get products")?;
+        for item in output.price_list.unwrap_or_default() {
+            products.push(
+                serde_json::from_str(&item)
+                    .context("failed to parse price list")?,
+            );
+        }
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    products
+}
+
+/// Print the on-demand price dimensions of a product.
+#[cfg(feature = "price")]
+fn print_on_demand_prices(product: &serde_json::Value) {
+    let description = product["product"]["attributes"]["usagetype"]
+        .as_str()
+        .unwrap_or_default();
+    let terms = product["terms"]["OnDemand"].as_object();
+    for term in terms.into_iter().flat_map(|terms| terms.values()) {
+        let dimensions = term["priceDimensions"].as_object();
+        for dim in dimensions.into_iter().flat_map(|dims| dims.values()) {
+            let prices = dim["pricePerUnit"].as_object();
+            for (currency, price) in prices.into_iter().flatten() {
+                let price: f64 =
+                    price.as_str().unwrap_or_default().parse().unwrap_or(0.0);
+                // Skip zero-cost dimensions such as unused
+                // reservations
+                if price == 0.0 {
+                    continue;
+                }
+                println!(
+                    "on-demand: {} {}/{} ({})",
+                    price,
+                    currency,
+                    dim["unit"].as_str().unwrap_or_default(),
+                    description
+                );
+            }
+        }
+    }
+}
+
+/// Get the Pricing API operating system and the EC2 product
+/// description of an OS name such as "linux".
+#[throws]
+fn parse_ec2_os(os: &str) -> (&'static str, &'static str) {
+    match os.to_lowercase().as_str() {
+        "linux" => ("Linux", "Linux/UNIX"),
+        "windows" => ("Windows", "Windows"),
+        "rhel" => ("RHEL", "Red Hat Enterprise Linux"),
+        "suse" => ("SUSE", "SUSE Linux"),
+        _ => throw!(anyhow!("unknown operating system: {}", os)),
+    }
+}
+
+/// Get the current spot price of an instance type in each
+/// availability zone.
+#[throws]
+fn get_spot_prices(
+    region: Region,
+    instance_type: String,
+    product_description: &str,
+) -> Vec<SpotPrice> {
+    let client = Ec2Client::new(region);
+    // Requesting the history starting now gives the current price in
+    // each availability zone
+    let output = client
+        .describe_spot_price_history(DescribeSpotPriceHistoryRequest {
+            instance_types: Some(vec![instance_type]),
+            product_descriptions: Some(vec![product_description.into()]),
+            start_time: Some(Utc::now().to_rfc3339()),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to get spot prices")?;
+    let mut prices = output.spot_price_history.unwrap_or_default();
+    prices.sort_unstable_by_key(|price| price.availability_zone.clone());
+    prices
+}
+
+#[cfg(feature = "price")]
+#[throws]
+fn price_ec2(args: PriceEc2) {
+    let region = parse_region(&args.region)?;
+    let (os, product_description) = parse_ec2_os(&args.os)?;
+    let products = get_price_list(
+        "AmazonEC2",
+        &[
+            ("instanceType", &args.instance_type),
+            ("regionCode", region.name()),
+            ("operatingSystem", os),
+            ("tenancy", "Shared"),
+            ("preInstalledSw", "NA"),
+            ("capacitystatus", "Used"),
+        ],
+    )?;
+    if products.is_empty() {
+        throw!(anyhow!(
+            "no prices found for {} in {}",
+            args.instance_type,
+            region.name()
+        ));
+    }
+    for product in &products {
+        print_on_demand_prices(product);
+    }
+
+    if args.spot {
+        for price in
+            get_spot_prices(region, args.instance_type, product_description)?
+        {
+            println!(
+                "spot ({}): {} USD/Hrs",
+                price.availability_zone.unwrap_or_default(),
+                price.spot_price.unwrap_or_default()
+            );
+        }
+    }
+}
+
+#[cfg(feature = "price")]
+#[throws]
+fn price_ebs(args: PriceEbs) {
+    let region = parse_region(&args.region)?;
+    let products = get_price_list(
+        "AmazonEC2",
+        &[
+            ("productFamily", "Storage"),
+            ("volumeApiName", &args.volume_type),
+            ("regionCode", region.name()),
+        ],
+    )?;
+    if products.is_empty() {
+        throw!(anyhow!(
+            "no prices found for {} in {}",
+            args.volume_type,
+            region.name()
+        ));
+    }
+    for product in &products {
+        print_on_demand_prices(product);
+    }
+}
+
+/// Print rows as left-aligned columns separated by spaces.
+fn print_table(rows: &[Vec<String>]) {
+    for line in format_table(rows) {
+        println!("{}", line);
+    }
+}
+
+/// Format rows as left-aligned columns separated by spaces.
+fn format_table(rows: &[Vec<String>]) -> Vec<String> {
+    let mut widths = Vec::new();
+    update_column_widths(&mut widths, rows);
+    rows.iter()
+        .map(|row| format_table_row(row, &widths))
+        .collect()
+}
+
+/// Widen the column widths to fit the rows.
+fn update_column_widths(widths: &mut Vec<usize>, rows: &[Vec<String>]) {
+    for row in rows {
+        if widths.len() < row.len() {
+            widths.resize(row.len(), 0);
+        }
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+}
+
+/// Format one row of a table with the given column widths.
+fn format_table_row(row: &[String], widths: &[usize]) -> String {
+    let mut line = String::new();
+    for (col, (cell, width)) in row.iter().zip(widths).enumerate() {
+        if col + 1 == row.len() {
+            line.push_str(cell);
+        } else {
+            line.push_str(&format!("{:width$} ", cell, width = width));
+        }
+    }
+    line
+}
+
+/// Print a table one page of results at a time, so that list
+/// commands show output as soon as the first page arrives rather than
+/// after the last one. Column widths are set by the header and the
+/// first page, and only grow if a later page has a wider cell.
+struct TableStream {
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    widths: Vec<usize>,
+}
+
+impl TableStream {
+    fn new(header: Vec<String>) -> TableStream {
+        TableStream {
+            header: Some(header),
+            rows: Vec::new(),
+            widths: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// Print the rows pushed since the last flush. Call this after
+    /// each page of results.
+    fn flush(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        if let Some(header) = self.header.take() {
+            self.rows.insert(0, header);
+        }
+        update_column_widths(&mut self.widths, &self.rows);
+        for row in self.rows.drain(..) {
+            println!("{}", format_table_row(&row, &self.widths));
+        }
+    }
+
+    /// Print any remaining rows, and the header if there were no rows
+    /// at all.
+    fn finish(mut self) {
+        self.flush();
+        if let Some(header) = self.header.take() {
+            print_table(&[header]);
+        }
+    }
+}
+
+#[cfg(feature = "eb")]
+#[throws]
+fn eb_list_environments() {
+    let client = ElasticBeanstalkClient::new(Region::default());
+    let mut environments = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_environments(DescribeEnvironmentsMessage {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list environments")?;
+        for env in output.environments.unwrap_or_default() {
+            environments.push(vec![
+                env.environment_name.unwrap_or_default(),
+                env.status.unwrap_or_default(),
+                env.health.unwrap_or_default(),
+                env.version_label.unwrap_or_default(),
+                env.date_updated.unwrap_or_default(),
+            ]);
+        }
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    environments.sort_unstable();
+
+    let mut rows = vec![vec![
+        "NAME".to_string(),
+        "STATUS".to_string(),
+        "HEALTH".to_string(),
+        "VERSION".to_string(),
+        "UPDATED".to_string(),
+    ]];
+    rows.extend(environments);
+    print_table(&rows);
+}
+
+#[cfg(feature = "eb")]
+#[throws]
+fn eb_deploy(args: EbDeploy) {
+    let client = ElasticBeanstalkClient::new(Region::default());
+    client
+        .update_environment(UpdateEnvironmentMessage {
+            environment_name: Some(args.environment_name),
+            version_label: Some(args.version_label),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to deploy")?;
+}
+
+#[cfg(feature = "eb")]
+#[throws]
+fn eb_events(args: EbEvents) {
+    let client = ElasticBeanstalkClient::new(Region::default());
+    let mut start_time: Option<String> = None;
+    loop {
+        let output = client
+            .describe_events(DescribeEventsMessage {
+                environment_name: Some(args.environment_name.clone()),
+                start_time: start_time.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get events")?;
+        // Events are returned newest first
+        let mut events = output.events.unwrap_or_default();
+        events.reverse();
+        for event in events {
+            let date = event.event_date.unwrap_or_default();
+            // The start time is inclusive, so skip events that have
+            // already been shown
+            if start_time.as_ref().is_some_and(|start| date <= *start) {
+                continue;
+            }
+            println!(
+                "{} {:5} {}",
+                date,
+                event.severity.unwrap_or_default(),
+                event.message.unwrap_or_default()
+            );
+            start_time = Some(date);
+        }
+        if !args.follow {
+            break;
+        }
+        thread::sleep(time::Duration::from_secs(5));
+    }
+}
+
+#[throws]
+fn ai_translate(args: AiTranslate) {
+    let output = json_request(
+        "translate",
+        "translate",
+        "AWSShineFrontendService_20170701",
+        "TranslateText",
+        serde_json::json!({
+            "Text": args.text,
+            "SourceLanguageCode": args.from,
+            "TargetLanguageCode": args.to,
+        }),
+    )?;
+    println!("{}", output["TranslatedText"].as_str().unwrap_or_default());
+}
+
+#[throws]
+fn ai_speak(args: AiSpeak) {
+    let audio = send_signed_request(
+        "POST",
+        "polly",
+        "polly",
+        "/v1/speech",
+        &[],
+        Some(serde_json::json!({
+            "Text": args.text,
+            "OutputFormat": "mp3",
+            "VoiceId": args.voice,
+        })),
+    )?;
+    fs::write(&args.out, audio)
+        .with_context(|| format!("failed to write {}", args.out.display()))?;
+}
+
+#[throws]
+fn ai_transcribe(args: AiTranscribe) {
+    let job_name = format!("claws-{}", Utc::now().format("%Y%m%d%H%M%S"));
+    // Transcribe only reads media from S3, so upload local files first
+    let (media_bucket, media_key, uploaded) = if args.file.starts_with("s3://")
+    {
+        let (bucket, key) = parse_s3_object_path(&args.file)?;
+        (bucket, key, false)
+    } else {
+        let bucket = args
+            .bucket
+            .clone()
+            .context("--bucket is required to transcribe a local file")?;
+        let key = s3_upload_temp_file(
+            &bucket,
+            &format!("claws-transcribe/{}", job_name),
+            Path::new(&args.file),
+        )?;
+        (bucket, key, true)
+    };
+    // The transcript is written to the media's bucket by default
+    let bucket = args.bucket.unwrap_or_else(|| media_bucket.clone());
+    let transcript_key = format!("claws-transcribe/{}.json", job_name);
+
+    let mut payload = serde_json::json!({
+        "TranscriptionJobName": job_name,
+        "Media": {
+            "MediaFileUri": format!("s3://{}/{}", media_bucket, media_key),
+        },
+        "OutputBucketName": bucket,
+        "OutputKey": transcript_key,
+    });
+    match args.language {
+        Some(language) => payload["LanguageCode"] = language.into(),
+        None => payload["IdentifyLanguage"] = true.into(),
+    }
+    let transcribe = |action: &str, payload: serde_json::Value| {
+        json_request("transcribe", "transcribe", "Transcribe", action, payload)
+    };
+    transcribe("StartTranscriptionJob", payload)?;
+    loop {
+        thread::sleep(time::Duration::from_secs(5));
+        let output = transcribe(
+            "GetTranscriptionJob",
+            serde_json::json!({ "TranscriptionJobName": job_name }),
+        )?;
+        let job = &output["TranscriptionJob"];
+        match job["TranscriptionJobStatus"].as_str() {
+            Some("COMPLETED") => break,
+            Some("FAILED") => throw!(anyhow!(
+                "transcription failed: {}",
+                job["FailureReason"].as_str().unwrap_or_default()
+            )),
+            _ => {}
+        }
+    }
+
+    let client = s3_client_for_bucket(&bucket)?;
+    let output = client
+        .get_object(GetObjectRequest {
+            bucket: bucket.clone(),
+            key: transcript_key.clone(),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to download transcript")?;
+    let mut transcript = Vec::new();
+    output
+        .body
+        .context("missing transcript body")?
+        .into_blocking_read()
+        .read_to_end(&mut transcript)?;
+    let transcript: serde_json::Value = serde_json::from_slice(&transcript)
+        .context("failed to parse transcript")?;
+    for result in transcript["results"]["transcripts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        println!("{}", result["transcript"].as_str().unwrap_or_default());
+    }
+
+    // Clean up the files that were created for the job
+    let mut keys = vec![transcript_key];
+    if uploaded {
+        keys.push(media_key);
+    }
+    for key in keys {
+        client
+            .delete_object(DeleteObjectRequest {
+                bucket: bucket.clone(),
+                key,
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to delete temporary object")?;
+    }
+}
+
+#[throws]
+fn acm_request(action: &str, payload: serde_json::Value) -> serde_json::Value {
+    json_request("acm", "acm", "CertificateManager", action, payload)?
+}
+
+/// Send a request to the Route 53 API, which is global and uses XML.
+#[throws]
+fn route53_request(
+    method: &str,
+    path: &str,
+    params: &[(&str, &str)],
+    body: Option<String>,
+) -> XmlElement {
+    // Route 53 requests are always signed for us-east-1
+    let mut request =
+        SignedRequest::new(method, "route53", &Region::UsEast1, path);
+    for (key, value) in params {
+        request.add_param(*key, *value);
+    }
+    if let Some(body) = body {
+        request.set_content_type("application/xml".into());
+        request.set_payload(Some(body.into_bytes()));
+    }
+    let body = dispatch_request(&Client::shared(), request)
+        .with_context(|| format!("request to {} failed", path))?;
+    XmlElement::parse(&body)?
+}
+
+/// A Route 53 hosted zone.
+struct Route53Zone {
+    /// ID in the form /hostedzone/<id>.
+    id: String,
+    /// Zone name with a trailing dot.
+    name: String,
+    private: bool,
+}
+
+/// A set of DNS records with the same name and type.
+struct Route53RecordSet {
+    name: String,
+    type_: String,
+    ttl: i64,
+    values: Vec<String>,
+}
+
+#[throws]
+fn route53_get_zones() -> Vec<Route53Zone> {
+    let mut zones = Vec::new();
+    let mut marker: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(marker) = &marker {
+            params.push(("marker", marker.as_str()));
+        }
+        let output =
+            route53_request("GET", "/2013-04-01/hostedzone", &params, None)?;
+        for zone in output.items("HostedZones") {
+            zones.push(Route53Zone {
+                id: zone.child_text("Id").unwrap_or_default().into(),
+                name: zone.child_text("Name").unwrap_or_default().into(),
+                private: zone
+                    .child("Config")
+                    .and_then(|config| config.child_text("PrivateZone"))
+                    == Some("true"),
+            });
+        }
+
+        // Finish if there are no more results
+        if output.child_text("IsTruncated") != Some("true") {
+            break;
+        }
+        marker = output.child_text("NextMarker").map(String::from);
+    }
+    zones
+}
+
+/// Find the public hosted zone that a DNS name belongs to. If zones
+/// are nested, the most specific one is used.
+#[throws]
+fn route53_find_zone_id(name: &str) -> String {
+    let name = format!("{}.", name.trim_end_matches('.'));
+    route53_get_zones()?
+        .into_iter()
+        .filter(|zone| {
+            !zone.private
+                && (name == zone.name
+                    || name.ends_with(&format!(".{}", zone.name)))
+        })
+        .max_by_key(|zone| zone.name.len())
+        .map(|zone| zone.id)
+        .with_context(|| format!("no hosted zone found for {}", name))?
+}
+
+/// Get a hosted zone identified by name or ID.
+#[throws]
+fn route53_get_zone(zone: &str) -> Route53Zone {
+    let name = format!("{}.", zone.trim_end_matches('.'));
+    let mut matches: Vec<_> = route53_get_zones()?
+        .into_iter()
+        .filter(|candidate| {
+            candidate.name == name
+                || candidate.id.trim_start_matches("/hostedzone/") == zone
+        })
+        .collect();
+    // A public and a private zone can have the same name
+    match matches.len() {
+        0 => throw!(anyhow!("hosted zone not found: {}", zone)),
+        1 => matches.remove(0),
+        _ => {
+            throw!(anyhow!("multiple hosted zones named {}, use the ID", zone))
+        }
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Create or update record sets in a hosted zone.
+#[throws]
+fn route53_upsert_records(zone_id: &str, record_sets: &[Route53RecordSet]) {
+    // Keep each change batch well under the API's size limits
+    for chunk in record_sets.chunks(100) {
+        let mut changes = String::new();
+        for record_set in chunk {
+            let values: String = record_set
+                .values
+                .iter()
+                .map(|value| {
+                    format!(
+                        "<ResourceRecord><Value>{}</Value></ResourceRecord>",
+                        xml_escape(value)
+                    )
+                })
+                .collect();
+            changes.push_str(&format!(
+                "<Change><Action>UPSERT</Action><ResourceRecordSet>\
+                 <Name>{}</Name><Type>{}</Type><TTL>{}</TTL>\
+                 <ResourceRecords>{}</ResourceRecords>\
+                 </ResourceRecordSet></Change>",
+                xml_escape(&record_set.name),
+                record_set.type_,
+                record_set.ttl,
+                values
+            ));
+        }
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <ChangeResourceRecordSetsRequest \
+             xmlns=\"https://route53.amazonaws.com/doc/2013-04-01/\">\
+             <ChangeBatch><Changes>{}</Changes></ChangeBatch>\
+             </ChangeResourceRecordSetsRequest>",
+            changes
+        );
+        // Zone IDs are returned as /hostedzone/<id>
+        let path = format!("/2013-04-01{}/rrset", zone_id);
+        route53_request("POST", &path, &[], Some(body))?;
+    }
+}
+
+/// Replace the octal escapes that Route 53 uses in record names,
+/// such as \052 for "*".
+fn route53_unescape(name: &str) -> String {
+    let mut output = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let digits: String = chars.clone().take(3).collect();
+            if digits.len() == 3 {
+                if let Ok(code) = u8::from_str_radix(&digits, 8) {
+                    output.push(code as char);
+                    for _ in 0..3 {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+        }
+        output.push(c);
+    }
+    output
+}
+
+#[throws]
+fn route53_export(zone: String) {
+    let zone = route53_get_zone(&zone)?;
+    println!("; exported from hosted zone {}", zone.id);
+    println!("$ORIGIN {}", zone.name);
+    let path = format!("/2013-04-01{}/rrset", zone.id);
+    let mut next: Option<(String, String)> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some((name, type_)) = &next {
+            params.push(("name", name.as_str()));
+            params.push(("type", type_.as_str()));
+        }
+        let output = route53_request("GET", &path, &params, None)?;
+        for record_set in output.items("ResourceRecordSets") {
+            let name = route53_unescape(
+                record_set.child_text("Name").unwrap_or_default(),
+            );
+            let type_ = record_set.child_text("Type").unwrap_or_default();
+            // Alias records are specific to Route 53 and have no
+            // zone file equivalent
+            if let Some(alias) = record_set.child("AliasTarget") {
+                eprintln!("skipping alias record {} {}", name, type_);
+                println!(
+                    "; ALIAS {} {} {}",
+                    name,
+                    type_,
+                    alias.child_text("DNSName").unwrap_or_default()
+                );
+                continue;
+            }
+            let ttl = record_set.child_text("TTL").unwrap_or_default();
+            for record in record_set.items("ResourceRecords") {
+                println!(
+                    "{} {} IN {} {}",
+                    name,
+                    ttl,
+                    type_,
+                    record.child_text("Value").unwrap_or_default()
+                );
+            }
+        }
+
+        // Finish if there are no more results
+        if output.child_text("IsTruncated") != Some("true") {
+            break;
+        }
+        next = Some((
+            output
+                .child_text("NextRecordName")
+                .unwrap_or_default()
+                .into(),
+            output
+                .child_text("NextRecordType")
+                .unwrap_or_default()
+                .into(),
+        ));
+    }
+}
+
+/// Split a zone file line into tokens, keeping quoted strings (which
+/// may contain spaces and semicolons) together.
+fn split_zone_line(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        if escaped {
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            current.push(c);
+            escaped = true;
+        } else if c == '"' {
+            current.push(c);
+            quoted = !quoted;
+        } else if quoted {
+            current.push(c);
+        } else if c == ';' {
+            break;
+        } else if c.is_whitespace() || c == '(' || c == ')' {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            if c == '(' || c == ')' {
+                tokens.push(c.to_string());
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Make a zone file name absolute.
+fn qualify_zone_name(name: &str, origin: &str) -> String {
+    if name == "@" {
+        origin.to_string()
+    } else if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{}.{}", name, origin)
+    }
+}
+
+/// Parse a zone file into record sets. The SOA record and the NS
+/// records of the zone apex are skipped because Route 53 manages
+/// them.
+#[throws]
+fn parse_zone_file(text: &str, zone_name: &str) -> Vec<Route53RecordSet> {
+    let mut origin = zone_name.to_string();
+    let mut default_ttl = 300;
+    let mut owner = origin.clone();
+    let mut record_sets: Vec<Route53RecordSet> = Vec::new();
+
+    // Join lines continued with parentheses
+    let mut entries = Vec::new();
+    let mut pending: Option<(bool, Vec<String>)> = None;
+    for line in text.lines() {
+        let tokens = split_zone_line(line);
+        let (blank_owner, mut all) = match pending.take() {
+            Some((blank_owner, all)) => (blank_owner, all),
+            None if tokens.is_empty() => continue,
+            None => (line.starts_with(char::is_whitespace), Vec::new()),
+        };
+        all.extend(tokens);
+        let depth = all.iter().filter(|t| *t == "(").count() as i64
+            - all.iter().filter(|t| *t == ")").count() as i64;
+        if depth > 0 {
+            pending = Some((blank_owner, all));
+        } else {
+            all.retain(|t| t != "(" && t != ")");
+            entries.push((blank_owner, all));
+        }
+    }
+
+    for (blank_owner, tokens) in entries {
+        let mut tokens = tokens.into_iter().peekable();
+        match tokens.peek().map(String::as_str) {
+            Some("$ORIGIN") => {
+                tokens.next();
+                let name = tokens.next().context("missing $ORIGIN value")?;
+                origin = qualify_zone_name(&name, &origin);
+                continue;
+            }
+            Some("$TTL") => {
+                tokens.next();
+                default_ttl = tokens
+                    .next()
+                    .context("missing $TTL value")?
+                    .parse()
+                    .context("invalid $TTL value")?;
+                continue;
+            }
+            _ => {}
+        }
+        if !blank_owner {
+            let name = tokens.next().context("missing record name")?;
+            owner = qualify_zone_name(&name, &origin);
+        }
+
+        // The TTL and class are optional and can be in either order
+        let mut ttl = default_ttl;
+        let type_ = loop {
+            let token = tokens.next().context("missing record type")?;
+            if let Ok(value) = token.parse() {
+                ttl = value;
+            } else if !token.eq_ignore_ascii_case("IN") {
+                break token.to_uppercase();
+            }
+        };
+        let mut rdata: Vec<String> = tokens.collect();
+
+        if type_ == "SOA" || (type_ == "NS" && owner == zone_name) {
+            eprintln!("skipping {} {}", owner, type_);
+            continue;
+        }
+        // Names in the record data must also be made absolute
+        let name_index = match type_.as_str() {
+            "CNAME" | "NS" | "PTR" => Some(0),
+            "MX" => Some(1),
+            "SRV" => Some(3),
+            _ => None,
+        };
+        if let Some(name) = name_index.and_then(|i| rdata.get_mut(i)) {
+            *name = qualify_zone_name(name, &origin);
+        }
+        let value = rdata.join(" ");
+
+        match record_sets
+            .iter_mut()
+            .find(|set| set.name == owner && set.type_ == type_)
+        {
+            Some(set) => set.values.push(value),
+            None => record_sets.push(Route53RecordSet {
+                name: owner.clone(),
+                type_,
+                ttl,
+                values: vec![value],
+            }),
+        }
+    }
+    record_sets
+}
+
+#[throws]
+fn route53_import(args: Route53Import) {
+    let zone = route53_get_zone(&args.zone)?;
+    let text = fs::read_to_string(&args.file)
+        .with_context(|| format!("failed to read {}", args.file.display()))?;
+    let record_sets = parse_zone_file(&text, &zone.name)?;
+    for record_set in &record_sets {
+        for value in &record_set.values {
+            println!(
+                "{}{} {} IN {} {}",
+                if args.dry_run {
+                    "would upsert "
+                } else {
+                    "upsert "
+                },
+                record_set.name,
+                record_set.ttl,
+                record_set.type_,
+                value
+            );
+        }
+    }
+    if !args.dry_run {
+        route53_upsert_records(&zone.id, &record_sets)?;
+    }
+}
+
+/// Describe a health check's target, such as
+/// "HTTPS example.com:443/health".
+fn route53_health_check_target(config: &XmlElement) -> String {
+    let type_ = config.child_text("Type").unwrap_or_default();
+    let host = config
+        .child_text("FullyQualifiedDomainName")
+        .or_else(|| config.child_text("IPAddress"));
+    match host {
+        Some(host) => format!(
+            "{} {}:{}{}",
+            type_,
+            host,
+            config.child_text("Port").unwrap_or_default(),
+            config.child_text("ResourcePath").unwrap_or_default()
+        ),
+        None => type_.to_string(),
+    }
+}
+
+#[throws]
+fn route53_health_checks() {
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "TARGET".to_string(),
+        "HEALTHY CHECKERS".to_string(),
+        "FAILURES".to_string(),
+    ]);
+    let mut marker: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(marker) = &marker {
+            params.push(("marker", marker.as_str()));
+        }
+        let output =
+            route53_request("GET", "/2013-04-01/healthcheck", &params, None)?;
+        let mut checks = Vec::new();
+        for check in output.items("HealthChecks") {
+            let config = check.child("HealthCheckConfig");
+            checks.push((
+                check.child_text("Id").unwrap_or_default().to_string(),
+                config.map(route53_health_check_target).unwrap_or_default(),
+            ));
+        }
+
+        for (id, target) in checks {
+            // Each health checker region reports its own latest status
+            let statuses = route53_request(
+                "GET",
+                &format!("/2013-04-01/healthcheck/{}/status", id),
+                &[],
+                None,
+            )?;
+            let observations = statuses.items("HealthCheckObservations");
+            let mut healthy = 0;
+            let mut failures = BTreeSet::new();
+            for observation in &observations {
+                let status = observation
+                    .child("StatusReport")
+                    .and_then(|report| report.child_text("Status"))
+                    .unwrap_or_default();
+                if status.starts_with("Success") {
+                    healthy += 1;
+                } else if let Some(reason) = status.strip_prefix("Failure: ") {
+                    failures.insert(reason.trim().to_string());
+                }
+            }
+            // Calculated and CloudWatch alarm checks have no checkers
+            let health = if observations.is_empty() {
+                String::new()
+            } else {
+                format!("{}/{}", healthy, observations.len())
+            };
+            table.push(vec![
+                id,
+                target,
+                health,
+                failures.into_iter().collect::<Vec<_>>().join("; "),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        if output.child_text("IsTruncated") != Some("true") {
+            break;
+        }
+        marker = output.child_text("NextMarker").map(String::from);
+    }
+    table.finish();
+}
+
+/// Query a DNS server with dig, returning the sorted answers.
+#[throws]
+fn dig_answers(name: &str, type_: &str, server: &str) -> Vec<String> {
+    let output =
+        run_lookup("dig", &["+short", name, type_, &format!("@{}", server)])?;
+    let mut answers: Vec<_> = output
+        .lines()
+        .map(normalize_dns_answer)
+        .filter(|line| !line.is_empty())
+        .collect();
+    answers.sort_unstable();
+    answers
+}
+
+/// Normalize a DNS answer for comparison, ignoring case and the
+/// trailing dot of names.
+fn normalize_dns_answer(answer: &str) -> String {
+    let answer = answer.trim();
+    if answer.starts_with('"') {
+        answer.to_string()
+    } else {
+        answer.trim_end_matches('.').to_lowercase()
+    }
+}
+
+#[throws]
+fn route53_check(name: String, type_: String) {
+    let type_ = type_.to_uppercase();
+    let zone_id = route53_find_zone_id(&name)?;
+    let fqdn = format!("{}.", name.trim_end_matches('.'));
+
+    // Get the record as configured in Route 53
+    let output = route53_request(
+        "GET",
+        &format!("/2013-04-01{}/rrset", zone_id),
+        &[("name", &fqdn), ("type", &type_), ("maxitems", "1")],
+        None,
+    )?;
+    let record_set =
+        output.items("ResourceRecordSets").into_iter().find(|set| {
+            set.child_text("Name").map(route53_unescape).as_deref()
+                == Some(&fqdn)
+                && set.child_text("Type") == Some(type_.as_str())
+        });
+    let mut expected: Option<Vec<String>> = record_set
+        .filter(|set| set.child("AliasTarget").is_none())
+        .map(|set| {
+            let mut values: Vec<_> = set
+                .items("ResourceRecords")
+                .iter()
+                .map(|record| {
+                    normalize_dns_answer(
+                        record.child_text("Value").unwrap_or_default(),
+                    )
+                })
+                .collect();
+            values.sort_unstable();
+            values
+        });
+    match (record_set, &expected) {
+        (None, _) => {
+            println!("no {} record for {} in {}", type_, fqdn, zone_id)
+        }
+        (Some(_), None) => {
+            println!(
+                "{} is an alias record, comparing with the zone's name servers",
+                fqdn
+            )
+        }
+        (Some(_), Some(values)) => println!("route53: {}", values.join(", ")),
+    }
+
+    let zone =
+        route53_request("GET", &format!("/2013-04-01{}", zone_id), &[], None)?;
+    let name_servers: Vec<_> = zone
+        .child("DelegationSet")
+        .map(|set| set.items("NameServers"))
+        .unwrap_or_default()
+        .iter()
+        .map(|server| server.text.clone())
+        .collect();
+    let public_resolvers = [
+        ("google", "8.8.8.8"),
+        ("cloudflare", "1.1.1.1"),
+        ("quad9", "9.9.9.9"),
+        ("opendns", "208.67.222.222"),
+    ];
+    let servers = name_servers
+        .iter()
+        .map(|server| (server.as_str(), server.as_str()))
+        .chain(public_resolvers.iter().copied());
+
+    let mut rows = vec![vec![
+        "SERVER".to_string(),
+        "ANSWER".to_string(),
+        "MATCH".to_string(),
+    ]];
+    let mut all_match = true;
+    for (label, server) in servers {
+        let (answer, matches) = match dig_answers(&fqdn, &type_, server) {
+            Ok(answers) => {
+                // Alias records are compared with the first
+                // authoritative answer
+                let expected = expected.get_or_insert_with(|| answers.clone());
+                let matches = &answers == expected;
+                (answers.join(", "), matches)
+            }
+            Err(err) => (format!("{:#}", err), false),
+        };
+        all_match &= matches;
+        rows.push(vec![
+            label.to_string(),
+            answer,
+            if matches { "yes" } else { "no" }.into(),
+        ]);
+    }
+    print_table(&rows);
+    if !all_match {
+        throw!(anyhow!("not all servers return the expected answer"));
+    }
+}
+
+#[throws]
+fn acm_describe_certificate(arn: &str) -> serde_json::Value {
+    let mut output = acm_request(
+        "DescribeCertificate",
+        serde_json::json!({ "CertificateArn": arn }),
+    )?;
+    output["Certificate"].take()
+}
+
+#[throws]
+fn acm_request_certificate(args: AcmRequest) {
+    let mut payload = serde_json::json!({
+        "DomainName": args.domain,
+        "ValidationMethod": "DNS",
+    });
+    if !args.sans.is_empty() {
+        payload["SubjectAlternativeNames"] = serde_json::json!(args.sans);
+    }
+    let output = acm_request("RequestCertificate", payload)?;
+    let arn = output["CertificateArn"]
+        .as_str()
+        .context("missing certificate ARN")?
+        .to_string();
+    println!("{}", arn);
+    if args.copy {
+        copy_to_clipboard(&arn)?;
+    }
+
+    // The validation records are filled in shortly after the request
+    let options = loop {
+        let certificate = acm_describe_certificate(&arn)?;
+        let options =
+            certificate["DomainValidationOptions"].as_array().cloned();
+        if let Some(options) = options {
+            if !options.is_empty()
+                && options
+                    .iter()
+                    .all(|option| option["ResourceRecord"].is_object())
+            {
+                break options;
+            }
+        }
+        thread::sleep(time::Duration::from_secs(2));
+    };
+
+    // Domains can share a validation record, such as example.com
+    // and *.example.com
+    let mut records = BTreeMap::new();
+    for option in &options {
+        let record = &option["ResourceRecord"];
+        records.insert(
+            record["Name"].as_str().unwrap_or_default().to_string(),
+            (
+                option["DomainName"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                record["Type"].as_str().unwrap_or_default().to_string(),
+                record["Value"].as_str().unwrap_or_default().to_string(),
+            ),
+        );
+    }
+
+    if !args.validate_route53 {
+        println!("create these records to validate the certificate:");
+        for (name, (_, type_, value)) in &records {
+            println!("  {} {} {}", name, type_, value);
+        }
+        return;
+    }
+
+    for (name, (domain, type_, value)) in &records {
+        let zone_id = route53_find_zone_id(domain.trim_start_matches("*."))?;
+        route53_upsert_records(
+            &zone_id,
+            &[Route53RecordSet {
+                name: name.clone(),
+                type_: type_.clone(),
+                ttl: 300,
+                values: vec![value.clone()],
+            }],
+        )?;
+        println!("created {} in {}", name, zone_id);
+    }
+
+    loop {
+        let certificate = acm_describe_certificate(&arn)?;
+        match certificate["Status"].as_str().unwrap_or_default() {
+            "ISSUED" => break,
+            "PENDING_VALIDATION" => {
+                thread::sleep(time::Duration::from_secs(10))
+            }
+            status => throw!(anyhow!("certificate is {}", status)),
+        }
+    }
+    println!("issued");
+}
+
+#[throws]
+fn amplify_request(
+    method: &str,
+    path: &str,
+    params: &[(&str, &str)],
+    payload: Option<serde_json::Value>,
+) -> serde_json::Value {
+    let output = send_signed_request(
+        method, "amplify", "amplify", path, params, payload,
+    )?;
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+#[throws]
+fn amplify_app_pages(
+    mut each_page: impl FnMut(Vec<serde_json::Value>) -> Result<()>,
+) {
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(token) = &next_token {
+            params.push(("nextToken", token.as_str()));
+        }
+        let mut output = amplify_request("GET", "/apps", &params, None)?;
+        each_page(
+            output["apps"]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        )?;
+
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+}
+
+#[throws]
+fn amplify_get_apps() -> Vec<serde_json::Value> {
+    let mut apps = Vec::new();
+    amplify_app_pages(|mut page| {
+        apps.append(&mut page);
+        Ok(())
+    })?;
+    apps
+}
+
+/// Get the ID of an app identified by name or ID.
+#[throws]
+fn amplify_find_app_id(app: &str) -> String {
+    amplify_get_apps()?
+        .into_iter()
+        .find(|candidate| candidate["appId"] == app || candidate["name"] == app)
+        .and_then(|candidate| candidate["appId"].as_str().map(String::from))
+        .with_context(|| format!("app not found: {}", app))?
+}
+
+#[throws]
+fn amplify_apps() {
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "DOMAIN".to_string(),
+        "REPOSITORY".to_string(),
+    ]);
+    amplify_app_pages(|apps| {
+        for app in apps {
+            table.push(vec![
+                app["appId"].as_str().unwrap_or_default().into(),
+                app["name"].as_str().unwrap_or_default().into(),
+                app["defaultDomain"].as_str().unwrap_or_default().into(),
+                app["repository"].as_str().unwrap_or_default().into(),
+            ]);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+}
+
+#[throws]
+fn amplify_builds(app: String, branch: String) {
+    let app_id = amplify_find_app_id(&app)?;
+    let output = amplify_request(
+        "GET",
+        &format!("/apps/{}/branches/{}/jobs", app_id, branch),
+        &[],
+        None,
+    )?;
+    let mut rows = vec![vec![
+        "JOB".to_string(),
+        "STATUS".to_string(),
+        "STARTED".to_string(),
+        "DURATION".to_string(),
+        "COMMIT".to_string(),
+    ]];
+    for job in output["jobSummaries"].as_array().into_iter().flatten() {
+        let start = job["startTime"].as_f64();
+        let duration = match (start, job["endTime"].as_f64()) {
+            (Some(start), Some(end)) => {
+                format_duration_short(Duration::seconds((end - start) as i64))
+            }
+            _ => String::new(),
+        };
+        let commit_id = job["commitId"].as_str().unwrap_or_default();
+        let message = job["commitMessage"]
+            .as_str()
+            .and_then(|message| message.lines().next())
+            .unwrap_or_default();
+        rows.push(vec![
+            job["jobId"].as_str().unwrap_or_default().into(),
+            job["status"].as_str().unwrap_or_default().into(),
+            start.map(format_timestamp_secs).unwrap_or_default(),
+            duration,
+            format!("{} {}", commit_id.get(..8).unwrap_or(commit_id), message),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn amplify_start_build(app: String, branch: String) {
+    let app_id = amplify_find_app_id(&app)?;
+    let output = amplify_request(
+        "POST",
+        &format!("/apps/{}/branches/{}/jobs", app_id, branch),
+        &[],
+        Some(serde_json::json!({ "jobType": "RELEASE" })),
+    )?;
+    println!(
+        "{}",
+        output["jobSummary"]["jobId"].as_str().unwrap_or_default()
+    );
+}
+
+/// An API Gateway API. REST APIs come from the v1 API, HTTP and
+/// WebSocket APIs come from the v2 API.
+#[cfg(feature = "apigw")]
+struct GatewayApi {
+    id: String,
+    name: String,
+    protocol: String,
+    /// Only set for HTTP and WebSocket APIs
+    endpoint: Option<String>,
+}
+
+#[cfg(feature = "apigw")]
+struct GatewayStage {
+    name: String,
+    deployment_id: Option<String>,
+    variables: BTreeMap<String, String>,
+    logging_level: Option<String>,
+    data_trace: Option<bool>,
+    access_log_arn: Option<String>,
+}
+
+#[cfg(feature = "apigw")]
+#[throws]
+fn apigw_get_apis() -> Vec<GatewayApi> {
+    let mut apis = Vec::new();
+
+    let client = ApiGatewayClient::new(Region::default());
+    let mut position = None;
+    loop {
+        let output = client
+            .get_rest_apis(GetRestApisRequest {
+                position: position.clone(),
+                limit: Some(500),
+            })
+            .sync()
+            .context("failed to list REST APIs")?;
+        for api in output.items.unwrap_or_default() {
+            apis.push(GatewayApi {
+                id: api.id.context("missing API ID")?,
+                name: api.name.unwrap_or_default(),
+                protocol: "REST".into(),
+                endpoint: None,
+            });
+        }
+        // Finish if there are no more results
+        if output.position.is_none() {
+            break;
+        }
+        position = output.position;
+    }
+
+    let client = ApiGatewayV2Client::new(Region::default());
+    let mut next_token = None;
+    loop {
+        let output = client
+            .get_apis(GetApisRequest {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list HTTP APIs")?;
+        for api in output.items.unwrap_or_default() {
+            apis.push(GatewayApi {
+                id: api.api_id.context("missing API ID")?,
+                name: api.name,
+                protocol: api.protocol_type,
+                endpoint: api.api_endpoint,
+            });
+        }
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+
+    apis
+}
+
+/// Find an API by ID or name.
+#[cfg(feature = "apigw")]
+#[throws]
+fn apigw_find_api(api: &str) -> GatewayApi {
+    let mut matches: Vec<_> = apigw_get_apis()?
+        .into_iter()
+        .filter(|a| a.id == api || a.name == api)
+        .collect();
+    match matches.len() {
+        0 => throw!(anyhow!("API not found: {}", api)),
+        1 => matches.remove(0),
+        _ => throw!(anyhow!("multiple APIs named {}, use the ID instead", api)),
+    }
+}
+
+#[cfg(feature = "apigw")]
+#[throws]
+fn apigw_get_stages(api: &GatewayApi) -> Vec<GatewayStage> {
+    let mut stages = Vec::new();
+    if api.protocol == "REST" {
+        let client = ApiGatewayClient::new(Region::default());
+        let output = client
+            .get_stages(GetRestStagesRequest {
+                rest_api_id: api.id.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list stages")?;
+        for stage in output.item.unwrap_or_default() {
+            // "*/*" holds the settings for all methods
+            let settings = stage
+                .method_settings
+                .and_then(|mut settings| settings.remove("*/*"));
+            stages.push(GatewayStage {
+                name: stage.stage_name.context("missing stage name")?,
+                deployment_id: stage.deployment_id,
+                variables: stage
+                    .variables
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect(),
+                logging_level: settings
+                    .as_ref()
+                    .and_then(|s| s.logging_level.clone()),
+                data_trace: settings.and_then(|s| s.data_trace_enabled),
+                access_log_arn: stage
+                    .access_log_settings
+                    .and_then(|s| s.destination_arn),
+            });
+        }
+    } else {
+        let client = ApiGatewayV2Client::new(Region::default());
+        let mut next_token = None;
+        loop {
+            let output = client
+                .get_stages(GetHttpStagesRequest {
+                    api_id: api.id.clone(),
+                    next_token: next_token.clone(),
+                    ..Default::default()
+                })
+                .sync()
+                .context("failed to list stages")?;
+            for stage in output.items.unwrap_or_default() {
+                let settings = stage.default_route_settings;
+                stages.push(GatewayStage {
+                    name: stage.stage_name,
+                    deployment_id: stage.deployment_id,
+                    variables: stage
+                        .stage_variables
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect(),
+                    logging_level: settings
+                        .as_ref()
+                        .and_then(|s| s.logging_level.clone()),
+                    data_trace: settings.and_then(|s| s.data_trace_enabled),
+                    access_log_arn: stage
+                        .access_log_settings
+                        .and_then(|s| s.destination_arn),
+                });
+            }
+            // Finish if there are no more results
+            if output.next_token.is_none() {
+                break;
+            }
+            next_token = output.next_token;
+        }
+    }
+    stages
+}
+
+#[cfg(feature = "apigw")]
+#[throws]
+fn apigw_list_apis() {
+    let mut rows: Vec<_> = apigw_get_apis()?
+        .into_iter()
+        .map(|api| vec![api.id, api.protocol, api.name])
+        .collect();
+    rows.sort_unstable_by(|a, b| a[2].cmp(&b[2]));
+    print_table(&rows);
+}
+
+#[cfg(feature = "apigw")]
+#[throws]
+fn apigw_list_stages(api: String) {
+    let api = apigw_find_api(&api)?;
+    for stage in apigw_get_stages(&api)? {
+        println!("{}:", stage.name);
+        println!("  deployment: {}", stage.deployment_id.unwrap_or_default());
+        println!(
+            "  logging level: {}",
+            stage.logging_level.unwrap_or_else(|| "OFF".into())
+        );
+        println!("  data trace: {}", stage.data_trace.unwrap_or(false));
+        println!("  access log: {}", stage.access_log_arn.unwrap_or_default());
+        if !stage.variables.is_empty() {
+            println!("  variables:");
+            for (key, value) in stage.variables {
+                println!("    {}={}", key, value);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "apigw")]
+#[throws]
+fn apigw_invoke_url(args: ApigwStage) {
+    let api = apigw_find_api(&args.api)?;
+    let url = match api.endpoint {
+        // The $default stage of an HTTP API is served from the root
+        Some(endpoint) if args.stage == "$default" => endpoint,
+        Some(endpoint) => format!("{}/{}", endpoint, args.stage),
+        None => format!(
+            "https://{}.execute-api.{}.amazonaws.com/{}",
+            api.id,
+            Region::default().name(),
+            args.stage
+        ),
+    };
+    println!("{}", url);
+    if args.copy {
+        copy_to_clipboard(&url)?;
+    }
+}
+
+/// Format an API Gateway log event with its request ID in a
+/// separate column. Execution log messages start with the request ID
+/// in parentheses, JSON access log messages usually have a
+/// "requestId" field.
+#[cfg(feature = "apigw")]
+fn format_apigw_log_event(event: &FilteredLogEvent) -> String {
+    let message = event.message.as_deref().unwrap_or_default().trim_end();
+    let (request_id, message) = if let Some(rest) = message.strip_prefix('(') {
+        match rest.split_once(") ") {
+            Some((id, rest)) => (id.to_string(), rest),
+            None => (String::new(), message),
+        }
+    } else if let Ok(json) = serde_json::from_str::<serde_json::Value>(message)
+    {
+        let id = json["requestId"].as_str().unwrap_or_default().to_string();
+        (id, message)
+    } else {
+        (String::new(), message)
+    };
+    format!(
+        "{} {:36} {}",
+        format_timestamp_millis(event.timestamp.unwrap_or_default()),
+        request_id,
+        message
+    )
+}
+
+#[cfg(feature = "apigw")]
+#[throws]
+fn apigw_logs(args: ApigwLogs) {
+    let api = apigw_find_api(&args.api)?;
+    // HTTP APIs only have access logs
+    let log_group_name = if args.access || api.protocol != "REST" {
+        let stage = apigw_get_stages(&api)?
+            .into_iter()
+            .find(|stage| stage.name == args.stage)
+            .with_context(|| format!("stage not found: {}", args.stage))?;
+        let arn = stage
+            .access_log_arn
+            .context("access logging is not enabled for this stage")?;
+        // The ARN looks like
+        // "arn:aws:logs:<region>:<account>:log-group:<name>[:*]"
+        let name = arn
+            .split_once(":log-group:")
+            .map(|(_, name)| name)
+            .context("invalid log group ARN")?;
+        name.trim_end_matches(":*").to_string()
+    } else {
+        format!("API-Gateway-Execution-Logs_{}/{}", api.id, args.stage)
+    };
+    tail_log_group(
+        &log_group_name,
+        &args.since,
+        None,
+        args.follow,
+        format_apigw_log_event,
+    )?;
+}
+
+/// Send a signed request to an API that the rusoto crates don't cover
+/// and return the response body. The `endpoint_prefix` is the first
+/// part of the service's hostname, which for some services differs
+/// from the name used for signing.
+#[throws]
+fn send_signed_request(
+    method: &str,
+    service: &str,
+    endpoint_prefix: &str,
+    path: &str,
+    params: &[(&str, &str)],
+    payload: Option<serde_json::Value>,
+) -> Vec<u8> {
+    let mut request =
+        SignedRequest::new(method, service, &Region::default(), path);
+    request.set_endpoint_prefix(endpoint_prefix.into());
+    for (key, value) in params {
+        request.add_param(*key, *value);
+    }
+    if let Some(payload) = payload {
+        request.set_content_type("application/json".into());
+        request.set_payload(Some(serde_json::to_vec(&payload)?));
+    }
+    dispatch_request(&Client::shared(), request)
+        .with_context(|| format!("request to {} failed", path))?
+}
+
+/// Send a request and return the response body.
+// The error type is defined by rusoto
+#[allow(clippy::result_large_err)]
+fn dispatch_request(
+    client: &Client,
+    request: SignedRequest,
+) -> Result<Vec<u8>, RusotoError<Infallible>> {
+    client
+        .sign_and_dispatch(request, |response| {
+            Box::new(response.buffer().from_err().and_then(|response| {
+                if response.status.is_success() {
+                    Ok(response.body.to_vec())
+                } else {
+                    Err(RusotoError::Unknown(response))
+                }
+            }))
+        })
+        .sync()
+}
+
+/// Send a request and return the response body as a stream, for
+/// responses that arrive incrementally.
+// The error type is defined by rusoto
+#[allow(clippy::result_large_err)]
+fn dispatch_streaming_request(
+    client: &Client,
+    request: SignedRequest,
+) -> Result<ByteStream, RusotoError<Infallible>> {
+    client
+        .sign_and_dispatch(request, |response| {
+            if response.status.is_success() {
+                Box::new(futures::future::ok(response.body))
+            } else {
+                Box::new(
+                    response.buffer().from_err().and_then(|response| {
+                        Err(RusotoError::Unknown(response))
+                    }),
+                )
+            }
+        })
+        .sync()
+}
+
+/// Split the first message off of a buffer of AWS event stream data,
+/// returning its string headers and its payload. Returns None if the
+/// buffer doesn't contain a complete message yet. Checksums aren't
+/// verified.
+#[throws]
+fn parse_event_stream_message(
+    buf: &mut Vec<u8>,
+) -> Option<(HashMap<String, String>, Vec<u8>)> {
+    let read_u32 = |bytes: &[u8]| {
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+    };
+    // The prelude is the total length, header length, and a checksum
+    if buf.len() < 12 || buf.len() < read_u32(&buf[0..4]) {
+        return None;
+    }
+    let total_len = read_u32(&buf[0..4]);
+    let headers_end = 12 + read_u32(&buf[4..8]);
+    if total_len < headers_end + 4 {
+        throw!(anyhow!("malformed event stream message"));
+    }
+    let message: Vec<u8> = buf.drain(..total_len).collect();
+    let slice = |start: usize, len: usize| {
+        message
+            .get(start..start + len)
+            .context("malformed event stream header")
+    };
+
+    let mut headers = HashMap::new();
+    let mut pos = 12;
+    while pos < headers_end {
+        let name_len = slice(pos, 1)?[0] as usize;
+        let name = String::from_utf8_lossy(slice(pos + 1, name_len)?);
+        pos += 1 + name_len;
+        let value_type = slice(pos, 1)?[0];
+        pos += 1;
+        let value_len = match value_type {
+            // Booleans are stored in the type
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            // Byte arrays and strings are prefixed with their length
+            _ => {
+                let len = slice(pos, 2)?;
+                pos += 2;
+                u16::from_be_bytes([len[0], len[1]]) as usize
+            }
+        };
+        if value_type == 7 {
+            let value = String::from_utf8_lossy(slice(pos, value_len)?);
+            headers.insert(name.into_owned(), value.into_owned());
+        }
+        pos += value_len;
+    }
+    Some((headers, message[headers_end..total_len - 4].to_vec()))
+}
+
+/// An element of a parsed XML document.
+#[derive(Debug, Default)]
+struct XmlElement {
+    name: String,
+    text: String,
+    children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    #[throws]
+    fn parse(input: &[u8]) -> XmlElement {
+        let mut stack = vec![XmlElement::default()];
+        for event in xml::reader::EventReader::new(input) {
+            match event.context("invalid XML response")? {
+                XmlEvent::StartElement { name, .. } => {
+                    stack.push(XmlElement {
+                        name: name.local_name,
+                        ..Default::default()
+                    });
+                }
+                XmlEvent::Characters(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().context("unbalanced XML")?;
+                    stack
+                        .last_mut()
+                        .context("unbalanced XML")?
+                        .children
+                        .push(element);
+                }
+                _ => {}
+            }
+        }
+        let mut document = stack.pop().context("empty XML document")?;
+        document.children.pop().context("empty XML document")?
+    }
+
+    /// Get the first direct child with the given name.
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Get the text of the first direct child with the given name.
+    fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.as_str())
+    }
+
+    /// Get the items of a list element such as `<fooSet>`.
+    fn items(&self, name: &str) -> Vec<&XmlElement> {
+        self.child(name)
+            .map(|child| child.children.iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Call an action of a query-protocol API and parse the XML response.
+#[throws]
+fn query_request(
+    service: &str,
+    region: &Region,
+    version: &str,
+    action: &str,
+    params: &[(&str, &str)],
+) -> XmlElement {
+    let mut request = SignedRequest::new("GET", service, region, "/");
+    request.add_param("Action", action);
+    request.add_param("Version", version);
+    for (key, value) in params {
+        request.add_param(*key, *value);
+    }
+    let body = dispatch_request(&Client::shared(), request)
+        .with_context(|| format!("{} failed", action))?;
+    XmlElement::parse(&body)?
+}
+
+/// Call an EC2 action that isn't available in rusoto_ec2.
+#[throws]
+fn ec2_query(action: &str, params: &[(&str, &str)]) -> XmlElement {
+    query_request("ec2", &Region::default(), "2016-11-15", action, params)?
+}
+
+/// Call an IAM action that isn't available in rusoto_iam.
+#[throws]
+fn iam_query(action: &str, params: &[(&str, &str)]) -> XmlElement {
+    query_request("iam", &Region::UsEast1, "2010-05-08", action, params)?
+}
+
+/// Call an action of a JSON-protocol API and parse the response.
+#[throws]
+fn json_request(
+    service: &str,
+    endpoint_prefix: &str,
+    target_prefix: &str,
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    let mut request =
+        SignedRequest::new("POST", service, &Region::default(), "/");
+    request.set_endpoint_prefix(endpoint_prefix.into());
+    request.set_content_type("application/x-amz-json-1.1".into());
+    request
+        .add_header("x-amz-target", &format!("{}.{}", target_prefix, action));
+    request.set_payload(Some(serde_json::to_vec(&payload)?));
+    let body = dispatch_request(&Client::shared(), request)
+        .with_context(|| format!("{} failed", action))?;
+    serde_json::from_slice(&body)
+        .with_context(|| format!("failed to parse {} response", action))?
+}
+
+/// Call an action of the AWS Support API, which is only available in
+/// us-east-1.
+#[throws]
+fn support_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    let mut request =
+        SignedRequest::new("POST", "support", &Region::UsEast1, "/");
+    request.set_content_type("application/x-amz-json-1.1".into());
+    request
+        .add_header("x-amz-target", &format!("AWSSupport_20130415.{}", action));
+    request.set_payload(Some(serde_json::to_vec(&payload)?));
+    let body =
+        dispatch_request(&Client::shared(), request).with_context(|| {
+            format!(
+                "{} failed (Trusted Advisor requires a Business or \
+                 Enterprise support plan)",
+                action
+            )
+        })?;
+    serde_json::from_slice(&body)
+        .with_context(|| format!("failed to parse {} response", action))?
+}
+
+/// Convert a short category name to a Trusted Advisor category ID.
+#[throws]
+fn advisor_category(name: &str) -> &'static str {
+    match name {
+        "cost" => "cost_optimizing",
+        "security" => "security",
+        "fault-tolerance" => "fault_tolerance",
+        "performance" => "performance",
+        "service-limits" => "service_limits",
+        _ => throw!(anyhow!(
+            "invalid category: {} (expected cost, security, \
+             fault-tolerance, performance, or service-limits)",
+            name
+        )),
+    }
+}
+
+#[throws]
+fn advisor_checks(args: Advisor) {
+    let category =
+        args.category.as_deref().map(advisor_category).transpose()?;
+
+    let output = support_request(
+        "DescribeTrustedAdvisorChecks",
+        serde_json::json!({ "language": "en" }),
+    )?;
+    let checks: Vec<serde_json::Value> = output["checks"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|check| {
+            category.is_none_or(|category| check["category"] == category)
+        })
+        .collect();
+    if checks.is_empty() {
+        return;
+    }
+    let check_ids: Vec<&str> = checks
+        .iter()
+        .filter_map(|check| check["id"].as_str())
+        .collect();
+
+    let output = support_request(
+        "DescribeTrustedAdvisorCheckSummaries",
+        serde_json::json!({ "checkIds": check_ids }),
+    )?;
+    let mut statuses = HashMap::new();
+    for summary in output["summaries"].as_array().into_iter().flatten() {
+        if let Some(check_id) = summary["checkId"].as_str() {
+            statuses.insert(
+                check_id.to_string(),
+                (
+                    summary["status"].as_str().unwrap_or_default().to_string(),
+                    summary["resourcesSummary"]["resourcesFlagged"]
+                        .as_i64()
+                        .unwrap_or_default(),
+                ),
+            );
+        }
+    }
+
+    let mut rows = vec![vec![
+        "CATEGORY".to_string(),
+        "STATUS".to_string(),
+        "FLAGGED".to_string(),
+        "CHECK".to_string(),
+    ]];
+    let mut flagged_checks = Vec::new();
+    for check in &checks {
+        let check_id = check["id"].as_str().unwrap_or_default();
+        let (status, flagged) =
+            statuses.get(check_id).cloned().unwrap_or_default();
+        if flagged > 0 {
+            flagged_checks.push(check);
+        }
+        rows.push(vec![
+            check["category"].as_str().unwrap_or_default().to_string(),
+            status,
+            flagged.to_string(),
+            check["name"].as_str().unwrap_or_default().to_string(),
+        ]);
+    }
+    print_table(&rows);
+
+    // Show the flagged resources of each check, using the check's
+    // metadata names as column headers
+    for check in flagged_checks {
+        let output = support_request(
+            "DescribeTrustedAdvisorCheckResult",
+            serde_json::json!({ "checkId": check["id"], "language": "en" }),
+        )?;
+        let mut header = vec!["STATUS".to_string(), "REGION".to_string()];
+        let metadata_names: Vec<String> = check["metadata"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|name| name.as_str().unwrap_or_default().to_string())
+            .collect();
+        let column_count = metadata_names.len();
+        header.extend(metadata_names);
+        let mut rows = vec![header];
+        for resource in output["result"]["flaggedResources"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            if resource["isSuppressed"] == true {
+                continue;
+            }
+            let mut row = vec![
+                resource["status"].as_str().unwrap_or_default().to_string(),
+                resource["region"].as_str().unwrap_or_default().to_string(),
+            ];
+            for index in 0..column_count {
+                row.push(
+                    resource["metadata"][index]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                );
+            }
+            rows.push(row);
+        }
+        println!();
+        println!("{}:", check["name"].as_str().unwrap_or_default());
+        print_table(&rows);
+    }
+}
+
+#[throws]
+fn appconfig_get(args: AppConfigGet) {
+    let session = send_signed_request(
+        "POST",
+        "appconfig",
+        "appconfigdata",
+        "/configurationsessions",
+        &[],
+        Some(serde_json::json!({
+            "ApplicationIdentifier": args.application,
+            "EnvironmentIdentifier": args.environment,
+            "ConfigurationProfileIdentifier": args.profile,
+        })),
+    )?;
+    let session: serde_json::Value = serde_json::from_slice(&session)
+        .context("failed to parse configuration session")?;
+    let token = session["InitialConfigurationToken"]
+        .as_str()
+        .context("missing configuration token")?;
+    let data = send_signed_request(
+        "GET",
+        "appconfig",
+        "appconfigdata",
+        "/configuration",
+        &[("configuration_token", token)],
+        None,
+    )?;
+    std::io::stdout().write_all(&data)?;
+}
+
+#[throws]
+fn backup_request(path: &str, params: &[(&str, &str)]) -> serde_json::Value {
+    let output =
+        send_signed_request("GET", "backup", "backup", path, params, None)?;
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+/// Call `each_page` with each page of a Backup list, which is stored
+/// in the `key` field of the response.
+#[throws]
+fn backup_pages(
+    path: &str,
+    params: &[(&str, &str)],
+    key: &str,
+    mut each_page: impl FnMut(Vec<serde_json::Value>) -> Result<()>,
+) {
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut page_params = params.to_vec();
+        if let Some(token) = &next_token {
+            page_params.push(("nextToken", token.as_str()));
+        }
+        let mut output = backup_request(path, &page_params)?;
+        each_page(
+            output[key]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        )?;
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+}
+
+/// Get all pages of a Backup list, which is stored in the `key` field
+/// of the response.
+#[throws]
+fn backup_list(
+    path: &str,
+    params: &[(&str, &str)],
+    key: &str,
+) -> Vec<serde_json::Value> {
+    let mut items = Vec::new();
+    backup_pages(path, params, key, |mut page| {
+        items.append(&mut page);
+        Ok(())
+    })?;
+    items
+}
+
+fn format_backup_date(value: &serde_json::Value) -> String {
+    value
+        .as_f64()
+        .map_or_else(|| "-".into(), format_timestamp_secs)
+}
+
+#[throws]
+fn backup_plans() {
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "LAST RUN".to_string(),
+        "SELECTIONS".to_string(),
+        "RULES".to_string(),
+    ]);
+    backup_pages("/backup/plans/", &[], "BackupPlansList", |plans| {
+        for plan in plans {
+            let id = plan["BackupPlanId"].as_str().unwrap_or_default();
+            let details =
+                backup_request(&format!("/backup/plans/{}/", id), &[])?;
+            let rules: Vec<_> = details["BackupPlan"]["Rules"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|rule| {
+                    format!(
+                        "{} {}",
+                        rule["RuleName"].as_str().unwrap_or_default(),
+                        rule["ScheduleExpression"].as_str().unwrap_or_default()
+                    )
+                })
+                .collect();
+            let selections = backup_list(
+                &format!("/backup/plans/{}/selections/", id),
+                &[],
+                "BackupSelectionsList",
+            )?;
+            table.push(vec![
+                id.into(),
+                plan["BackupPlanName"].as_str().unwrap_or_default().into(),
+                format_backup_date(&plan["LastExecutionDate"]),
+                selections.len().to_string(),
+                rules.join(", "),
+            ]);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+}
+
+#[throws]
+fn backup_jobs(args: BackupJobs) {
+    let mut params = Vec::new();
+    let status = args.status.map(|status| status.to_uppercase());
+    if let Some(status) = &status {
+        params.push(("state", status.as_str()));
+    }
+    let created_after = (Utc::now() - args.since)
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string();
+    params.push(("createdAfter", created_after.as_str()));
+
+    let mut rows = vec![vec![
+        "CREATED".to_string(),
+        "STATE".to_string(),
+        "TYPE".to_string(),
+        "RESOURCE".to_string(),
+        "VAULT".to_string(),
+        "MESSAGE".to_string(),
+    ]];
+    let mut jobs = backup_list("/backup-jobs/", &params, "BackupJobs")?;
+    jobs.sort_by_key(|item| {
+        item["CreationDate"].as_f64().unwrap_or_default() as i64
+    });
+    for job in jobs {
+        rows.push(vec![
+            format_backup_date(&job["CreationDate"]),
+            job["State"].as_str().unwrap_or_default().into(),
+            job["ResourceType"].as_str().unwrap_or_default().into(),
+            job["ResourceArn"].as_str().unwrap_or_default().into(),
+            job["BackupVaultName"].as_str().unwrap_or_default().into(),
+            job["StatusMessage"].as_str().unwrap_or_default().into(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn backup_recovery_points(resource_arn: String) {
+    let mut rows = vec![vec![
+        "CREATED".to_string(),
+        "STATUS".to_string(),
+        "VAULT".to_string(),
+        "SIZE".to_string(),
+        "ARN".to_string(),
+    ]];
+    // Resource ARNs often contain slashes, which can't be passed in
+    // the path of ListRecoveryPointsByResource, so filter each vault
+    // by resource instead
+    let mut points = Vec::new();
+    for vault in backup_list("/backup-vaults/", &[], "BackupVaultList")? {
+        let name = vault["BackupVaultName"].as_str().unwrap_or_default();
+        points.extend(backup_list(
+            &format!("/backup-vaults/{}/recovery-points/", name),
+            &[("resourceArn", resource_arn.as_str())],
+            "RecoveryPoints",
+        )?);
+    }
+    points.sort_by_key(|item| {
+        item["CreationDate"].as_f64().unwrap_or_default() as i64
+    });
+    for point in points {
+        rows.push(vec![
+            format_backup_date(&point["CreationDate"]),
+            point["Status"].as_str().unwrap_or_default().into(),
+            point["BackupVaultName"].as_str().unwrap_or_default().into(),
+            point["BackupSizeInBytes"]
+                .as_f64()
+                .map_or_else(|| "-".into(), format_bytes),
+            point["RecoveryPointArn"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn bedrock_models(provider: Option<String>) {
+    let mut params = Vec::new();
+    if let Some(provider) = &provider {
+        params.push(("byProvider", provider.as_str()));
+    }
+    let output = send_signed_request(
+        "GET",
+        "bedrock",
+        "bedrock",
+        "/foundation-models",
+        &params,
+        None,
+    )?;
+    let output: serde_json::Value =
+        serde_json::from_slice(&output).context("failed to parse models")?;
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "PROVIDER".to_string(),
+        "NAME".to_string(),
+        "STREAMING".to_string(),
+    ]];
+    for model in output["modelSummaries"].as_array().into_iter().flatten() {
+        rows.push(vec![
+            model["modelId"].as_str().unwrap_or_default().into(),
+            model["providerName"].as_str().unwrap_or_default().into(),
+            model["modelName"].as_str().unwrap_or_default().into(),
+            match model["responseStreamingSupported"].as_bool() {
+                Some(true) => "yes".into(),
+                _ => "no".into(),
+            },
+        ]);
+    }
+    print_table(&rows);
+}
+
+/// Get the provider of a Bedrock model, such as "anthropic" for both
+/// "anthropic.claude-3-haiku-20240307-v1:0" and the inference
+/// profile "us.anthropic.claude-3-haiku-20240307-v1:0".
+fn bedrock_model_family(model_id: &str) -> &str {
+    let mut parts = model_id.split('.');
+    let first = parts.next().unwrap_or_default();
+    if ["us", "us-gov", "eu", "apac", "global"].contains(&first) {
+        parts.next().unwrap_or_default()
+    } else {
+        first
+    }
+}
+
+/// Build the request body for a model, which differs by provider.
+#[throws]
+fn bedrock_request_body(
+    model_id: &str,
+    prompt: &str,
+    max_tokens: u32,
+) -> serde_json::Value {
+    match bedrock_model_family(model_id) {
+        "anthropic" => serde_json::json!({
+            "anthropic_version": "bedrock-2023-05-31",
+            "max_tokens": max_tokens,
+            "messages": [{ "role": "user", "content": prompt }],
+        }),
+        "amazon" if model_id.contains("nova") => serde_json::json!({
+            "messages": [{ "role": "user", "content": [{ "text": prompt }] }],
+            "inferenceConfig": { "maxTokens": max_tokens },
+        }),
+        "amazon" => serde_json::json!({
+            "inputText": prompt,
+            "textGenerationConfig": { "maxTokenCount": max_tokens },
+        }),
+        "meta" => serde_json::json!({
+            "prompt": prompt,
+            "max_gen_len": max_tokens,
+        }),
+        "mistral" => serde_json::json!({
+            "prompt": format!("<s>[INST] {} [/INST]", prompt),
+            "max_tokens": max_tokens,
+        }),
+        "cohere" => serde_json::json!({
+            "message": prompt,
+            "max_tokens": max_tokens,
+        }),
+        "ai21" => serde_json::json!({
+            "messages": [{ "role": "user", "content": prompt }],
+            "max_tokens": max_tokens,
+        }),
+        family => throw!(anyhow!("unsupported model provider: {}", family)),
+    }
+}
+
+/// Get the JSON pointer to the generated text in a model's response,
+/// or in one chunk of a streaming response.
+fn bedrock_text_pointer(model_id: &str, streaming: bool) -> &'static str {
+    match (bedrock_model_family(model_id), streaming) {
+        ("anthropic", false) => "/content/0/text",
+        ("anthropic", true) => "/delta/text",
+        ("amazon", false) if model_id.contains("nova") => {
+            "/output/message/content/0/text"
+        }
+        ("amazon", true) if model_id.contains("nova") => {
+            "/contentBlockDelta/delta/text"
+        }
+        ("amazon", false) => "/results/0/outputText",
+        ("amazon", true) => "/outputText",
+        ("meta", _) => "/generation",
+        ("mistral", _) => "/outputs/0/text",
+        ("ai21", false) => "/choices/0/message/content",
+        ("ai21", true) => "/choices/0/delta/content",
+        _ => "/text",
+    }
+}
+
+#[throws]
+fn bedrock_invoke(args: BedrockInvoke) {
+    let body =
+        bedrock_request_body(&args.model_id, &args.prompt, args.max_tokens)?;
+    if !args.stream {
+        let output = send_signed_request(
+            "POST",
+            "bedrock",
+            "bedrock-runtime",
+            &format!("/model/{}/invoke", args.model_id),
+            &[],
+            Some(body),
+        )?;
+        let output: serde_json::Value = serde_json::from_slice(&output)
+            .context("failed to parse model response")?;
+        let text = output
+            .pointer(bedrock_text_pointer(&args.model_id, false))
+            .and_then(|text| text.as_str())
+            .context("missing text in model response")?;
+        println!("{}", text);
+        return;
+    }
+
+    let path = format!("/model/{}/invoke-with-response-stream", args.model_id);
+    let mut request =
+        SignedRequest::new("POST", "bedrock", &Region::default(), &path);
+    request.set_endpoint_prefix("bedrock-runtime".into());
+    request.set_content_type("application/json".into());
+    request.set_payload(Some(serde_json::to_vec(&body)?));
+    let stream = dispatch_streaming_request(&Client::shared(), request)
+        .context("failed to invoke model")?;
+    let pointer = bedrock_text_pointer(&args.model_id, true);
+    let mut stdout = std::io::stdout();
+    let mut buf = Vec::new();
+    for data in stream.wait() {
+        buf.extend_from_slice(&data?);
+        while let Some((headers, payload)) =
+            parse_event_stream_message(&mut buf)?
+        {
+            let payload: serde_json::Value =
+                serde_json::from_slice(&payload)
+                    .context("failed to parse event")?;
+            if headers.get(":message-type").map(String::as_str)
+                == Some("exception")
+            {
+                throw!(anyhow!(
+                    "{}: {}",
+                    headers
+                        .get(":exception-type")
+                        .map(String::as_str)
+                        .unwrap_or_default(),
+                    payload["message"].as_str().unwrap_or_default()
+                ));
+            }
+            // Each chunk holds the model's own JSON, base64 encoded
+            let chunk =
+                base64::decode(payload["bytes"].as_str().unwrap_or_default())
+                    .context("invalid chunk encoding")?;
+            let chunk: serde_json::Value = serde_json::from_slice(&chunk)
+                .context("failed to parse chunk")?;
+            if let Some(text) =
+                chunk.pointer(pointer).and_then(|text| text.as_str())
+            {
+                write!(stdout, "{}", text)?;
+                stdout.flush()?;
+            }
+        }
+    }
+    println!();
+}
+
+#[throws]
+fn codeartifact_login(args: CodeArtifactLogin) {
+    let format = match args.tool.as_str() {
+        "pip" => "pypi",
+        "npm" => "npm",
+        "cargo" => "cargo",
+        _ => throw!(anyhow!("unsupported tool: {}", args.tool)),
+    };
+    let owner = match args.domain_owner {
+        Some(owner) => owner,
+        None => get_account_id()?,
+    };
+    let output = send_signed_request(
+        "POST",
+        "codeartifact",
+        "codeartifact",
+        "/v1/authorization-token",
+        &[("domain", &args.domain), ("domain-owner", &owner)],
+        None,
+    )?;
+    let auth: serde_json::Value = serde_json::from_slice(&output)
+        .context("failed to parse authorization token")?;
+    let token = auth["authorizationToken"]
+        .as_str()
+        .context("missing authorization token")?;
+    let output = send_signed_request(
+        "GET",
+        "codeartifact",
+        "codeartifact",
+        "/v1/repository/endpoint",
+        &[
+            ("domain", &args.domain),
+            ("domain-owner", &owner),
+            ("repository", &args.repository),
+            ("format", format),
+        ],
+        None,
+    )?;
+    let output: serde_json::Value = serde_json::from_slice(&output)
+        .context("failed to parse repository endpoint")?;
+    let endpoint = output["repositoryEndpoint"]
+        .as_str()
+        .context("missing repository endpoint")?;
+
+    match format {
+        "pypi" => {
+            let host = endpoint.trim_start_matches("https://");
+            let index_url = format!("https://aws:{}@{}simple/", token, host);
+            run_lookup(
+                "pip",
+                &["config", "set", "global.index-url", &index_url],
+            )?;
+        }
+        "npm" => {
+            run_lookup("npm", &["config", "set", "registry", endpoint])?;
+            let auth = format!(
+                "{}:_authToken={}",
+                endpoint.trim_start_matches("https:"),
+                token
+            );
+            run_lookup("npm", &["config", "set", &auth])?;
+        }
+        _ => {
+            let cargo_home = match std::env::var_os("CARGO_HOME") {
+                Some(path) => PathBuf::from(path),
+                None => home_dir()?.join(".cargo"),
+            };
+            let section = format!("registries.{}", args.repository);
+            let index = format!("sparse+{}", endpoint);
+            write_config_section(
+                &cargo_home.join("config.toml"),
+                &section,
+                &[("index", &index)],
+            )?;
+            let token = format!("Bearer {}", token);
+            write_config_section(
+                &cargo_home.join("credentials.toml"),
+                &section,
+                &[("token", &token)],
+            )?;
+        }
+    }
+    println!(
+        "Configured {} for {}/{} (token expires {})",
+        args.tool,
+        args.domain,
+        args.repository,
+        auth["expiration"]
+            .as_f64()
+            .map(format_timestamp_secs)
+            .unwrap_or_default()
+    );
+}
+
+#[throws]
+fn codebuild_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    json_request(
+        "codebuild",
+        "codebuild",
+        "CodeBuild_20161006",
+        action,
+        payload,
+    )?
+}
+
+/// Get the details of builds by ID.
+#[throws]
+fn codebuild_get_builds(ids: &[String]) -> Vec<serde_json::Value> {
+    let mut builds = Vec::new();
+    // BatchGetBuilds accepts up to 100 IDs
+    for chunk in ids.chunks(100) {
+        let mut output = codebuild_request(
+            "BatchGetBuilds",
+            serde_json::json!({ "ids": chunk }),
+        )?;
+        if let Some(list) = output["builds"].as_array_mut() {
+            builds.append(list);
+        }
+    }
+    builds
+}
+
+fn format_build_duration(build: &serde_json::Value) -> String {
+    let start = build["startTime"].as_f64();
+    let end = build["endTime"]
+        .as_f64()
+        .unwrap_or_else(|| Utc::now().timestamp() as f64);
+    match start {
+        Some(start) => {
+            format_duration_short(Duration::seconds((end - start) as i64))
+        }
+        None => String::new(),
+    }
+}
+
+#[throws]
+fn codebuild_projects() {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "SOURCE".to_string(),
+        "LAST BUILD".to_string(),
+        "STARTED".to_string(),
+    ]);
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut payload = serde_json::json!({ "sortBy": "NAME" });
+        if let Some(token) = &next_token {
+            payload["nextToken"] = token.as_str().into();
+        }
+        let output = codebuild_request("ListProjects", payload)?;
+        let names: Vec<_> = output["projects"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|name| name.as_str())
+            .collect();
+        // BatchGetProjects rejects an empty list of names
+        if names.is_empty() {
+            break;
+        }
+
+        // Pages hold up to 100 names, which is also the most that
+        // BatchGetProjects accepts
+        let projects = codebuild_request(
+            "BatchGetProjects",
+            serde_json::json!({ "names": names }),
+        )?;
+        for project in projects["projects"].as_array().into_iter().flatten() {
+            let name = project["name"].as_str().unwrap_or_default();
+            let builds = codebuild_request(
+                "ListBuildsForProject",
+                serde_json::json!({
+                    "projectName": name,
+                    "sortOrder": "DESCENDING",
+                }),
+            )?;
+            let last_build = match builds["ids"][0].as_str() {
+                Some(id) => codebuild_get_builds(&[id.into()])?.pop(),
+                None => None,
+            };
+            let last_build = last_build.unwrap_or_default();
+            table.push(vec![
+                name.into(),
+                project["source"]["location"]
+                    .as_str()
+                    .or_else(|| project["source"]["type"].as_str())
+                    .unwrap_or_default()
+                    .into(),
+                last_build["buildStatus"].as_str().unwrap_or("-").into(),
+                last_build["startTime"]
+                    .as_f64()
+                    .map(format_timestamp_secs)
+                    .unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    table.finish();
+}
+
+#[throws]
+fn codebuild_builds(args: CodeBuildBuilds) {
+    let output = codebuild_request(
+        "ListBuildsForProject",
+        serde_json::json!({
+            "projectName": args.project,
+            "sortOrder": "DESCENDING",
+        }),
+    )?;
+    let ids: Vec<String> = output["ids"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|id| id.as_str())
+        .take(args.limit)
+        .map(String::from)
+        .collect();
+
+    let mut rows = vec![vec![
+        "NUMBER".to_string(),
+        "STATUS".to_string(),
+        "STARTED".to_string(),
+        "DURATION".to_string(),
+        "SOURCE VERSION".to_string(),
+        "INITIATOR".to_string(),
+    ]];
+    for build in codebuild_get_builds(&ids)? {
+        rows.push(vec![
+            build["buildNumber"]
+                .as_i64()
+                .map(|number| number.to_string())
+                .unwrap_or_default(),
+            build["buildStatus"].as_str().unwrap_or_default().into(),
+            build["startTime"]
+                .as_f64()
+                .map(format_timestamp_secs)
+                .unwrap_or_default(),
+            format_build_duration(&build),
+            build["resolvedSourceVersion"]
+                .as_str()
+                .or_else(|| build["sourceVersion"].as_str())
+                .unwrap_or_default()
+                .into(),
+            build["initiator"].as_str().unwrap_or_default().into(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+/// Print new events from a log stream, updating `next_token` so that
+/// the next call continues where this one left off.
+#[throws]
+fn print_log_stream_events(
+    client: &CloudWatchLogsClient,
+    log_group_name: &str,
+    log_stream_name: &str,
+    next_token: &mut Option<String>,
+) {
+    loop {
+        let output = client
+            .get_log_events(GetLogEventsRequest {
+                log_group_name: log_group_name.into(),
+                log_stream_name: log_stream_name.into(),
+                next_token: next_token.clone(),
+                start_from_head: Some(true),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to get log events")?;
+        for event in output.events.unwrap_or_default() {
+            print!("{}", event.message.unwrap_or_default());
+        }
+        // Finish if the end of the stream has been reached
+        if output.next_forward_token.is_none()
+            || output.next_forward_token == *next_token
+        {
+            break;
+        }
+        *next_token = output.next_forward_token;
+    }
+}
+
+#[throws]
+fn codebuild_start(args: CodeBuildStart) {
+    let output = codebuild_request(
+        "StartBuild",
+        serde_json::json!({ "projectName": args.project }),
+    )?;
+    let id = output["build"]["id"]
+        .as_str()
+        .context("missing build ID")?
+        .to_string();
+    println!("{}", id);
+    if !args.wait && !args.logs {
+        return;
+    }
+
+    let logs_client = CloudWatchLogsClient::new(Region::default());
+    let mut logs_token = None;
+    loop {
+        let build = codebuild_get_builds(std::slice::from_ref(&id))?
+            .pop()
+            .context("build not found")?;
+        let logs = &build["logs"];
+        // The log stream isn't available until the build has started
+        if let (true, Some(group), Some(stream)) = (
+            args.logs,
+            logs["groupName"].as_str(),
+            logs["streamName"].as_str(),
+        ) {
+            print_log_stream_events(
+                &logs_client,
+                group,
+                stream,
+                &mut logs_token,
+            )?;
+        }
+        let status = build["buildStatus"].as_str().unwrap_or_default();
+        if status != "IN_PROGRESS" {
+            eprintln!("{} after {}", status, format_build_duration(&build));
+            if status != "SUCCEEDED" {
+                throw!(anyhow!("build {}", status.to_lowercase()));
+            }
+            break;
+        }
+        thread::sleep(time::Duration::from_secs(5));
+    }
+}
+
+#[throws]
+fn codedeploy_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    json_request(
+        "codedeploy",
+        "codedeploy",
+        "CodeDeploy_20141006",
+        action,
+        payload,
+    )?
+}
+
+/// Get up to `limit` deployment IDs, most recent first.
+#[throws]
+fn codedeploy_list_deployment_ids(
+    payload: serde_json::Value,
+    limit: usize,
+) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut payload = payload.clone();
+        if let Some(token) = &next_token {
+            payload["nextToken"] = token.as_str().into();
+        }
+        let output = codedeploy_request("ListDeployments", payload)?;
+        ids.extend(
+            output["deployments"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|id| id.as_str())
+                .map(String::from),
+        );
+
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) if ids.len() < limit => next_token = Some(token.into()),
+            _ => break,
+        }
+    }
+    ids.truncate(limit);
+    ids
+}
+
+#[throws]
+fn codedeploy_get_deployment(id: &str) -> serde_json::Value {
+    let output = codedeploy_request(
+        "GetDeployment",
+        serde_json::json!({ "deploymentId": id }),
+    )?;
+    output["deploymentInfo"].clone()
+}
+
+#[throws]
+fn codedeploy_deployments(args: CodeDeployDeployments) {
+    let mut ids = Vec::new();
+    match &args.app {
+        // Listing an application's deployments requires a deployment
+        // group, so list each group's deployments
+        Some(app) => {
+            let output = codedeploy_request(
+                "ListDeploymentGroups",
+                serde_json::json!({ "applicationName": app }),
+            )?;
+            for group in
+                output["deploymentGroups"].as_array().into_iter().flatten()
+            {
+                ids.extend(codedeploy_list_deployment_ids(
+                    serde_json::json!({
+                        "applicationName": app,
+                        "deploymentGroupName": group,
+                    }),
+                    args.limit,
+                )?);
+            }
+        }
+        None => {
+            ids = codedeploy_list_deployment_ids(
+                serde_json::json!({}),
+                args.limit,
+            )?
+        }
+    }
+
+    let mut deployments = Vec::new();
+    // BatchGetDeployments accepts up to 25 IDs
+    for chunk in ids.chunks(25) {
+        let mut output = codedeploy_request(
+            "BatchGetDeployments",
+            serde_json::json!({ "deploymentIds": chunk }),
+        )?;
+        if let Some(list) = output["deploymentsInfo"].as_array_mut() {
+            deployments.append(list);
+        }
+    }
+    deployments.sort_by_key(|deployment| {
+        -(deployment["createTime"].as_f64().unwrap_or_default() as i64)
+    });
+    deployments.truncate(args.limit);
+
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "APPLICATION".to_string(),
+        "GROUP".to_string(),
+        "STATUS".to_string(),
+        "CREATED".to_string(),
+        "INSTANCES".to_string(),
+    ]];
+    for deployment in deployments {
+        let overview = &deployment["deploymentOverview"];
+        let count = |key: &str| overview[key].as_i64().unwrap_or_default();
+        rows.push(vec![
+            deployment["deploymentId"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+            deployment["applicationName"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+            deployment["deploymentGroupName"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+            deployment["status"].as_str().unwrap_or_default().into(),
+            deployment["createTime"]
+                .as_f64()
+                .map(format_timestamp_secs)
+                .unwrap_or_default(),
+            format!(
+                "{} succeeded, {} failed, {} pending",
+                count("Succeeded"),
+                count("Failed"),
+                count("Pending") + count("InProgress")
+            ),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn codedeploy_show(deployment_id: String) {
+    let deployment = codedeploy_get_deployment(&deployment_id)?;
+    println!(
+        "{}/{}: {}",
+        deployment["applicationName"].as_str().unwrap_or_default(),
+        deployment["deploymentGroupName"]
+            .as_str()
+            .unwrap_or_default(),
+        deployment["status"].as_str().unwrap_or_default()
+    );
+    if let Some(message) = deployment["errorInformation"]["message"].as_str() {
+        println!("{}", message);
+    }
+    println!();
+
+    let mut table = TableStream::new(vec![
+        "INSTANCE".to_string(),
+        "EVENT".to_string(),
+        "STATUS".to_string(),
+        "DURATION".to_string(),
+        "MESSAGE".to_string(),
+    ]);
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut payload = serde_json::json!({ "deploymentId": deployment_id });
+        if let Some(token) = &next_token {
+            payload["nextToken"] = token.as_str().into();
+        }
+        let output = codedeploy_request("ListDeploymentTargets", payload)?;
+        let target_ids: Vec<_> = output["targetIds"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|id| id.as_str())
+            .collect();
+
+        // BatchGetDeploymentTargets accepts up to 25 IDs
+        for chunk in target_ids.chunks(25) {
+            let output = codedeploy_request(
+                "BatchGetDeploymentTargets",
+                serde_json::json!({
+                    "deploymentId": deployment_id,
+                    "targetIds": chunk,
+                }),
+            )?;
+            for target in
+                output["deploymentTargets"].as_array().into_iter().flatten()
+            {
+                let instance = &target["instanceTarget"];
+                let instance_id =
+                    instance["targetId"].as_str().unwrap_or_default();
+                for event in
+                    instance["lifecycleEvents"].as_array().into_iter().flatten()
+                {
+                    let duration = match (
+                        event["startTime"].as_f64(),
+                        event["endTime"].as_f64(),
+                    ) {
+                        (Some(start), Some(end)) => format_duration_short(
+                            Duration::seconds((end - start) as i64),
+                        ),
+                        _ => String::new(),
+                    };
+                    table.push(vec![
+                        instance_id.into(),
+                        event["lifecycleEventName"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .into(),
+                        event["status"].as_str().unwrap_or_default().into(),
+                        duration,
+                        event["diagnostics"]["message"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .trim()
+                            .into(),
+                    ]);
+                }
+            }
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    table.finish();
+}
+
+#[throws]
+fn codedeploy_stop(deployment_id: String, rollback: bool) {
+    let output = codedeploy_request(
+        "StopDeployment",
+        serde_json::json!({
+            "deploymentId": deployment_id,
+            "autoRollbackEnabled": rollback,
+        }),
+    )?;
+    if let Some(message) = output["statusMessage"].as_str() {
+        println!("{}", message);
+    }
+}
+
+#[throws]
+fn codedeploy_rollback(deployment_id: String) {
+    let deployment = codedeploy_get_deployment(&deployment_id)?;
+    let status = deployment["status"].as_str().unwrap_or_default();
+    // An in-progress deployment can be stopped and rolled back in one
+    // step
+    if ["Created", "Queued", "InProgress", "Baking", "Ready"].contains(&status)
+    {
+        return codedeploy_stop(deployment_id, true)?;
+    }
+
+    let revision = &deployment["previousRevision"];
+    if revision.is_null() {
+        throw!(anyhow!("no previous revision to roll back to"));
+    }
+    let output = codedeploy_request(
+        "CreateDeployment",
+        serde_json::json!({
+            "applicationName": deployment["applicationName"],
+            "deploymentGroupName": deployment["deploymentGroupName"],
+            "revision": revision,
+            "description": format!("Rollback of {}", deployment_id),
+        }),
+    )?;
+    println!("{}", output["deploymentId"].as_str().unwrap_or_default());
+}
+
+#[throws]
+fn codepipeline_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    json_request(
+        "codepipeline",
+        "codepipeline",
+        "CodePipeline_20150709",
+        action,
+        payload,
+    )?
+}
+
+#[throws]
+fn codepipeline_list() {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "LAST EXECUTION".to_string(),
+        "STATUS".to_string(),
+    ]);
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut payload = serde_json::json!({});
+        if let Some(token) = &next_token {
+            payload["nextToken"] = token.as_str().into();
+        }
+        let output = codepipeline_request("ListPipelines", payload)?;
+        for pipeline in output["pipelines"].as_array().into_iter().flatten() {
+            let name = pipeline["name"].as_str().unwrap_or_default();
+            let executions = codepipeline_request(
+                "ListPipelineExecutions",
+                serde_json::json!({ "pipelineName": name, "maxResults": 1 }),
+            )?;
+            let execution = &executions["pipelineExecutionSummaries"][0];
+            table.push(vec![
+                name.into(),
+                execution["startTime"]
+                    .as_f64()
+                    .map(format_timestamp_secs)
+                    .unwrap_or_default(),
+                execution["status"].as_str().unwrap_or("-").into(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    table.finish();
+}
+
+/// Format the source revisions of a pipeline execution, such as
+/// "abc12345 Fix login page".
+#[throws]
+fn codepipeline_get_revision(pipeline: &str, execution_id: &str) -> String {
+    let output = codepipeline_request(
+        "GetPipelineExecution",
+        serde_json::json!({
+            "pipelineName": pipeline,
+            "pipelineExecutionId": execution_id,
+        }),
+    )?;
+    let revisions: Vec<_> = output["pipelineExecution"]["artifactRevisions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|revision| {
+            let id = revision["revisionId"].as_str().unwrap_or_default();
+            let summary = revision["revisionSummary"]
+                .as_str()
+                .and_then(|summary| summary.lines().next())
+                .unwrap_or_default();
+            format!("{} {}", id.get(..8).unwrap_or(id), summary)
+        })
+        .collect();
+    revisions.join(", ")
+}
+
+#[throws]
+fn codepipeline_show(pipeline: String) {
+    let state = codepipeline_request(
+        "GetPipelineState",
+        serde_json::json!({ "name": pipeline }),
+    )?;
+    let mut rows = vec![vec![
+        "STAGE".to_string(),
+        "ACTION".to_string(),
+        "STATUS".to_string(),
+        "CHANGED".to_string(),
+        "REVISION".to_string(),
+    ]];
+    // Map from execution ID to its formatted revision
+    let mut revisions = HashMap::new();
+    for stage in state["stageStates"].as_array().into_iter().flatten() {
+        let stage_name = stage["stageName"].as_str().unwrap_or_default();
+        let revision = match stage["latestExecution"]["pipelineExecutionId"]
+            .as_str()
+        {
+            Some(id) => {
+                if !revisions.contains_key(id) {
+                    let revision = codepipeline_get_revision(&pipeline, id)?;
+                    revisions.insert(id.to_string(), revision);
+                }
+                revisions[id].clone()
+            }
+            None => String::new(),
+        };
+        for action in stage["actionStates"].as_array().into_iter().flatten() {
+            let execution = &action["latestExecution"];
+            rows.push(vec![
+                stage_name.into(),
+                action["actionName"].as_str().unwrap_or_default().into(),
+                execution["status"].as_str().unwrap_or("-").into(),
+                execution["lastStatusChange"]
+                    .as_f64()
+                    .map(format_timestamp_secs)
+                    .unwrap_or_default(),
+                revision.clone(),
+            ]);
+        }
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn codepipeline_approve(args: CodePipelineApprove) {
+    let state = codepipeline_request(
+        "GetPipelineState",
+        serde_json::json!({ "name": args.pipeline }),
+    )?;
+    // The token of the pending approval is needed to approve it
+    let token = state["stageStates"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|stage| stage["stageName"] == args.stage.as_str())
+        .flat_map(|stage| {
+            stage["actionStates"].as_array().into_iter().flatten()
+        })
+        .filter(|action| action["actionName"] == args.action.as_str())
+        .filter_map(|action| action["latestExecution"]["token"].as_str())
+        .next()
+        .with_context(|| {
+            format!("no pending approval for {}/{}", args.stage, args.action)
+        })?
+        .to_string();
+    let status = if args.reject { "Rejected" } else { "Approved" };
+    let summary = args
+        .summary
+        .unwrap_or_else(|| format!("{} with claws", status));
+    codepipeline_request(
+        "PutApprovalResult",
+        serde_json::json!({
+            "pipelineName": args.pipeline,
+            "stageName": args.stage,
+            "actionName": args.action,
+            "token": token,
+            "result": {
+                "status": status,
+                "summary": summary,
+            },
+        }),
+    )?;
+}
+
+/// Build a Rekognition image from a local file or an S3 URI.
+#[throws]
+fn rekognition_image(image: &str) -> serde_json::Value {
+    if image.starts_with("s3://") {
+        let (bucket, key) = parse_s3_object_path(image)?;
+        serde_json::json!({ "S3Object": { "Bucket": bucket, "Name": key } })
+    } else {
+        let data = fs::read(image)
+            .with_context(|| format!("failed to read {}", image))?;
+        serde_json::json!({ "Bytes": base64::encode(data) })
+    }
+}
+
+#[throws]
+fn rekognition_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    json_request(
+        "rekognition",
+        "rekognition",
+        "RekognitionService",
+        action,
+        payload,
+    )?
+}
+
+#[throws]
+fn rekognition_labels(args: RekognitionLabels) {
+    let output = rekognition_request(
+        "DetectLabels",
+        serde_json::json!({
+            "Image": rekognition_image(&args.image)?,
+            "MinConfidence": args.min_confidence,
+        }),
+    )?;
+    let mut rows = vec![vec![
+        "LABEL".to_string(),
+        "CONFIDENCE".to_string(),
+        "INSTANCES".to_string(),
+        "PARENTS".to_string(),
+    ]];
+    for label in output["Labels"].as_array().into_iter().flatten() {
+        let parents: Vec<_> = label["Parents"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|parent| parent["Name"].as_str())
+            .collect();
+        rows.push(vec![
+            label["Name"].as_str().unwrap_or_default().into(),
+            format!("{:.1}%", label["Confidence"].as_f64().unwrap_or_default()),
+            label["Instances"]
+                .as_array()
+                .map_or(0, Vec::len)
+                .to_string(),
+            parents.join(", "),
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn rekognition_faces(image: String) {
+    let output = rekognition_request(
+        "DetectFaces",
+        serde_json::json!({
+            "Image": rekognition_image(&image)?,
+            "Attributes": ["ALL"],
+        }),
+    )?;
+    let mut rows = vec![vec![
+        "FACE".to_string(),
+        "CONFIDENCE".to_string(),
+        "AGE".to_string(),
+        "EMOTION".to_string(),
+        "POSITION".to_string(),
+    ]];
+    for (i, face) in output["FaceDetails"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        // Show the most likely emotion
+        let emotion = face["Emotions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .max_by_key(|emotion| {
+                (emotion["Confidence"].as_f64().unwrap_or_default() * 100.0)
+                    as i64
+            })
+            .and_then(|emotion| emotion["Type"].as_str())
+            .unwrap_or_default();
+        let bounding_box = &face["BoundingBox"];
+        let position =
+            |key: &str| bounding_box[key].as_f64().unwrap_or_default();
+        rows.push(vec![
+            (i + 1).to_string(),
+            format!("{:.1}%", face["Confidence"].as_f64().unwrap_or_default()),
+            format!(
+                "{}-{}",
+                face["AgeRange"]["Low"].as_i64().unwrap_or_default(),
+                face["AgeRange"]["High"].as_i64().unwrap_or_default()
+            ),
+            emotion.into(),
+            format!(
+                "left {:.2}, top {:.2}, {:.2}x{:.2}",
+                position("Left"),
+                position("Top"),
+                position("Width"),
+                position("Height")
+            ),
+        ]);
+    }
+    print_table(&rows);
+}
+
+/// Documents larger than this can't be sent to Textract directly.
+const TEXTRACT_MAX_SYNC_SIZE: u64 = 5 * 1024 * 1024;
+
+#[throws]
+fn textract_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    json_request("textract", "textract", "Textract", action, payload)?
+}
+
+/// Run an asynchronous Textract job on an S3 object and return the
+/// blocks of all pages.
+#[throws]
+fn textract_run_job(
+    bucket: &str,
+    key: &str,
+    features: &[&str],
+) -> Vec<serde_json::Value> {
+    let (start_action, get_action) = if features.is_empty() {
+        ("StartDocumentTextDetection", "GetDocumentTextDetection")
+    } else {
+        ("StartDocumentAnalysis", "GetDocumentAnalysis")
+    };
+    let mut payload = serde_json::json!({
+        "DocumentLocation": { "S3Object": { "Bucket": bucket, "Name": key } },
+    });
+    if !features.is_empty() {
+        payload["FeatureTypes"] = features.into();
+    }
+    let output = textract_request(start_action, payload)?;
+    let job_id = output["JobId"].as_str().context("missing job ID")?;
+
+    let mut blocks = Vec::new();
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut payload = serde_json::json!({ "JobId": job_id });
+        if let Some(token) = &next_token {
+            payload["NextToken"] = token.as_str().into();
+        }
+        let mut output = textract_request(get_action, payload)?;
+        match output["JobStatus"].as_str() {
+            Some("IN_PROGRESS") => {
+                thread::sleep(time::Duration::from_secs(5));
+                continue;
+            }
+            Some("FAILED") => throw!(anyhow!(
+                "analysis failed: {}",
+                output["StatusMessage"].as_str().unwrap_or_default()
+            )),
+            _ => {}
+        }
+        if let Some(list) = output["Blocks"].as_array_mut() {
+            blocks.append(list);
+        }
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    blocks
+}
+
+/// Get the IDs of a block's relationships of one type, such as
+/// "CHILD" or "VALUE".
+fn textract_related<'a>(
+    block: &'a serde_json::Value,
+    kind: &str,
+) -> Vec<&'a str> {
+    block["Relationships"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|relationship| relationship["Type"] == kind)
+        .flat_map(|relationship| {
+            relationship["Ids"].as_array().into_iter().flatten()
+        })
+        .filter_map(|id| id.as_str())
+        .collect()
+}
+
+/// Get the text of a block from its child words and checkboxes.
+fn textract_text(
+    block: &serde_json::Value,
+    blocks: &HashMap<&str, &serde_json::Value>,
+) -> String {
+    let words: Vec<_> = textract_related(block, "CHILD")
+        .into_iter()
+        .filter_map(|id| blocks.get(id))
+        .filter_map(|child| match child["BlockType"].as_str() {
+            Some("WORD") => child["Text"].as_str().map(String::from),
+            Some("SELECTION_ELEMENT") => {
+                if child["SelectionStatus"] == "SELECTED" {
+                    Some("[x]".into())
+                } else {
+                    Some("[ ]".into())
+                }
+            }
+            _ => None,
+        })
+        .collect();
+    words.join(" ")
+}
+
+#[throws]
+fn textract_analyze(args: TextractAnalyze) {
+    let mut features = Vec::new();
+    if args.tables {
+        features.push("TABLES");
+    }
+    if args.forms {
+        features.push("FORMS");
+    }
+
+    let is_s3 = args.document.starts_with("s3://");
+    let size = if is_s3 {
+        None
+    } else {
+        Some(
+            fs::metadata(&args.document)
+                .with_context(|| format!("failed to read {}", args.document))?
+                .len(),
+        )
+    };
+    let blocks = match size {
+        // Small local files are analyzed synchronously
+        Some(size) if size <= TEXTRACT_MAX_SYNC_SIZE => {
+            let data = fs::read(&args.document)?;
+            let mut payload = serde_json::json!({
+                "Document": { "Bytes": base64::encode(data) },
+            });
+            let action = if features.is_empty() {
+                "DetectDocumentText"
+            } else {
+                payload["FeatureTypes"] = features.clone().into();
+                "AnalyzeDocument"
+            };
+            let mut output = textract_request(action, payload)?;
+            output["Blocks"].as_array_mut().map(std::mem::take)
+        }
+        Some(_) => {
+            let bucket = args.bucket.as_deref().context(
+                "--bucket is required to analyze a local file over 5 MB",
+            )?;
+            let prefix =
+                format!("claws-textract/{}", Utc::now().format("%Y%m%d%H%M%S"));
+            let key = s3_upload_temp_file(
+                bucket,
+                &prefix,
+                Path::new(&args.document),
+            )?;
+            let blocks = textract_run_job(bucket, &key, &features);
+            s3_client_for_bucket(bucket)?
+                .delete_object(DeleteObjectRequest {
+                    bucket: bucket.into(),
+                    key,
+                    ..Default::default()
+                })
+                .sync()
+                .context("failed to delete uploaded file")?;
+            Some(blocks?)
+        }
+        None => {
+            let (bucket, key) = parse_s3_object_path(&args.document)?;
+            Some(textract_run_job(&bucket, &key, &features)?)
+        }
+    }
+    .unwrap_or_default();
+
+    let by_id: HashMap<&str, &serde_json::Value> = blocks
+        .iter()
+        .filter_map(|block| block["Id"].as_str().map(|id| (id, block)))
+        .collect();
+    let of_type = |block_type: &'static str| {
+        blocks
+            .iter()
+            .filter(move |block| block["BlockType"] == block_type)
+    };
+
+    for line in of_type("LINE") {
+        println!("{}", line["Text"].as_str().unwrap_or_default());
+    }
+
+    for (i, table) in of_type("TABLE").enumerate() {
+        println!("\nTable {}:", i + 1);
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        for cell in textract_related(table, "CHILD")
+            .into_iter()
+            .filter_map(|id| by_id.get(id))
+        {
+            let row = cell["RowIndex"].as_u64().unwrap_or(1).max(1) as usize;
+            let column =
+                cell["ColumnIndex"].as_u64().unwrap_or(1).max(1) as usize;
+            if rows.len() < row {
+                rows.resize(row, Vec::new());
+            }
+            if rows[row - 1].len() < column {
+                rows[row - 1].resize(column, String::new());
+            }
+            rows[row - 1][column - 1] = textract_text(cell, &by_id);
+        }
+        print_table(&rows);
+    }
+
+    if args.forms {
+        println!("\nForm fields:");
+        let mut rows = Vec::new();
+        for key in of_type("KEY_VALUE_SET") {
+            let is_key = key["EntityTypes"]
+                .as_array()
+                .is_some_and(|types| types.iter().any(|t| t == "KEY"));
+            if !is_key {
+                continue;
+            }
+            let value: Vec<_> = textract_related(key, "VALUE")
+                .into_iter()
+                .filter_map(|id| by_id.get(id))
+                .map(|value| textract_text(value, &by_id))
+                .collect();
+            rows.push(vec![textract_text(key, &by_id), value.join(" ")]);
+        }
+        print_table(&rows);
+    }
+}
+
+#[throws]
+fn msk_request(path: &str, params: &[(&str, &str)]) -> serde_json::Value {
+    let output =
+        send_signed_request("GET", "kafka", "kafka", path, params, None)?;
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+/// Call `each_page` with the items of each page of a paginated MSK
+/// list operation.
+#[throws]
+fn msk_pages(
+    path: &str,
+    key: &str,
+    mut each_page: impl FnMut(Vec<serde_json::Value>) -> Result<()>,
+) {
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(token) = &next_token {
+            params.push(("nextToken", token.as_str()));
+        }
+        let mut output = msk_request(path, &params)?;
+        each_page(
+            output[key]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        )?;
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+}
+
+/// Get all items of a paginated MSK list operation.
+#[throws]
+fn msk_list(path: &str, key: &str) -> Vec<serde_json::Value> {
+    let mut items = Vec::new();
+    msk_pages(path, key, |mut page| {
+        items.append(&mut page);
+        Ok(())
+    })?;
+    items
+}
+
+/// Get the ARN of a cluster identified by name or ARN.
+#[throws]
+fn msk_find_cluster_arn(cluster: &str) -> String {
+    if cluster.starts_with("arn:") {
+        return cluster.into();
+    }
+    msk_list("/v1/clusters", "ClusterInfoList")?
+        .into_iter()
+        .find(|candidate| candidate["ClusterName"] == cluster)
+        .and_then(|candidate| {
+            candidate["ClusterArn"].as_str().map(String::from)
+        })
+        .with_context(|| format!("cluster not found: {}", cluster))?
+}
+
+#[throws]
+fn msk_clusters() {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "STATE".to_string(),
+        "VERSION".to_string(),
+        "INSTANCE TYPE".to_string(),
+        "BROKERS".to_string(),
+    ]);
+    msk_pages("/v1/clusters", "ClusterInfoList", |clusters| {
+        for cluster in clusters {
+            let state = cluster["State"].as_str().unwrap_or_default();
+
+            // Brokers only have endpoints once the cluster is active
+            let mut endpoints: Vec<String> = Vec::new();
+            if state == "ACTIVE" {
+                let arn = cluster["ClusterArn"].as_str().unwrap_or_default();
+                let path = format!("/v1/clusters/{}/nodes", arn);
+                for node in msk_list(&path, "NodeInfoList")? {
+                    if let Some(list) =
+                        node["BrokerNodeInfo"]["Endpoints"].as_array()
+                    {
+                        endpoints.extend(
+                            list.iter()
+                                .filter_map(|e| e.as_str().map(String::from)),
+                        );
+                    }
+                }
+            }
+            let endpoints = endpoints.join(",");
+
+            table.push(vec![
+                cluster["ClusterName"].as_str().unwrap_or_default().into(),
+                state.into(),
+                cluster["CurrentBrokerSoftwareInfo"]["KafkaVersion"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                cluster["BrokerNodeGroupInfo"]["InstanceType"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                if endpoints.is_empty() {
+                    cluster["NumberOfBrokerNodes"].to_string()
+                } else {
+                    endpoints
+                },
+            ]);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+}
+
+#[throws]
+fn msk_bootstrap(cluster: String) {
+    let arn = msk_find_cluster_arn(&cluster)?;
+    let output =
+        msk_request(&format!("/v1/clusters/{}/bootstrap-brokers", arn), &[])?;
+    let strings: Vec<_> = [
+        ("plaintext", "BootstrapBrokerString"),
+        ("tls", "BootstrapBrokerStringTls"),
+        ("sasl-scram", "BootstrapBrokerStringSaslScram"),
+        ("sasl-iam", "BootstrapBrokerStringSaslIam"),
+        ("public-tls", "BootstrapBrokerStringPublicTls"),
+        ("public-sasl-scram", "BootstrapBrokerStringPublicSaslScram"),
+        ("public-sasl-iam", "BootstrapBrokerStringPublicSaslIam"),
+    ]
+    .iter()
+    .filter_map(|(name, key)| output[*key].as_str().map(|s| (*name, s)))
+    .collect();
+
+    // Print just the string if there's only one so that it can be
+    // used directly in scripts
+    if let [(_, string)] = strings.as_slice() {
+        println!("{}", string);
+    } else {
+        for (name, string) in strings {
+            println!("{}: {}", name, string);
+        }
+    }
+}
+
+#[throws]
+fn mq_request(
+    method: &str,
+    path: &str,
+    params: &[(&str, &str)],
+) -> serde_json::Value {
+    let output = send_signed_request(method, "mq", "mq", path, params, None)?;
+    if output.is_empty() {
+        return serde_json::Value::Null;
+    }
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+#[throws]
+fn mq_broker_pages(
+    mut each_page: impl FnMut(Vec<serde_json::Value>) -> Result<()>,
+) {
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(token) = &next_token {
+            params.push(("nextToken", token.as_str()));
+        }
+        let mut output = mq_request("GET", "/v1/brokers", &params)?;
+        each_page(
+            output["brokerSummaries"]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        )?;
+
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+}
+
+#[throws]
+fn mq_get_brokers() -> Vec<serde_json::Value> {
+    let mut brokers = Vec::new();
+    mq_broker_pages(|mut page| {
+        brokers.append(&mut page);
+        Ok(())
+    })?;
+    brokers
+}
+
+/// Get the ID of a broker identified by name or ID.
+#[throws]
+fn mq_find_broker_id(broker: &str) -> String {
+    mq_get_brokers()?
+        .into_iter()
+        .find(|candidate| {
+            candidate["brokerId"] == broker || candidate["brokerName"] == broker
+        })
+        .and_then(|candidate| candidate["brokerId"].as_str().map(String::from))
+        .with_context(|| format!("broker not found: {}", broker))?
+}
+
+#[throws]
+fn mq_brokers() {
+    let mut table = TableStream::new(vec![
+        "ID".to_string(),
+        "NAME".to_string(),
+        "ENGINE".to_string(),
+        "STATE".to_string(),
+        "ENDPOINTS".to_string(),
+    ]);
+    mq_broker_pages(|brokers| {
+        for broker in brokers {
+            let id = broker["brokerId"].as_str().unwrap_or_default();
+            // The summary doesn't include the engine version or endpoints
+            let details =
+                mq_request("GET", &format!("/v1/brokers/{}", id), &[])?;
+            let endpoints: Vec<_> = details["brokerInstances"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|instance| instance["endpoints"].as_array())
+                .flatten()
+                .filter_map(|endpoint| endpoint.as_str())
+                .collect();
+            table.push(vec![
+                id.into(),
+                broker["brokerName"].as_str().unwrap_or_default().into(),
+                format!(
+                    "{} {}",
+                    broker["engineType"].as_str().unwrap_or_default(),
+                    details["engineVersion"].as_str().unwrap_or_default()
+                ),
+                broker["brokerState"].as_str().unwrap_or_default().into(),
+                endpoints.join(", "),
+            ]);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+}
+
+#[throws]
+fn mq_reboot(broker: String) {
+    let id = mq_find_broker_id(&broker)?;
+    mq_request("POST", &format!("/v1/brokers/{}/reboot", id), &[])?;
+    println!("rebooting {}", id);
+}
+
+#[throws]
+fn opensearch_request(
+    method: &str,
+    path: &str,
+    payload: Option<serde_json::Value>,
+) -> serde_json::Value {
+    let output = send_signed_request(method, "es", "es", path, &[], payload)?;
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+/// Get the status of domains, or all domains if `names` is empty.
+#[throws]
+fn opensearch_describe_domains(names: Vec<String>) -> Vec<serde_json::Value> {
+    let names = if names.is_empty() {
+        let output = opensearch_request("GET", "/2021-01-01/domain", None)?;
+        output["DomainNames"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|domain| domain["DomainName"].as_str())
+            .map(String::from)
+            .collect()
+    } else {
+        names
+    };
+
+    let mut domains = Vec::new();
+    // DescribeDomains accepts at most five domains per request
+    for chunk in names.chunks(5) {
+        let mut output = opensearch_request(
+            "POST",
+            "/2021-01-01/opensearch/domain-info",
+            Some(serde_json::json!({ "DomainNames": chunk })),
+        )?;
+        if let Some(list) = output["DomainStatusList"].as_array_mut() {
+            domains.append(list);
+        }
+    }
+    domains
+}
+
+/// Get a domain's cluster health color. The API doesn't report
+/// health directly, so it comes from the ClusterStatus metrics.
+#[throws]
+fn opensearch_health(
+    client: &CloudWatchClient,
+    account_id: &str,
+    domain: &str,
+) -> String {
+    let now = Utc::now();
+    for color in &["red", "yellow", "green"] {
+        let output = client
+            .get_metric_statistics(GetMetricStatisticsInput {
+                namespace: "AWS/ES".into(),
+                metric_name: format!("ClusterStatus.{}", color),
+                dimensions: Some(vec![
+                    Dimension {
+                        name: "DomainName".into(),
+                        value: domain.into(),
+                    },
+                    Dimension {
+                        name: "ClientId".into(),
+                        value: account_id.into(),
+                    },
+                ]),
+                start_time: (now - Duration::minutes(15)).to_rfc3339(),
+                end_time: now.to_rfc3339(),
+                period: 60,
+                statistics: Some(vec!["Maximum".into()]),
+                ..Default::default()
+            })
+            .sync()
+            .with_context(|| format!("failed to get health of {}", domain))?;
+        let latest = output
+            .datapoints
+            .unwrap_or_default()
+            .into_iter()
+            .max_by(|a, b| a.timestamp.cmp(&b.timestamp))
+            .and_then(|point| point.maximum);
+        if latest == Some(1.0) {
+            return color.to_string();
+        }
+    }
+    String::new()
+}
+
+#[throws]
+fn opensearch_domains() {
+    let client = CloudWatchClient::new(Region::default());
+    let account_id = get_account_id()?;
+    let mut rows = vec![vec![
+        "NAME".to_string(),
+        "VERSION".to_string(),
+        "DATA NODES".to_string(),
+        "MASTER NODES".to_string(),
+        "STORAGE".to_string(),
+        "HEALTH".to_string(),
+    ]];
+    for domain in opensearch_describe_domains(Vec::new())? {
+        let name = domain["DomainName"].as_str().unwrap_or_default();
+        let cluster = &domain["ClusterConfig"];
+        let data_nodes = format!(
+            "{} x {}",
+            cluster["InstanceCount"],
+            cluster["InstanceType"].as_str().unwrap_or_default()
+        );
+        let master_nodes = if cluster["DedicatedMasterEnabled"] == true {
+            format!(
+                "{} x {}",
+                cluster["DedicatedMasterCount"],
+                cluster["DedicatedMasterType"].as_str().unwrap_or_default()
+            )
+        } else {
+            "-".into()
+        };
+        let ebs = &domain["EBSOptions"];
+        let storage = if ebs["EBSEnabled"] == true {
+            format!(
+                "{} GiB {}",
+                ebs["VolumeSize"],
+                ebs["VolumeType"].as_str().unwrap_or_default()
+            )
+        } else {
+            "instance".into()
+        };
+        rows.push(vec![
+            name.into(),
+            domain["EngineVersion"].as_str().unwrap_or_default().into(),
+            data_nodes,
+            master_nodes,
+            storage,
+            opensearch_health(&client, &account_id, name)?,
+        ]);
+    }
+    print_table(&rows);
+}
+
+#[throws]
+fn opensearch_endpoint(domain: String, copy: bool) {
+    let status = opensearch_describe_domains(vec![domain.clone()])?
+        .into_iter()
+        .next()
+        .with_context(|| format!("domain not found: {}", domain))?;
+    // Domains in a VPC have a VPC endpoint instead of a public one
+    let endpoint = status["Endpoint"]
+        .as_str()
+        .or_else(|| status["Endpoints"]["vpc"].as_str())
+        .with_context(|| format!("domain has no endpoint yet: {}", domain))?;
+    let url = format!("https://{}", endpoint);
+    println!("{}", url);
+    if copy {
+        copy_to_clipboard(&url)?;
+    }
+}
+
+#[throws]
+fn config_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    json_request("config", "config", "StarlingDoveService", action, payload)?
+}
+
+/// Call `each_page` with the items of each page of a paginated Config
+/// operation.
+#[throws]
+fn config_pages(
+    action: &str,
+    payload: serde_json::Value,
+    key: &str,
+    mut each_page: impl FnMut(Vec<serde_json::Value>) -> Result<()>,
+) {
+    let mut payload = payload;
+    loop {
+        let mut output = config_request(action, payload.clone())?;
+        each_page(
+            output[key]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        )?;
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => payload["NextToken"] = token.into(),
+            None => break,
+        }
+    }
+}
+
+#[throws]
+fn config_rules(args: ConfigRules) {
+    let mut payload = serde_json::json!({});
+    if args.noncompliant_only {
+        payload["ComplianceTypes"] = serde_json::json!(["NON_COMPLIANT"]);
+    }
+    let mut table = TableStream::new(vec![
+        "RULE".to_string(),
+        "COMPLIANCE".to_string(),
+        "NONCOMPLIANT".to_string(),
+    ]);
+    config_pages(
+        "DescribeComplianceByConfigRule",
+        payload,
+        "ComplianceByConfigRules",
+        |rules| {
+            for rule in rules {
+                let compliance = &rule["Compliance"];
+                let count = &compliance["ComplianceContributorCount"];
+                let count = match count["CappedCount"].as_i64() {
+                    Some(n) if count["CapExceeded"] == true => {
+                        format!("{}+", n)
+                    }
+                    Some(n) => n.to_string(),
+                    None => String::new(),
+                };
+                table.push(vec![
+                    rule["ConfigRuleName"].as_str().unwrap_or_default().into(),
+                    compliance["ComplianceType"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .into(),
+                    count,
+                ]);
+            }
+            table.flush();
+            Ok(())
+        },
+    )?;
+    table.finish();
+}
+
+#[throws]
+fn config_resources(rule: String) {
+    let mut table = TableStream::new(vec![
+        "TYPE".to_string(),
+        "RESOURCE".to_string(),
+        "RECORDED".to_string(),
+        "ANNOTATION".to_string(),
+    ]);
+    config_pages(
+        "GetComplianceDetailsByConfigRule",
+        serde_json::json!({
+            "ConfigRuleName": rule,
+            "ComplianceTypes": ["NON_COMPLIANT"],
+        }),
+        "EvaluationResults",
+        |results| {
+            for result in results {
+                let qualifier = &result["EvaluationResultIdentifier"]
+                    ["EvaluationResultQualifier"];
+                table.push(vec![
+                    qualifier["ResourceType"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .into(),
+                    qualifier["ResourceId"].as_str().unwrap_or_default().into(),
+                    result["ResultRecordedTime"]
+                        .as_f64()
+                        .map(format_timestamp_secs)
+                        .unwrap_or_default(),
+                    result["Annotation"].as_str().unwrap_or_default().into(),
+                ]);
+            }
+            table.flush();
+            Ok(())
+        },
+    )?;
+    table.finish();
+}
+
+#[throws]
+fn securityhub_request(
+    method: &str,
+    path: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    let output = send_signed_request(
+        method,
+        "securityhub",
+        "securityhub",
+        path,
+        &[],
+        Some(payload),
+    )?;
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+/// Build a GetFindings filter matching any of the values exactly.
+fn securityhub_filter(values: &[String]) -> serde_json::Value {
+    values
+        .iter()
+        .map(|value| serde_json::json!({ "Value": value, "Comparison": "EQUALS" }))
+        .collect()
+}
+
+#[throws]
+fn securityhub_finding_pages(
+    filters: serde_json::Value,
+    mut each_page: impl FnMut(Vec<serde_json::Value>) -> Result<()>,
+) {
+    let mut payload =
+        serde_json::json!({ "Filters": filters, "MaxResults": 100 });
+    loop {
+        let mut output =
+            securityhub_request("POST", "/findings", payload.clone())?;
+        each_page(
+            output["Findings"]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        )?;
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => payload["NextToken"] = token.into(),
+            None => break,
+        }
+    }
+}
+
+#[throws]
+fn securityhub_get_findings(
+    filters: serde_json::Value,
+) -> Vec<serde_json::Value> {
+    let mut findings = Vec::new();
+    securityhub_finding_pages(filters, |mut page| {
+        findings.append(&mut page);
+        Ok(())
+    })?;
+    findings
+}
+
+/// Set the workflow status of a finding.
+#[throws]
+fn securityhub_update_workflow(id: &str, status: &str) {
+    // Updates need the product ARN as well as the finding ID
+    let finding = securityhub_get_findings(serde_json::json!({
+        "Id": securityhub_filter(&[id.to_string()]),
+    }))?
+    .into_iter()
+    .next()
+    .with_context(|| format!("finding not found: {}", id))?;
+    let output = securityhub_request(
+        "PATCH",
+        "/findings/batchupdate",
+        serde_json::json!({
+            "FindingIdentifiers": [{
+                "Id": finding["Id"],
+                "ProductArn": finding["ProductArn"],
+            }],
+            "Workflow": { "Status": status },
+        }),
+    )?;
+    if let Some(unprocessed) = output["UnprocessedFindings"]
+        .as_array()
+        .and_then(|list| list.first())
+    {
+        throw!(anyhow!(
+            "failed to update {}: {}",
+            id,
+            unprocessed["ErrorMessage"].as_str().unwrap_or_default()
+        ));
+    }
+    println!("{} is now {}", id, status);
+}
+
+#[throws]
+fn securityhub_findings(args: SecurityHubFindings) {
+    if let Some(id) = &args.suppress {
+        return securityhub_update_workflow(id, "SUPPRESSED")?;
+    }
+    if let Some(id) = &args.resolve {
+        return securityhub_update_workflow(id, "RESOLVED")?;
+    }
+
+    let mut filters = serde_json::json!({
+        "RecordState": securityhub_filter(&["ACTIVE".to_string()]),
+    });
+    if !args.severities.is_empty() {
+        filters["SeverityLabel"] = securityhub_filter(&args.severities);
+    }
+    if !args.statuses.is_empty() {
+        filters["WorkflowStatus"] = securityhub_filter(&args.statuses);
+    }
+
+    let mut table = TableStream::new(vec![
+        "SEVERITY".to_string(),
+        "STATUS".to_string(),
+        "UPDATED".to_string(),
+        "RESOURCE".to_string(),
+        "TITLE".to_string(),
+        "ID".to_string(),
+    ]);
+    securityhub_finding_pages(filters, |findings| {
+        for finding in findings {
+            table.push(vec![
+                finding["Severity"]["Label"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                finding["Workflow"]["Status"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                finding["UpdatedAt"]
+                    .as_str()
+                    .and_then(|date| date.get(..10))
+                    .unwrap_or_default()
+                    .into(),
+                finding["Resources"][0]["Id"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                finding["Title"].as_str().unwrap_or_default().into(),
+                finding["Id"].as_str().unwrap_or_default().into(),
+            ]);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+}
+
+/// Inspector severities from most to least severe.
+const INSPECTOR_SEVERITIES: &[&str] = &[
+    "CRITICAL",
+    "HIGH",
+    "MEDIUM",
+    "LOW",
+    "INFORMATIONAL",
+    "UNTRIAGED",
+];
+
+fn inspector_severity_rank(severity: &str) -> usize {
+    INSPECTOR_SEVERITIES
+        .iter()
+        .position(|s| s.eq_ignore_ascii_case(severity))
+        .unwrap_or(INSPECTOR_SEVERITIES.len())
+}
+
+/// Build the filter criteria for a resource, which is either an
+/// instance ID, a resource ARN, or an ECR image such as repo:tag or
+/// repo@sha256:digest.
+fn inspector_resource_filter(resource: &str) -> serde_json::Value {
+    let equals = |value: &str| serde_json::json!([{ "comparison": "EQUALS", "value": value }]);
+    if resource.starts_with("i-") || resource.starts_with("arn:") {
+        serde_json::json!({ "resourceId": equals(resource) })
+    } else if let Some((repository, digest)) = resource.split_once('@') {
+        serde_json::json!({
+            "ecrImageRepositoryName": equals(repository),
+            "ecrImageHash": equals(digest),
+        })
+    } else if let Some((repository, tag)) = resource.split_once(':') {
+        serde_json::json!({
+            "ecrImageRepositoryName": equals(repository),
+            "ecrImageTags": equals(tag),
+        })
+    } else {
+        serde_json::json!({ "ecrImageRepositoryName": equals(resource) })
+    }
+}
+
+#[throws]
+fn inspector_findings(args: InspectorFindings) {
+    if let Some(severity) = &args.fail_on {
+        if inspector_severity_rank(severity) == INSPECTOR_SEVERITIES.len() {
+            throw!(anyhow!("unknown severity: {}", severity));
+        }
+    }
+
+    let mut filters = match &args.resource {
+        Some(resource) => inspector_resource_filter(resource),
+        None => serde_json::json!({}),
+    };
+    filters["findingStatus"] =
+        serde_json::json!([{ "comparison": "EQUALS", "value": "ACTIVE" }]);
+
+    let mut findings = Vec::new();
+    let mut payload = serde_json::json!({
+        "filterCriteria": filters,
+        "maxResults": 100,
+    });
+    loop {
+        let output = send_signed_request(
+            "POST",
+            "inspector2",
+            "inspector2",
+            "/findings/list",
+            &[],
+            Some(payload.clone()),
+        )?;
+        let mut output: serde_json::Value = serde_json::from_slice(&output)
+            .context("failed to parse ListFindings response")?;
+        if let Some(list) = output["findings"].as_array_mut() {
+            findings.append(list);
+        }
+
+        // Finish if there are no more results
+        match output["nextToken"].as_str() {
+            Some(token) => payload["nextToken"] = token.into(),
+            None => break,
+        }
+    }
+
+    // Group findings by vulnerability, so that a CVE affecting many
+    // resources is only listed once
+    struct Vulnerability {
+        severity: String,
+        resources: HashSet<String>,
+        packages: HashSet<String>,
+        fixed_in: HashSet<String>,
+    }
+    let mut vulnerabilities: BTreeMap<String, Vulnerability> = BTreeMap::new();
+    for finding in &findings {
+        let details = &finding["packageVulnerabilityDetails"];
+        let id = details["vulnerabilityId"]
+            .as_str()
+            .or_else(|| finding["title"].as_str())
+            .unwrap_or_default();
+        let entry =
+            vulnerabilities
+                .entry(id.into())
+                .or_insert_with(|| Vulnerability {
+                    severity: finding["severity"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .into(),
+                    resources: HashSet::new(),
+                    packages: HashSet::new(),
+                    fixed_in: HashSet::new(),
+                });
+        for resource in finding["resources"].as_array().into_iter().flatten() {
+            entry
+                .resources
+                .extend(resource["id"].as_str().map(String::from));
+        }
+        for package in details["vulnerablePackages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            let name = package["name"].as_str().unwrap_or_default();
+            entry.packages.insert(format!(
+                "{} {}",
+                name,
+                package["version"].as_str().unwrap_or_default()
+            ));
+            if let Some(fixed) = package["fixedInVersion"].as_str() {
+                if fixed != "NotAvailable" {
+                    entry.fixed_in.insert(format!("{} {}", name, fixed));
+                }
+            }
+        }
+    }
+    let mut vulnerabilities: Vec<_> = vulnerabilities.into_iter().collect();
+    vulnerabilities.sort_by_key(|(_, vulnerability)| {
+        inspector_severity_rank(&vulnerability.severity)
+    });
+
+    let join = |set: &HashSet<String>| {
+        let mut values: Vec<_> = set.iter().cloned().collect();
+        values.sort_unstable();
+        values.join(", ")
+    };
+    let mut rows = vec![vec![
+        "SEVERITY".to_string(),
+        "VULNERABILITY".to_string(),
+        "RESOURCES".to_string(),
+        "PACKAGES".to_string(),
+        "FIXED IN".to_string(),
+    ]];
+    for (id, vulnerability) in &vulnerabilities {
+        rows.push(vec![
+            vulnerability.severity.clone(),
+            id.clone(),
+            vulnerability.resources.len().to_string(),
+            join(&vulnerability.packages),
+            join(&vulnerability.fixed_in),
+        ]);
+    }
+    print_table(&rows);
+
+    if let Some(severity) = &args.fail_on {
+        let threshold = inspector_severity_rank(severity);
+        let failing = vulnerabilities
+            .iter()
+            .filter(|(_, v)| inspector_severity_rank(&v.severity) <= threshold)
+            .count();
+        if failing > 0 {
+            throw!(anyhow!(
+                "{} vulnerabilities at or above {}",
+                failing,
+                severity.to_uppercase()
+            ));
+        }
+    }
+}
+
+#[throws]
+fn waf_request(action: &str, payload: serde_json::Value) -> serde_json::Value {
+    json_request("wafv2", "wafv2", "AWSWAF_20190729", action, payload)?
+}
+
+/// Get the WAF scope, checking that CloudFront web ACLs are only
+/// requested from us-east-1, which is the only region they're
+/// available in.
+#[throws]
+fn waf_scope(cloudfront: bool) -> &'static str {
+    if !cloudfront {
+        return "REGIONAL";
+    }
+    if Region::default() != Region::UsEast1 {
+        throw!(anyhow!(
+            "CloudFront web ACLs are only available in us-east-1"
+        ));
+    }
+    "CLOUDFRONT"
+}
+
+#[throws]
+fn waf_acl_pages(
+    scope: &str,
+    mut each_page: impl FnMut(Vec<serde_json::Value>) -> Result<()>,
+) {
+    let mut payload = serde_json::json!({ "Scope": scope, "Limit": 100 });
+    loop {
+        let mut output = waf_request("ListWebACLs", payload.clone())?;
+        each_page(
+            output["WebACLs"]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        )?;
+
+        // Finish if there are no more results
+        match output["NextMarker"].as_str() {
+            Some(marker) => payload["NextMarker"] = marker.into(),
+            None => break,
+        }
+    }
+}
+
+#[throws]
+fn waf_get_acls(scope: &str) -> Vec<serde_json::Value> {
+    let mut acls = Vec::new();
+    waf_acl_pages(scope, |mut page| {
+        acls.append(&mut page);
+        Ok(())
+    })?;
+    acls
+}
+
+/// Get a web ACL identified by name or ID.
+#[throws]
+fn waf_get_acl(acl: &str, scope: &str) -> serde_json::Value {
+    let summary = waf_get_acls(scope)?
+        .into_iter()
+        .find(|candidate| candidate["Name"] == acl || candidate["Id"] == acl)
+        .with_context(|| format!("web ACL not found: {}", acl))?;
+    let mut output = waf_request(
+        "GetWebACL",
+        serde_json::json!({
+            "Name": summary["Name"],
+            "Id": summary["Id"],
+            "Scope": scope,
+        }),
+    )?;
+    output["WebACL"].take()
+}
+
+/// Get the name of the first key of an object, such as the action
+/// type in {"Block": {}}.
+fn waf_first_key(value: &serde_json::Value) -> String {
+    value
+        .as_object()
+        .and_then(|object| object.keys().next())
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[throws]
+fn waf_acls(cloudfront: bool) {
+    let scope = waf_scope(cloudfront)?;
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "ID".to_string(),
+        "DESCRIPTION".to_string(),
+    ]);
+    waf_acl_pages(scope, |acls| {
+        for acl in acls {
+            table.push(vec![
+                acl["Name"].as_str().unwrap_or_default().into(),
+                acl["Id"].as_str().unwrap_or_default().into(),
+                acl["Description"].as_str().unwrap_or_default().into(),
+            ]);
+        }
+        table.flush();
+        Ok(())
+    })?;
+    table.finish();
+}
+
+#[throws]
+fn waf_rules(acl: String, cloudfront: bool) {
+    let scope = waf_scope(cloudfront)?;
+    let acl = waf_get_acl(&acl, scope)?;
+    let mut rows = vec![vec![
+        "PRIORITY".to_string(),
+        "NAME".to_string(),
+        "ACTION".to_string(),
+        "STATEMENT".to_string(),
+    ]];
+    for rule in acl["Rules"].as_array().into_iter().flatten() {
+        // Rule groups have an override action instead of an action
+        let action = if rule["Action"].is_object() {
+            waf_first_key(&rule["Action"])
+        } else {
+            match waf_first_key(&rule["OverrideAction"]).as_str() {
+                "None" => "(rule group)".to_string(),
+                action => action.to_string(),
+            }
+        };
+        let statement_type = waf_first_key(&rule["Statement"]);
+        let statement = &rule["Statement"][&statement_type];
+        let mut description =
+            statement_type.trim_end_matches("Statement").to_string();
+        if let Some(name) = statement["Name"].as_str() {
+            description = format!("{} {}", description, name);
+        }
+        if let Some(limit) = statement["Limit"].as_i64() {
+            description = format!("{} {}", description, limit);
+        }
+        rows.push(vec![
+            rule["Priority"].to_string(),
+            rule["Name"].as_str().unwrap_or_default().into(),
+            action,
+            description,
+        ]);
+    }
+    rows.push(vec![
+        "-".into(),
+        "(default)".into(),
+        waf_first_key(&acl["DefaultAction"]),
+        String::new(),
+    ]);
+    print_table(&rows);
+}
+
+#[throws]
+fn waf_sampled_requests(args: WafSampledRequests) {
+    let scope = waf_scope(args.cloudfront)?;
+    let acl = waf_get_acl(&args.acl, scope)?;
+    // Requests are sampled per rule metric, the web ACL's own metric
+    // covers requests handled by the default action
+    let metric_name = match &args.rule {
+        Some(name) => acl["Rules"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|rule| rule["Name"] == name.as_str())
+            .and_then(|rule| rule["VisibilityConfig"]["MetricName"].as_str())
+            .with_context(|| format!("rule not found: {}", name))?,
+        None => acl["VisibilityConfig"]["MetricName"]
+            .as_str()
+            .context("missing metric name")?,
+    };
+
+    let end = Utc::now();
+    let start = end - args.since;
+    let output = waf_request(
+        "GetSampledRequests",
+        serde_json::json!({
+            "WebAclArn": acl["ARN"],
+            "RuleMetricName": metric_name,
+            "Scope": scope,
+            "TimeWindow": {
+                "StartTime": start.timestamp(),
+                "EndTime": end.timestamp(),
+            },
+            "MaxItems": 500,
+        }),
+    )?;
+
+    let mut rows = vec![vec![
+        "TIME".to_string(),
+        "ACTION".to_string(),
+        "RULE".to_string(),
+        "CLIENT".to_string(),
+        "COUNTRY".to_string(),
+        "METHOD".to_string(),
+        "URI".to_string(),
+    ]];
+    for sample in output["SampledRequests"].as_array().into_iter().flatten() {
+        let request = &sample["Request"];
+        rows.push(vec![
+            sample["Timestamp"]
+                .as_f64()
+                .map(format_timestamp_secs)
+                .unwrap_or_default(),
+            sample["Action"].as_str().unwrap_or_default().into(),
+            sample["RuleNameWithinRuleGroup"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+            request["ClientIP"].as_str().unwrap_or_default().into(),
+            request["Country"].as_str().unwrap_or_default().into(),
+            request["Method"].as_str().unwrap_or_default().into(),
+            request["URI"].as_str().unwrap_or_default().into(),
+        ]);
+    }
+    print_table(&rows);
+    // Only a sample of the matching requests is returned
+    println!(
+        "{} requests sampled out of {}",
+        rows.len() - 1,
+        output["PopulationSize"]
+    );
+}
+
+#[throws]
+fn synthetics_request(
+    path: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    let output = send_signed_request(
+        "POST",
+        "synthetics",
+        "synthetics",
+        path,
+        &[],
+        Some(payload),
+    )?;
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+/// Format the duration of a canary run from its timeline.
+fn format_canary_duration(timeline: &serde_json::Value) -> String {
+    match (timeline["Started"].as_f64(), timeline["Completed"].as_f64()) {
+        (Some(start), Some(end)) => format_duration_short(
+            Duration::milliseconds(((end - start) * 1000.0) as i64),
+        ),
+        _ => String::new(),
+    }
+}
+
+#[throws]
+fn cw_canaries() {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "LAST RUN".to_string(),
+        "STATE".to_string(),
+        "DURATION".to_string(),
+        "REASON".to_string(),
+    ]);
+    let mut payload = serde_json::json!({});
+    loop {
+        let output = synthetics_request("/canaries/last-run", payload.clone())?;
+        for canary in output["CanariesLastRun"].as_array().into_iter().flatten()
+        {
+            let run = &canary["LastRun"];
+            table.push(vec![
+                canary["CanaryName"].as_str().unwrap_or_default().into(),
+                run["Timeline"]["Started"]
+                    .as_f64()
+                    .map(format_timestamp_secs)
+                    .unwrap_or_default(),
+                run["Status"]["State"].as_str().unwrap_or_default().into(),
+                format_canary_duration(&run["Timeline"]),
+                run["Status"]["StateReason"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => payload["NextToken"] = token.into(),
+            None => break,
+        }
+    }
+    table.finish();
+}
+
+#[throws]
+fn cw_canary_runs(name: String) {
+    let output = synthetics_request(
+        &format!("/canary/{}/runs", name),
+        serde_json::json!({ "MaxResults": 20 }),
+    )?;
+    let runs = output["CanaryRuns"].as_array().cloned().unwrap_or_default();
+
+    let mut rows = vec![vec![
+        "STARTED".to_string(),
+        "STATE".to_string(),
+        "DURATION".to_string(),
+        "REASON".to_string(),
+    ]];
+    for run in &runs {
+        rows.push(vec![
+            run["Timeline"]["Started"]
+                .as_f64()
+                .map(format_timestamp_secs)
+                .unwrap_or_default(),
+            run["Status"]["State"].as_str().unwrap_or_default().into(),
+            format_canary_duration(&run["Timeline"]),
+            run["Status"]["StateReason"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+        ]);
+    }
+    print_table(&rows);
+
+    // Show where the screenshots and logs of failed runs are
+    let failed: Vec<(&serde_json::Value, &str)> = runs
+        .iter()
+        .filter(|run| run["Status"]["State"] == "FAILED")
+        .filter_map(|run| Some((run, run["ArtifactS3Location"].as_str()?)))
+        .collect();
+    // The artifact buckets may be in other regions, so create one
+    // client per bucket rather than one per run
+    let mut clients = HashMap::new();
+    for (_, location) in &failed {
+        if let Some((bucket, _)) = location.split_once('/') {
+            if !clients.contains_key(bucket) {
+                clients.insert(bucket, s3_client_for_bucket(bucket)?);
+            }
+        }
+    }
+    for (run, location) in failed {
+        let (bucket, prefix) = match location.split_once('/') {
+            Some(parts) => parts,
+            None => continue,
+        };
+        println!();
+        println!(
+            "{}:",
+            run["Timeline"]["Started"]
+                .as_f64()
+                .map(format_timestamp_secs)
+                .unwrap_or_default()
+        );
+        let output = clients[bucket]
+            .list_objects_v2(ListObjectsV2Request {
+                bucket: bucket.into(),
+                prefix: Some(prefix.into()),
+                ..Default::default()
+            })
+            .sync()
+            .with_context(|| format!("failed to list s3://{}", location))?;
+        for object in output.contents.unwrap_or_default() {
+            println!("  s3://{}/{}", bucket, object.key.unwrap_or_default());
+        }
+    }
+}
+
+/// Get the CloudWatch dimension value of an Application Load Balancer
+/// identified by name or ARN, such as "app/web/0123456789abcdef".
+#[throws]
+fn elb_get_dimension(load_balancer: &str) -> String {
+    let arn = if load_balancer.starts_with("arn:") {
+        load_balancer.to_string()
+    } else {
+        let output = query_request(
+            "elasticloadbalancing",
+            &Region::default(),
+            "2015-12-01",
+            "DescribeLoadBalancers",
+            &[("Names.member.1", load_balancer)],
+        )?;
+        output
+            .child("DescribeLoadBalancersResult")
+            .map(|result| result.items("LoadBalancers"))
+            .unwrap_or_default()
+            .first()
+            .and_then(|lb| lb.child_text("LoadBalancerArn"))
+            .with_context(|| {
+                format!("load balancer not found: {}", load_balancer)
+            })?
+            .to_string()
+    };
+    arn.split_once(":loadbalancer/")
+        .map(|(_, dimension)| dimension.to_string())
+        .with_context(|| format!("invalid load balancer ARN: {}", arn))?
+}
+
+/// Get the sum of a load balancer metric over a time range.
+#[throws]
+fn elb_metric_sum(
+    client: &CloudWatchClient,
+    dimension: &str,
+    metric_name: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> f64 {
+    // Requests are limited to 1440 datapoints
+    let period = if end - start <= Duration::days(60) {
+        3600
+    } else {
+        86400
+    };
+    let output = client
+        .get_metric_statistics(GetMetricStatisticsInput {
+            namespace: "AWS/ApplicationELB".into(),
+            metric_name: metric_name.into(),
+            dimensions: Some(vec![Dimension {
+                name: "LoadBalancer".into(),
+                value: dimension.into(),
+            }]),
+            start_time: start.to_rfc3339(),
+            end_time: end.to_rfc3339(),
+            period,
+            statistics: Some(vec!["Sum".into()]),
+            ..Default::default()
+        })
+        .sync()
+        .with_context(|| format!("failed to get {}", metric_name))?;
+    output
+        .datapoints
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|point| point.sum)
+        .sum()
+}
+
+#[throws]
+fn elb_slo(args: ElbSlo) {
+    if args.target <= 0.0 || args.target >= 100.0 {
+        throw!(anyhow!("target must be between 0 and 100"));
+    }
+    let dimension = elb_get_dimension(&args.load_balancer)?;
+    let client = CloudWatchClient::new(Region::default());
+    let end = Utc::now();
+    let start = end - args.since;
+    let requests =
+        elb_metric_sum(&client, &dimension, "RequestCount", start, end)?;
+    // Count errors from both the targets and the load balancer itself
+    let target_errors = elb_metric_sum(
+        &client,
+        &dimension,
+        "HTTPCode_Target_5XX_Count",
+        start,
+        end,
+    )?;
+    let elb_errors = elb_metric_sum(
+        &client,
+        &dimension,
+        "HTTPCode_ELB_5XX_Count",
+        start,
+        end,
+    )?;
+    let errors = target_errors + elb_errors;
+
+    println!("requests: {}", requests as u64);
+    println!(
+        "5xx errors: {} ({} from targets, {} from the load balancer)",
+        errors as u64, target_errors as u64, elb_errors as u64
+    );
+    if requests == 0.0 {
+        println!("no requests in this period");
+        return;
+    }
+    let success_rate = 100.0 * (1.0 - errors / requests);
+    let budget = requests * (1.0 - args.target / 100.0);
+    println!(
+        "success rate: {:.3}% (target {}%)",
+        success_rate, args.target
+    );
+    println!(
+        "error budget consumed: {:.1}% ({} of {} allowed errors)",
+        100.0 * errors / budget,
+        errors as u64,
+        budget as u64
+    );
+}
+
+#[throws]
+fn sagemaker_request(
+    action: &str,
+    payload: serde_json::Value,
+) -> serde_json::Value {
+    json_request("sagemaker", "api.sagemaker", "SageMaker", action, payload)?
+}
+
+#[throws]
+fn sagemaker_notebooks() {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "STATUS".to_string(),
+        "INSTANCE TYPE".to_string(),
+        "MODIFIED".to_string(),
+    ]);
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut payload = serde_json::json!({});
+        if let Some(token) = &next_token {
+            payload["NextToken"] = token.as_str().into();
+        }
+        let output = sagemaker_request("ListNotebookInstances", payload)?;
+        for notebook in
+            output["NotebookInstances"].as_array().into_iter().flatten()
+        {
+            table.push(vec![
+                notebook["NotebookInstanceName"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                notebook["NotebookInstanceStatus"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                notebook["InstanceType"].as_str().unwrap_or_default().into(),
+                notebook["LastModifiedTime"]
+                    .as_f64()
+                    .map(format_timestamp_secs)
+                    .unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    table.finish();
+}
+
+/// Start or stop a notebook instance.
+#[throws]
+fn sagemaker_notebook_action(action: &str, name: String) {
+    sagemaker_request(
+        action,
+        serde_json::json!({ "NotebookInstanceName": name }),
+    )?;
+}
+
+#[throws]
+fn sagemaker_endpoints() {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "STATUS".to_string(),
+        "INSTANCE TYPE".to_string(),
+        "CREATED".to_string(),
+    ]);
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut payload = serde_json::json!({});
+        if let Some(token) = &next_token {
+            payload["NextToken"] = token.as_str().into();
+        }
+        let output = sagemaker_request("ListEndpoints", payload)?;
+        for endpoint in output["Endpoints"].as_array().into_iter().flatten() {
+            let name = endpoint["EndpointName"].as_str().unwrap_or_default();
+            // The instance type is only in the endpoint config
+            let details = sagemaker_request(
+                "DescribeEndpoint",
+                serde_json::json!({ "EndpointName": name }),
+            )?;
+            let config = sagemaker_request(
+                "DescribeEndpointConfig",
+                serde_json::json!({
+                    "EndpointConfigName": details["EndpointConfigName"],
+                }),
+            )?;
+            let variants: Vec<_> = config["ProductionVariants"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|variant| match variant["InstanceType"].as_str() {
+                    Some(instance_type) => format!(
+                        "{} x{}",
+                        instance_type,
+                        variant["InitialInstanceCount"]
+                            .as_i64()
+                            .unwrap_or_default()
+                    ),
+                    None => "serverless".into(),
+                })
+                .collect();
+            table.push(vec![
+                name.into(),
+                endpoint["EndpointStatus"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into(),
+                variants.join(", "),
+                endpoint["CreationTime"]
+                    .as_f64()
+                    .map(format_timestamp_secs)
+                    .unwrap_or_default(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    table.finish();
+}
+
+#[throws]
+fn sagemaker_invoke(args: SageMakerInvoke) {
+    let body = fs::read_to_string(&args.body)
+        .with_context(|| format!("failed to read {}", args.body.display()))?;
+    let body: serde_json::Value = serde_json::from_str(&body)
+        .with_context(|| format!("invalid JSON in {}", args.body.display()))?;
+    let output = send_signed_request(
+        "POST",
+        "sagemaker",
+        "runtime.sagemaker",
+        &format!("/endpoints/{}/invocations", args.endpoint),
+        &[],
+        Some(body),
+    )?;
+    std::io::stdout().write_all(&output)?;
+}
+
+#[throws]
+fn scheduler_request(
+    method: &str,
+    path: &str,
+    params: &[(&str, &str)],
+    payload: Option<serde_json::Value>,
+) -> serde_json::Value {
+    let output = send_signed_request(
+        method,
+        "scheduler",
+        "scheduler",
+        path,
+        params,
+        payload,
+    )?;
+    if output.is_empty() {
+        return serde_json::Value::Null;
+    }
+    serde_json::from_slice(&output)
+        .with_context(|| format!("failed to parse {} response", path))?
+}
+
+/// Parse a field of a cron expression into the sorted list of values
+/// it matches. Returns None for syntax that isn't supported, such as
+/// `L`, `W`, or `#`.
+fn parse_cron_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+) -> Option<Vec<u32>> {
+    let parse_value = |s: &str| -> Option<u32> {
+        match names.iter().position(|name| name.eq_ignore_ascii_case(s)) {
+            Some(index) => Some(min + index as u32),
+            None => s.parse().ok(),
+        }
+    };
+    let mut values = Vec::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().ok()?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return None;
+        }
+        let (start, end) = if range == "*" || range == "?" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start)?, parse_value(end)?)
+        } else {
+            let start = parse_value(range)?;
+            (start, if range.len() < part.len() { max } else { start })
+        };
+        values.extend((start..=end).step_by(step));
+    }
+    values.sort_unstable();
+    values.dedup();
+    Some(values)
+}
+
+/// Get the next time a schedule expression fires after `now`. Rate
+/// expressions are counted from `start`. Returns None if the
+/// schedule won't fire again or the expression isn't supported.
+fn scheduler_next_fire(
+    expression: &str,
+    start: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let (kind, args) = expression.strip_suffix(')')?.split_once('(')?;
+    match kind {
+        "at" => {
+            let time = NaiveDateTime::parse_from_str(args, "%Y-%m-%dT%H:%M:%S")
+                .ok()?;
+            Some(Utc.from_utc_datetime(&time)).filter(|time| *time > now)
+        }
+        "rate" => {
+            let (num, unit) = args.split_once(' ')?;
+            let num: i64 = num.parse().ok()?;
+            let period = match unit.trim_end_matches('s') {
+                "minute" => Duration::minutes(num),
+                "hour" => Duration::hours(num),
+                "day" => Duration::days(num),
+                _ => return None,
+            };
+            let start = start?;
+            if start > now {
+                return Some(start);
+            }
+            let elapsed = (now - start).num_seconds() / period.num_seconds();
+            Some(start + period * (elapsed as i32 + 1))
+        }
+        "cron" => {
+            let fields: Vec<_> = args.split_whitespace().collect();
+            if fields.len() != 6 {
+                return None;
+            }
+            let minutes = parse_cron_field(fields[0], 0, 59, &[])?;
+            let hours = parse_cron_field(fields[1], 0, 23, &[])?;
+            let days = parse_cron_field(fields[2], 1, 31, &[])?;
+            let months = parse_cron_field(
+                fields[3],
+                1,
+                12,
+                &[
+                    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG",
+                    "SEP", "OCT", "NOV", "DEC",
+                ],
+            )?;
+            let weekdays = parse_cron_field(
+                fields[4],
+                1,
+                7,
+                &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"],
+            )?;
+            let years = parse_cron_field(fields[5], 1970, 2199, &[])?;
+
+            // Check each day for the next five years
+            let mut date = now.date_naive();
+            for _ in 0..366 * 5 {
+                if years.contains(&(date.year() as u32))
+                    && months.contains(&date.month())
+                    && days.contains(&date.day())
+                    && weekdays.contains(&date.weekday().number_from_sunday())
+                {
+                    for hour in &hours {
+                        for minute in &minutes {
+                            let time = Utc.from_utc_datetime(
+                                &date.and_hms_opt(*hour, *minute, 0)?,
+                            );
+                            if time > now {
+                                return Some(time);
+                            }
+                        }
+                    }
+                }
+                date = date.succ_opt()?;
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Format a duration as a short human-readable string such as "3h 20m".
+fn format_duration_short(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    if minutes < 1 {
+        format!("{}s", duration.num_seconds())
+    } else if minutes < 60 {
+        format!("{}m {}s", minutes, duration.num_seconds() % 60)
+    } else if minutes < 60 * 24 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}d {}h", minutes / (60 * 24), minutes % (60 * 24) / 60)
+    }
+}
+
+#[throws]
+fn scheduler_list(args: SchedulerList) {
+    let mut table = TableStream::new(vec![
+        "NAME".to_string(),
+        "GROUP".to_string(),
+        "STATE".to_string(),
+        "EXPRESSION".to_string(),
+        "NEXT FIRE".to_string(),
+        "TARGET".to_string(),
+    ]);
+    let now = Utc::now();
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(group) = &args.group {
+            params.push(("ScheduleGroup", group.as_str()));
+        }
+        if let Some(token) = &next_token {
+            params.push(("NextToken", token.as_str()));
+        }
+        let output = scheduler_request("GET", "/schedules", &params, None)?;
+        for summary in output["Schedules"].as_array().into_iter().flatten() {
+            let name = summary["Name"].as_str().unwrap_or_default();
+            let group = summary["GroupName"].as_str().unwrap_or_default();
+            // The summary doesn't include the expression
+            let schedule = scheduler_request(
+                "GET",
+                &format!("/schedules/{}", name),
+                &[("groupName", group)],
+                None,
+            )?;
+            let expression =
+                schedule["ScheduleExpression"].as_str().unwrap_or_default();
+            let timezone = schedule["ScheduleExpressionTimezone"]
+                .as_str()
+                .filter(|tz| *tz != "UTC" && *tz != "Etc/UTC");
+            let start = schedule["StartDate"]
+                .as_f64()
+                .or_else(|| schedule["CreationDate"].as_f64())
+                .and_then(|secs| Utc.timestamp_opt(secs as i64, 0).single());
+
+            // Only UTC times can be computed for at and cron
+            // expressions in other timezones
+            let next_fire = if schedule["State"] != "ENABLED"
+                || (timezone.is_some() && !expression.starts_with("rate("))
+            {
+                None
+            } else {
+                scheduler_next_fire(expression, start, now)
+            };
+            table.push(vec![
+                name.into(),
+                group.into(),
+                schedule["State"].as_str().unwrap_or_default().into(),
+                match timezone {
+                    Some(tz) => format!("{} {}", expression, tz),
+                    None => expression.into(),
+                },
+                next_fire.map_or("-".into(), |time| {
+                    format!(
+                        "{} (in {})",
+                        time.format("%Y-%m-%d %H:%M:%S"),
+                        format_duration_short(time - now)
+                    )
+                }),
+                summary["Target"]["Arn"].as_str().unwrap_or_default().into(),
+            ]);
+        }
+        table.flush();
+
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    table.finish();
+}
+
+#[throws]
+fn scheduler_create(args: SchedulerCreate) {
+    let mut target = serde_json::json!({
+        "Arn": args.target_arn,
+        "RoleArn": args.role_arn,
+    });
+    if let Some(input) = args.input {
+        target["Input"] = input.into();
+    }
+    let mut payload = serde_json::json!({
+        "ScheduleExpression": args.expression,
+        "FlexibleTimeWindow": { "Mode": "OFF" },
+        "Target": target,
+    });
+    if let Some(group) = args.group {
+        payload["GroupName"] = group.into();
+    }
+    if let Some(timezone) = args.timezone {
+        payload["ScheduleExpressionTimezone"] = timezone.into();
+    }
+    if args.delete_after_completion {
+        payload["ActionAfterCompletion"] = "DELETE".into();
+    }
+    let output = scheduler_request(
+        "POST",
+        &format!("/schedules/{}", args.name),
+        &[],
+        Some(payload),
+    )?;
+    println!("{}", output["ScheduleArn"].as_str().unwrap_or_default());
+}
+
+#[throws]
+fn scheduler_delete(name: String, group: Option<String>) {
+    let mut params = Vec::new();
+    if let Some(group) = &group {
+        params.push(("groupName", group.as_str()));
+    }
+    scheduler_request(
+        "DELETE",
+        &format!("/schedules/{}", name),
+        &params,
+        None,
+    )?;
+}
+
+#[cfg(feature = "ses")]
+#[throws]
+fn ses_stats() {
+    let client = SesClient::new(Region::default());
+    let output = client
+        .get_send_statistics()
+        .sync()
+        .context("failed to get sending statistics")?;
+    // The data points cover 15-minute periods over the last two
+    // weeks, combine them into daily totals
+    let mut days: BTreeMap<String, [i64; 4]> = BTreeMap::new();
+    for point in output.send_data_points.unwrap_or_default() {
+        let timestamp = point.timestamp.unwrap_or_default();
+        let day = timestamp.get(..10).unwrap_or_default().to_string();
+        let totals = days.entry(day).or_default();
+        totals[0] += point.delivery_attempts.unwrap_or(0);
+        totals[1] += point.bounces.unwrap_or(0);
+        totals[2] += point.complaints.unwrap_or(0);
+        totals[3] += point.rejects.unwrap_or(0);
+    }
+    let mut rows = vec![vec![
+        "DATE".to_string(),
+        "SENDS".to_string(),
+        "BOUNCES".to_string(),
+        "COMPLAINTS".to_string(),
+        "REJECTS".to_string(),
+    ]];
+    for (day, totals) in days {
+        let mut row = vec![day];
+        row.extend(totals.iter().map(|n| n.to_string()));
+        rows.push(row);
+    }
+    print_table(&rows);
+}
+
+#[cfg(feature = "ses")]
+#[throws]
+fn ses_list_suppressed() {
+    let mut suppressed = Vec::new();
+    let mut next_token: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if let Some(token) = &next_token {
+            params.push(("NextToken", token.as_str()));
+        }
+        // The suppression list is only available in the SES v2 API
+        let output = send_signed_request(
+            "GET",
+            "ses",
+            "email",
+            "/v2/email/suppression/addresses",
+            &params,
+            None,
+        )?;
+        let output: serde_json::Value = serde_json::from_slice(&output)
+            .context("failed to parse suppression list")?;
+        let summaries = output["SuppressedDestinationSummaries"].as_array();
+        for summary in summaries.into_iter().flatten() {
+            suppressed.push(vec![
+                summary["EmailAddress"].as_str().unwrap_or_default().into(),
+                summary["Reason"].as_str().unwrap_or_default().into(),
+                // Timestamps are in seconds since the epoch
+                format_timestamp_secs(
+                    summary["LastUpdateTime"].as_f64().unwrap_or(0.0),
+                ),
+            ]);
+        }
+        // Finish if there are no more results
+        match output["NextToken"].as_str() {
+            Some(token) => next_token = Some(token.into()),
+            None => break,
+        }
+    }
+    suppressed.sort_unstable();
+
+    let mut rows = vec![vec![
+        "EMAIL".to_string(),
+        "REASON".to_string(),
+        "UPDATED".to_string(),
+    ]];
+    rows.extend(suppressed);
+    print_table(&rows);
+}
+
+#[cfg(feature = "ses")]
+#[throws]
+fn ses_remove_suppressed(email: String) {
+    send_signed_request(
+        "DELETE",
+        "ses",
+        "email",
+        &format!("/v2/email/suppression/addresses/{}", email),
+        &[],
+        None,
+    )
+    .with_context(|| format!("failed to remove {}", email))?;
+}
+
+#[cfg(feature = "workspaces")]
+#[throws]
+fn workspaces_list() {
+    let client = WorkspacesClient::new(Region::default());
+    let mut workspaces = Vec::new();
+    let mut next_token = None;
+    loop {
+        let output = client
+            .describe_workspaces(DescribeWorkspacesRequest {
+                next_token: next_token.clone(),
+                ..Default::default()
+            })
+            .sync()
+            .context("failed to list workspaces")?;
+        for workspace in output.workspaces.unwrap_or_default() {
+            workspaces.push(vec![
+                workspace.workspace_id.unwrap_or_default(),
+                workspace.state.unwrap_or_default(),
+                workspace.user_name.unwrap_or_default(),
+                workspace.bundle_id.unwrap_or_default(),
+            ]);
+        }
+        // Finish if there are no more results
+        if output.next_token.is_none() {
+            break;
+        }
+        next_token = output.next_token;
+    }
+    // Sort by user name
+    workspaces.sort_unstable_by(|a, b| a[2].cmp(&b[2]));
+
+    let mut rows = vec![vec![
+        "ID".to_string(),
+        "STATE".to_string(),
+        "USER".to_string(),
+        "BUNDLE".to_string(),
+    ]];
+    rows.extend(workspaces);
+    print_table(&rows);
+}
+
+/// Workspace operations report failures in the response rather than
+/// as an error, convert the first failure to an error.
+#[cfg(feature = "workspaces")]
+#[throws]
+fn check_workspace_failures(failed: Option<Vec<FailedWorkspaceChangeRequest>>) {
+    if let Some(failure) = failed.unwrap_or_default().into_iter().next() {
+        throw!(anyhow!(
+            "{}: {}",
+            failure.error_code.unwrap_or_default(),
+            failure.error_message.unwrap_or_default()
+        ));
+    }
+}
+
+#[cfg(feature = "workspaces")]
+#[throws]
+fn workspaces_reboot(workspace_id: String) {
+    let client = WorkspacesClient::new(Region::default());
+    let output = client
+        .reboot_workspaces(RebootWorkspacesRequest {
+            reboot_workspace_requests: vec![RebootRequest {
+                workspace_id: workspace_id.clone(),
+            }],
+        })
+        .sync()
+        .context("failed to reboot workspace")?;
+    check_workspace_failures(output.failed_requests)
+        .with_context(|| format!("failed to reboot {}", workspace_id))?;
+}
+
+#[cfg(feature = "workspaces")]
+#[throws]
+fn workspaces_rebuild(workspace_id: String) {
+    let client = WorkspacesClient::new(Region::default());
+    let output = client
+        .rebuild_workspaces(RebuildWorkspacesRequest {
+            rebuild_workspace_requests: vec![RebuildRequest {
+                workspace_id: workspace_id.clone(),
+            }],
+        })
+        .sync()
+        .context("failed to rebuild workspace")?;
+    check_workspace_failures(output.failed_requests)
+        .with_context(|| format!("failed to rebuild {}", workspace_id))?;
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2RunCommand {
+    /// Run on instances with this tag (KEY=VALUE). If repeated,
+    /// instances must match all the tags.
+    #[structopt(
+        long = "tag",
+        required = true,
+        number_of_values = 1,
+        parse(try_from_str = parse_key_value)
+    )]
+    tags: Vec<(String, String)>,
+    /// Shell command to run.
+    #[structopt(last = true, required = true)]
+    command: Vec<String>,
+}
+
+#[throws]
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .context("HOME is not set")?
+}
+
+/// Read a section of the AWS config file, for example "default",
+/// "profile dev", or "sso-session my-sso".
+#[throws]
+fn read_aws_config_section(section: &str) -> HashMap<String, String> {
+    let path = match std::env::var_os("AWS_CONFIG_FILE") {
+        Some(path) => PathBuf::from(path),
+        None => home_dir()?.join(".aws/config"),
+    };
+    // Credentials can be configured without a config file
+    if !path.exists() {
+        return HashMap::new();
+    }
+    read_config_section(&path, section)?
+}
+
+#[throws]
+fn claws_config_path() -> PathBuf {
+    match std::env::var_os("CLAWS_CONFIG_FILE") {
+        Some(path) => PathBuf::from(path),
+        None => home_dir()?.join(".config/claws/config"),
+    }
+}
+
+/// Read a section of the claws config file, for example
+/// "preset.prod-logs". Values can optionally be quoted.
+#[throws]
+fn read_claws_config_section(section: &str) -> HashMap<String, String> {
+    read_config_section(&claws_config_path()?, section)?
+        .into_iter()
+        .map(|(key, value)| {
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .map(String::from)
+                .unwrap_or(value);
+            (key, value)
+        })
+        .collect()
+}
+
+/// Write a section of the claws config file, replacing the section if
+/// it already exists. Values are quoted.
+#[throws]
+fn write_claws_config_section(section: &str, values: &[(&str, &str)]) {
+    write_config_section(&claws_config_path()?, section, values)?;
+}
+
+/// Write a section of an INI-style config file, replacing the section
+/// if it already exists. Values are quoted.
+#[throws]
+fn write_config_section(path: &Path, section: &str, values: &[(&str, &str)]) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => Err(err)
+            .with_context(|| format!("failed to read {}", path.display()))?,
+    };
+    let mut output = String::new();
+    let mut in_section = false;
+    for line in contents.lines() {
+        if let Some(name) = line
+            .trim()
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            in_section = name.trim() == section;
+        }
+        if !in_section {
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    if !output.is_empty() && !output.ends_with("\n\n") {
+        output.push('\n');
+    }
+    output.push_str(&format!("[{}]\n", section));
+    for (key, value) in values {
+        output.push_str(&format!("{} = \"{}\"\n", key, value));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, output)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+}
+
+/// Read a section of an INI-style config file.
+#[throws]
+fn read_config_section(path: &Path, section: &str) -> HashMap<String, String> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut values = HashMap::new();
+    let mut in_section = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            in_section = name.trim() == section;
+        } else if in_section {
+            if let Some((key, value)) = line.split_once('=') {
+                values.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+    }
+    values
+}
+
+/// Get credentials from a rusoto provider.
+#[throws]
+fn get_credentials<P>(provider: &P) -> AwsCredentials
+where
+    P: ProvideAwsCredentials,
+    P::Future: Send + 'static,
+{
+    // Some providers need the runtime that rusoto uses for requests,
+    // so wrap the future to run it the same way
+    RusotoFuture::<_, Infallible>::from_future(
+        provider.credentials().map_err(RusotoError::Credentials),
+    )
+    .sync()
+    .context("failed to get credentials")?
+}
+
+/// An SSO profile from the AWS config file.
+struct SsoProfile {
+    start_url: String,
+    region: String,
+    account_id: String,
+    role_name: String,
+}
+
+impl SsoProfile {
+    /// Read an SSO profile from the AWS config file. Returns None if
+    /// the profile isn't configured for SSO.
+    #[throws]
+    fn load(profile: &str) -> Option<SsoProfile> {
+        let section = if profile == "default" {
+            profile.to_string()
+        } else {
+            format!("profile {}", profile)
+        };
+        let mut values = read_aws_config_section(&section)?;
+        // Newer configs put the start URL and region in a separate
+        // sso-session section
+        if let Some(session) = values.get("sso_session") {
+            let session =
+                read_aws_config_section(&format!("sso-session {}", session))?;
+            values.extend(session);
+        }
+        if !values.contains_key("sso_start_url") {
+            return None;
+        }
+        let mut get = |key: &str| {
+            values.remove(key).with_context(|| {
+                format!("profile {} is missing {}", profile, key)
+            })
+        };
+        Some(SsoProfile {
+            start_url: get("sso_start_url")?,
+            region: get("sso_region")?,
+            account_id: get("sso_account_id")?,
+            role_name: get("sso_role_name")?,
+        })
+    }
+
+    /// Get the access token cached by "aws sso login".
+    #[throws]
+    fn cached_token(&self) -> String {
+        let dir = home_dir()?.join(".aws/sso/cache");
+        let mut best: Option<(String, String)> = None;
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("failed to read {}", dir.display()))?
+        {
+            let contents = fs::read_to_string(entry?.path())?;
+            let json: serde_json::Value = match serde_json::from_str(&contents)
+            {
+                Ok(json) => json,
+                Err(_) => continue,
+            };
+            if json["startUrl"].as_str() != Some(&self.start_url) {
+                continue;
+            }
+            let (token, expires) = match (
+                json["accessToken"].as_str(),
+                json["expiresAt"].as_str(),
+            ) {
+                (Some(token), Some(expires)) => (token, expires),
+                _ => continue,
+            };
+            // Use the token that expires last
+            if best
+                .as_ref()
+                .is_none_or(|(_, best)| expires > best.as_str())
+            {
+                best = Some((token.to_string(), expires.to_string()));
+            }
+        }
+        let (token, expires) =
+            best.context("no cached SSO token found, run \"aws sso login\"")?;
+        if let Ok(expires) = chrono::DateTime::parse_from_rfc3339(&expires) {
+            if expires < Utc::now() {
+                throw!(anyhow!("SSO token has expired, run \"aws sso login\""));
+            }
+        }
+        token
+    }
+
+    #[throws]
+    fn credentials(&self) -> AwsCredentials {
+        let region: Region = self.region.parse()?;
+        let mut request = SignedRequest::new(
+            "GET",
+            "sso",
+            &region,
+            "/federation/credentials",
+        );
+        request.set_hostname(Some(format!(
+            "portal.sso.{}.amazonaws.com",
+            region.name()
+        )));
+        request.add_param("account_id", &self.account_id);
+        request.add_param("role_name", &self.role_name);
+        request.add_header("x-amz-sso_bearer_token", &self.cached_token()?);
+        // The request is authorized by the token rather than by signing
+        let client = Client::new_not_signing(
+            HttpClient::new().context("failed to create HTTP client")?,
+        );
+        let body = dispatch_request(&client, request)
+            .context("failed to get SSO role credentials")?;
+        let json: serde_json::Value = serde_json::from_slice(&body)?;
+        let creds = &json["roleCredentials"];
+        let expiration = creds["expiration"]
+            .as_i64()
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single());
+        AwsCredentials::new(
+            creds["accessKeyId"]
+                .as_str()
+                .context("missing access key")?,
+            creds["secretAccessKey"]
+                .as_str()
+                .context("missing secret key")?,
+            creds["sessionToken"].as_str().map(String::from),
+            expiration,
+        )
+    }
+}
+
+/// Format credentials the way the EC2 instance metadata service does.
+fn format_imds_credentials(creds: &AwsCredentials) -> String {
+    let format_time = |time: chrono::DateTime<Utc>| {
+        time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    };
+    serde_json::json!({
+        "Code": "Success",
+        "LastUpdated": format_time(Utc::now()),
+        "Type": "AWS-HMAC",
+        "AccessKeyId": creds.aws_access_key_id(),
+        "SecretAccessKey": creds.aws_secret_access_key(),
+        "Token": creds.token(),
+        "Expiration": creds.expires_at().map(format_time),
+    })
+    .to_string()
+}
+
+// Not using #[throws] here because it doesn't handle the boxed
+// closure type
+fn sso_serve(args: SsoServe) -> Result<(), Error> {
+    let profile = args
+        .profile
+        .clone()
+        .or_else(|| std::env::var("AWS_PROFILE").ok())
+        .unwrap_or_else(|| "default".into());
+
+    let fetch: Box<dyn Fn() -> Result<AwsCredentials> + Send> =
+        if let Some(role_arn) = args.role_arn {
+            let provider = StsAssumeRoleSessionCredentialsProvider::new(
+                StsClient::new(Region::default()),
+                role_arn,
+                "claws".into(),
+                None,
+                None,
+                None,
+                None,
+            );
+            Box::new(move || get_credentials(&provider))
+        } else if let Some(sso) = SsoProfile::load(&profile)? {
+            Box::new(move || sso.credentials())
+        } else if args.profile.is_some() {
+            let mut provider = ProfileProvider::new()?;
+            provider.set_profile(profile);
+            Box::new(move || get_credentials(&provider))
+        } else {
+            let provider = DefaultCredentialsProvider::new()?;
+            Box::new(move || get_credentials(&provider))
+        };
+
+    // Get the initial credentials before starting the server so that
+    // configuration problems are reported right away
+    let credentials = Arc::new(Mutex::new(fetch()?));
+
+    // Refresh the credentials in the background shortly before they
+    // expire
+    let refresh = credentials.clone();
+    thread::spawn(move || loop {
+        let expires = *refresh.lock().unwrap().expires_at();
+        let wait = expires
+            .map(|expires| expires - Utc::now() - Duration::minutes(5))
+            .unwrap_or_else(|| Duration::minutes(15))
+            .max(Duration::minutes(1));
+        thread::sleep(wait.to_std().unwrap_or(time::Duration::from_secs(60)));
+        match fetch() {
+            Ok(creds) => *refresh.lock().unwrap() = creds,
+            Err(err) => eprintln!("failed to refresh credentials: {:#}", err),
+        }
+    });
+
+    let listener = TcpListener::bind(&args.listen)
+        .with_context(|| format!("failed to listen on {}", args.listen))?;
+    println!(
+        "serving credentials, set \
+         AWS_EC2_METADATA_SERVICE_ENDPOINT=http://{}",
+        args.listen
+    );
+    let role_path = "/latest/meta-data/iam/security-credentials/";
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        // Requests are handled one at a time, so don't let a client
+        // that stops sending hold up the others
+        if stream
+            .set_read_timeout(Some(time::Duration::from_secs(5)))
+            .is_err()
+        {
+            continue;
+        }
+        // Read the request line and skip the headers, none of the
+        // requests have a body
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).is_err() {
+            continue;
+        }
+        let mut header = String::new();
+        while reader.read_line(&mut header).is_ok() && header.trim() != "" {
+            header.clear();
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let path = parts.next().unwrap_or_default();
+
+        let (status, body) = match (method, path) {
+            // IMDSv2 session token, which isn't checked
+            ("PUT", "/latest/api/token") => ("200 OK", "claws".to_string()),
+            ("GET", path) if path == role_path.trim_end_matches('/') => {
+                ("200 OK", "claws".to_string())
+            }
+            ("GET", path) if path == role_path => {
+                ("200 OK", "claws".to_string())
+            }
+            ("GET", path) if path.strip_prefix(role_path) == Some("claws") => (
+                "200 OK",
+                format_imds_credentials(&credentials.lock().unwrap()),
+            ),
+            _ => ("404 Not Found", String::new()),
+        };
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+    }
+    Ok(())
+}
+
+/// Get the regions available to the account, including regions that
+/// haven't been enabled.
+#[throws]
+fn get_account_regions() -> Vec<rusoto_ec2::Region> {
+    let client = Ec2Client::new(Region::default());
+    let output = client
+        .describe_regions(DescribeRegionsRequest {
+            all_regions: Some(true),
+            ..Default::default()
+        })
+        .sync()
+        .context("failed to list regions")?;
+    let mut regions = output.regions.unwrap_or_default();
+    regions.sort_unstable_by_key(|region| region.region_name.clone());
+    regions
+}
+
+fn is_region_enabled(region: &rusoto_ec2::Region) -> bool {
+    region.opt_in_status.as_deref() != Some("not-opted-in")
+}
+
+#[throws]
+fn regions_list() {
+    let mut rows = vec![vec!["REGION".to_string(), "STATUS".to_string()]];
+    for region in get_account_regions()? {
+        rows.push(vec![
+            region.region_name.unwrap_or_default(),
+            region.opt_in_status.unwrap_or_default(),
+        ]);
+    }
+    print_table(&rows);
+}
+
+/// Measure the time to open a TCP connection to a host. The best of
+/// a few attempts is used to reduce noise.
+fn measure_latency(host: &str) -> Option<time::Duration> {
+    let addr = (host, 443).to_socket_addrs().ok()?.next()?;
+    (0..3)
+        .filter_map(|_| {
+            let start = time::Instant::now();
+            TcpStream::connect_timeout(&addr, time::Duration::from_secs(5))
+                .ok()?;
+            Some(start.elapsed())
+        })
+        .min()
+}
+
+#[throws]
+fn regions_ping() {
+    let regions: Vec<_> = get_account_regions()?
+        .into_iter()
+        .filter(is_region_enabled)
+        .collect();
+    let handles: Vec<_> = regions
+        .into_iter()
+        .map(|region| {
+            thread::spawn(move || {
+                let name = region.region_name.unwrap_or_default();
+                let host = region
+                    .endpoint
+                    .unwrap_or_else(|| format!("ec2.{}.amazonaws.com", name));
+                (name, measure_latency(&host))
+            })
+        })
+        .collect();
+    let mut results: Vec<_> = handles
+        .into_iter()
+        .filter_map(|handle| handle.join().ok())
+        .collect();
+    // Sort by latency, with unreachable regions last
+    results.sort_unstable_by_key(|(_, latency)| {
+        latency.unwrap_or(time::Duration::MAX)
+    });
+
+    let mut rows = vec![vec!["REGION".to_string(), "LATENCY".to_string()]];
+    for (name, latency) in &results {
+        let latency = match latency {
+            Some(latency) => format!("{} ms", latency.as_millis()),
+            None => "unreachable".into(),
+        };
+        rows.push(vec![name.clone(), latency]);
+    }
+    print_table(&rows);
+
+    if let Some((name, Some(_))) = results.first() {
+        println!("\nclosest region: {}", name);
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Addr {
+    /// Instance IDs or names.
+    instances: Vec<String>,
+    /// Look up the reverse DNS names of the public IP.
+    #[structopt(long)]
+    rdns: bool,
+    /// Look up the owner and location of the public IP with whois.
+    #[structopt(long)]
+    whois: bool,
+    /// Copy the public IP (or private IP if there's no public one) to
+    /// the clipboard.
+    #[structopt(long)]
+    copy: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Console {
+    /// Instance ID or name.
+    instance: String,
+    /// Get the most recent output instead of the output captured at
+    /// the last boot. Only supported on Nitro instances.
+    #[structopt(long)]
+    latest: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Screenshot {
+    /// Instance ID or name.
+    instance: String,
+    /// Path to write the JPEG image to [default: <instance-id>.jpg].
+    #[structopt(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Ssh {
+    /// Instance ID or name.
+    instance: String,
+    /// Connect to the private IP instead of the public IP.
+    #[structopt(long)]
+    private: bool,
+    /// User to log in as (defaults to the usual user for the AMI).
+    #[structopt(long)]
+    user: Option<String>,
+    /// Additional arguments to pass to ssh.
+    #[structopt(last = true)]
+    ssh_args: Vec<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Reachability {
+    /// Source instance ID or name.
+    source_instance: String,
+    /// Destination in the form <ip>:<port>.
+    destination: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2FlowLogsEnable {
+    /// ID of a VPC, subnet, or network interface.
+    resource_id: String,
+    /// CloudWatch Logs group to deliver flow logs to.
+    #[structopt(long)]
+    to_cw: String,
+    /// ARN of an IAM role that can publish to the log group.
+    #[structopt(long)]
+    role_arn: String,
+    /// Traffic to log: ACCEPT, REJECT, or ALL.
+    #[structopt(long, default_value = "ALL")]
+    traffic_type: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2FlowLogsQuery {
+    log_group_name: String,
+    /// Only show records matching space-separated field conditions,
+    /// such as "dstport=443 action=REJECT".
+    #[structopt(long)]
+    filter: Option<String>,
+    /// Show records newer than this duration, such as "10m" or "1h".
+    #[structopt(long, default_value = "1h")]
+    since: String,
+    /// Keep waiting for new records.
+    #[structopt(long)]
+    follow: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2Sg {
+    /// Add an inbound rule.
+    Allow(Ec2SgRule),
+    /// Remove an inbound rule.
+    Revoke(Ec2SgRule),
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2FlowLogs {
+    /// Enable flow logs delivered to CloudWatch Logs.
+    Enable(Ec2FlowLogsEnable),
+    /// Show flow log records from a log group.
+    Query(Ec2FlowLogsQuery),
+}
+
+#[derive(Debug, StructOpt)]
+enum CwContributorInsights {
+    /// List Contributor Insights rules.
+    List,
+    /// Enable rules.
+    Enable { rule_names: Vec<String> },
+    /// Disable rules.
+    Disable { rule_names: Vec<String> },
+}
+
+#[derive(Debug, StructOpt)]
+struct CwAnomalyDetector {
+    namespace: String,
+    metric_name: String,
+    /// Statistic to model.
+    #[structopt(long, default_value = "Average")]
+    stat: String,
+    /// Metric dimension in the form NAME=VALUE.
+    #[structopt(
+        long = "dimension",
+        parse(try_from_str = parse_key_value),
+        number_of_values = 1
+    )]
+    dimensions: Vec<(String, String)>,
+}
+
+#[derive(Debug, StructOpt)]
+enum CwAnomalyDetectors {
+    /// List anomaly detectors.
+    List,
+    /// Create an anomaly detector for a metric.
+    Enable(CwAnomalyDetector),
+    /// Delete a metric's anomaly detector.
+    Disable(CwAnomalyDetector),
+}
+
+#[derive(Debug, StructOpt)]
+struct ConfigRules {
+    /// Only list rules with non-compliant resources.
+    #[structopt(long)]
+    noncompliant_only: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Config {
+    /// List Config rules with their compliance.
+    Rules(ConfigRules),
+    /// List the non-compliant resources of a rule.
+    Resources { rule: String },
+}
+
+#[derive(Debug, StructOpt)]
+enum Cw {
+    /// Manage Contributor Insights rules.
+    ContributorInsights(CwContributorInsights),
+    /// Manage metric anomaly detectors.
+    AnomalyDetectors(CwAnomalyDetectors),
+    /// List Synthetics canaries with the result of their last run.
+    Canaries,
+    /// Show a Synthetics canary's runs.
+    Canary(CwCanary),
+}
+
+#[derive(Debug, StructOpt)]
+enum CwCanary {
+    /// List recent runs, with the artifacts of failed runs.
+    Runs { name: String },
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2Tgw {
+    /// List transit gateways.
+    List,
+    /// List a transit gateway's attachments and their route tables.
+    Attachments { transit_gateway_id: String },
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2Peering {
+    /// List VPC peering connections.
+    List,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2EndpointsCreate {
+    vpc_id: String,
+    /// Service name, either in full or a short name such as "s3".
+    service: String,
+    /// Subnets to create an interface endpoint in.
+    #[structopt(long)]
+    subnets: Vec<String>,
+    /// Route tables to attach a gateway endpoint to.
+    #[structopt(long)]
+    route_tables: Vec<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2EndpointsCommand {
+    /// Create a VPC endpoint.
+    Create(Ec2EndpointsCreate),
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Endpoints {
+    /// Only list endpoints in this VPC.
+    #[structopt(long)]
+    vpc: Option<String>,
+    #[structopt(subcommand)]
+    command: Option<Ec2EndpointsCommand>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2Vpn {
+    /// List VPN connections with the status of each tunnel, and
+    /// client VPN endpoints.
+    List,
+    /// Show tunnel telemetry for a VPN connection.
+    Telemetry { vpn_connection_id: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2VolumesModify {
+    volume_id: String,
+    /// New size in GiB.
+    #[structopt(long)]
+    size: Option<i64>,
+    /// New volume type, such as gp3 or io2.
+    #[structopt(long = "type")]
+    volume_type: Option<String>,
+    /// New provisioned IOPS.
+    #[structopt(long)]
+    iops: Option<i64>,
+    /// New provisioned throughput in MiB/s (gp3 only).
+    #[structopt(long)]
+    throughput: Option<i64>,
+    /// Wait for the modification, including the optimization phase,
+    /// to complete.
+    #[structopt(long)]
+    wait: bool,
+    /// With --wait, give up after this long, such as 30m or 6h.
+    #[structopt(long, default_value = "1h", parse(try_from_str = parse_duration))]
+    timeout: Duration,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2VolumesCommand {
+    /// Resize a volume or change its type or performance.
+    Modify(Ec2VolumesModify),
+    /// Attach a volume to an instance.
+    Attach(Ec2VolumesAttach),
+    /// Detach a volume from its instance.
+    Detach {
+        volume_id: String,
+        /// Force the detachment if it doesn't happen cleanly. The
+        /// instance doesn't get a chance to flush its file system.
+        #[structopt(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2VolumesAttach {
+    volume_id: String,
+    /// Instance ID or name.
+    instance: String,
+    /// Device name to expose the volume as, such as /dev/sdf.
+    #[structopt(long)]
+    device: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2InstanceTypes {
+    /// Only show types with at least this much memory in GiB.
+    #[structopt(long)]
+    min_memory: Option<f64>,
+    /// Only show types with at least this many vCPUs.
+    #[structopt(long)]
+    min_vcpus: Option<u64>,
+    /// Only show types supporting this architecture, such as x86_64
+    /// or arm64.
+    #[structopt(long)]
+    arch: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Wait {
+    /// State to wait for: running, stopped, or terminated.
+    state: String,
+    /// Instances to wait for (IDs or Name tags).
+    #[structopt(required = true)]
+    instances: Vec<String>,
+    /// Give up after this long, such as 30s or 10m.
+    #[structopt(long, default_value = "10m", parse(try_from_str = parse_duration))]
+    timeout: Duration,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Enis {
+    /// Only show network interfaces attached to this instance (ID
+    /// or Name tag).
+    #[structopt(long)]
+    instance: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Volumes {
+    #[structopt(subcommand)]
+    command: Option<Ec2VolumesCommand>,
+    /// Sort by size, largest first.
+    #[structopt(long)]
+    sort_by_size: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2SnapshotsCopy {
+    snapshot_id: String,
+    /// Region to copy the snapshot to.
+    #[structopt(long)]
+    to_region: String,
+    /// Encrypt the copy.
+    #[structopt(long)]
+    encrypt: bool,
+    /// KMS key to encrypt the copy with (implies --encrypt).
+    #[structopt(long)]
+    kms_key: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2SnapshotsEnableFsr {
+    snapshot_id: String,
+    /// Availability zone to enable fast snapshot restore in.
+    #[structopt(long = "az", required = true, number_of_values = 1)]
+    azs: Vec<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2SnapshotsCommand {
+    /// Create a snapshot of a volume.
+    Create {
+        volume_id: String,
+        #[structopt(long)]
+        description: Option<String>,
+    },
+    /// Delete a snapshot.
+    Delete { snapshot_id: String },
+    /// Copy a snapshot to another region.
+    Copy(Ec2SnapshotsCopy),
+    /// Enable fast snapshot restore for a snapshot.
+    EnableFsr(Ec2SnapshotsEnableFsr),
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Snapshots {
+    #[structopt(subcommand)]
+    command: Option<Ec2SnapshotsCommand>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2DlmCreate {
+    /// Snapshot volumes with this tag, in the form KEY=VALUE.
+    #[structopt(long, parse(try_from_str = parse_key_value))]
+    target_tag: (String, String),
+    /// How often to snapshot: "daily", "weekly", or a number of
+    /// hours such as "12h".
+    #[structopt(long, default_value = "daily")]
+    schedule: String,
+    /// Number of snapshots to keep.
+    #[structopt(long, default_value = "7")]
+    retain: u32,
+    /// IAM role for the policy (defaults to the
+    /// AWSDataLifecycleManagerDefaultRole role).
+    #[structopt(long)]
+    role_arn: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2Dlm {
+    /// List lifecycle policies.
+    List,
+    /// Create a policy that snapshots volumes with a tag.
+    Create(Ec2DlmCreate),
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Azs {
+    /// Region to list zones for (defaults to the current region).
+    #[structopt(long)]
+    region: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Launch {
+    /// AMI to launch.
+    #[structopt(long)]
+    ami: String,
+    /// Instance type, such as t3.micro.
+    #[structopt(long)]
+    instance_type: String,
+    /// Name of the key pair to allow SSH with.
+    #[structopt(long)]
+    key_name: Option<String>,
+    /// Security group ID. Can be repeated.
+    #[structopt(long = "security-group", number_of_values = 1)]
+    security_groups: Vec<String>,
+    /// Subnet to launch in (defaults to the default VPC).
+    #[structopt(long)]
+    subnet: Option<String>,
+    /// Value of the Name tag.
+    #[structopt(long)]
+    name: Option<String>,
+    /// File to pass as user data.
+    #[structopt(long)]
+    user_data: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2SpotLaunch {
+    #[structopt(flatten)]
+    launch: Ec2Launch,
+    /// Maximum price per hour in USD (defaults to the on-demand
+    /// price).
+    #[structopt(long)]
+    max_price: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2Spot {
+    /// List spot instance requests.
+    Requests,
+    /// Show the current spot price of an instance type in each
+    /// availability zone.
+    Prices {
+        instance_type: String,
+        /// Operating system: linux, windows, rhel, or suse.
+        #[structopt(long, default_value = "linux")]
+        os: String,
+    },
+    /// Launch a spot instance.
+    Launch(Ec2SpotLaunch),
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Resize {
+    /// Instance ID or name.
+    instance: String,
+    /// New instance type, such as m5.large.
+    instance_type: String,
+    /// Start the instance after resizing it.
+    #[structopt(long)]
+    start: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2CreateImage {
+    /// Instance ID or name.
+    instance: String,
+    /// Name of the new AMI.
+    #[structopt(long)]
+    name: String,
+    /// Don't reboot the instance before creating the image.
+    #[structopt(long)]
+    no_reboot: bool,
+    /// Wait until the image is available.
+    #[structopt(long)]
+    wait: bool,
+    /// With --wait, give up after this long, such as 30s or 10m.
+    #[structopt(long, default_value = "30m", parse(try_from_str = parse_duration))]
+    timeout: Duration,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2DeregisterImage {
+    image_id: String,
+    /// Also delete the image's EBS snapshots.
+    #[structopt(long)]
+    delete_snapshots: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2SecurityGroups {
+    /// List each group's inbound and outbound rules instead.
+    #[structopt(long)]
+    rules: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2SgRule {
+    /// Security group ID or name.
+    group: String,
+    /// Port or range of ports, such as 22 or 8000-8080.
+    #[structopt(long)]
+    port: String,
+    #[structopt(long, default_value = "tcp")]
+    protocol: String,
+    /// IPv4 or IPv6 CIDR block.
+    #[structopt(long)]
+    cidr: Option<String>,
+    /// Use the caller's public IP address as the CIDR block.
+    #[structopt(long)]
+    my_ip: bool,
+    /// Description of the rule.
+    #[structopt(long)]
+    description: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2KeyPairsCommand {
+    /// Create a key pair and write its private key to disk.
+    Create {
+        name: String,
+        /// Path of the private key file [default: <name>.pem].
+        #[structopt(long)]
+        output: Option<PathBuf>,
+    },
+    /// Import an existing public key.
+    Import {
+        name: String,
+        /// Public key file, such as ~/.ssh/id_ed25519.pub.
+        public_key: PathBuf,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2KeyPairs {
+    #[structopt(subcommand)]
+    command: Option<Ec2KeyPairsCommand>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Ec2Instances {
+    /// Only list instances with this tag (KEY=VALUE). Can be repeated
+    /// to require several tags.
+    #[structopt(
+        long = "tag",
+        number_of_values = 1,
+        parse(try_from_str = parse_key_value)
+    )]
+    tags: Vec<(String, String)>,
+    /// Only list instances in this state, such as running or stopped.
+    #[structopt(long)]
+    state: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ec2 {
+    /// List instances.
+    Instances(Ec2Instances),
+    /// List availability zones with their zone IDs.
+    Azs(Ec2Azs),
+    /// List AMIs owned by the account, oldest first.
+    Amis,
+    /// Create an AMI from an instance.
+    CreateImage(Ec2CreateImage),
+    /// Deregister an AMI, optionally deleting its snapshots.
+    DeregisterImage(Ec2DeregisterImage),
+    /// List security groups with their rule counts.
+    SecurityGroups(Ec2SecurityGroups),
+    /// Add or remove security group inbound rules.
+    Sg(Ec2Sg),
+    /// List, create, or import key pairs.
+    KeyPairs(Ec2KeyPairs),
+    /// Show an instance's IP address(es)
+    Addr(Ec2Addr),
+    /// SSH to an instance.
+    Ssh(Ec2Ssh),
+    /// List active reserved instances and Savings Plans, and compare
+    /// them with the running instances.
+    Reservations,
+    /// List over-provisioned instances with Compute Optimizer's
+    /// suggested types and savings.
+    Rightsize,
+    /// Show an instance's console output.
+    Console(Ec2Console),
+    /// Save a screenshot of an instance's console.
+    Screenshot(Ec2Screenshot),
+    /// List instance types with their vCPUs, memory, and network
+    /// performance.
+    InstanceTypes(Ec2InstanceTypes),
+    /// Launch a new instance.
+    Launch(Ec2Launch),
+    /// List spot requests and prices, or launch a spot instance.
+    Spot(Ec2Spot),
+    /// Start an instance.
+    Start { instances: Vec<String> },
+    /// Stop an instance.
+    Stop { instances: Vec<String> },
+    /// Terminate an instance.
+    Terminate { instances: Vec<String> },
+    /// Reboot an instance.
+    Reboot { instances: Vec<String> },
+    /// Wait for instances to reach a state.
+    Wait(Ec2Wait),
+    /// Show instance status checks and scheduled events, such as
+    /// retirements and maintenance reboots.
+    Status { instances: Vec<String> },
+    /// Add or overwrite tags on instances, volumes, AMIs, or
+    /// snapshots.
+    Tag {
+        /// Resource IDs followed by KEY=VALUE tags.
+        #[structopt(required = true)]
+        args: Vec<String>,
+    },
+    /// Remove tags from instances, volumes, AMIs, or snapshots.
+    Untag {
+        /// Resource IDs followed by tag keys.
+        #[structopt(required = true)]
+        args: Vec<String>,
+    },
+    /// Change an instance's type, stopping it first if needed.
+    Resize(Ec2Resize),
+    /// Run a shell command on instances via SSM.
+    RunCommand(Ec2RunCommand),
+    /// Check whether an instance can reach an address with VPC
+    /// Reachability Analyzer.
+    Reachability(Ec2Reachability),
+    /// Enable and query VPC flow logs.
+    FlowLogs(Ec2FlowLogs),
+    /// Show transit gateways.
+    Tgw(Ec2Tgw),
+    /// Show VPC peering connections.
+    Peering(Ec2Peering),
+    /// List or create VPC endpoints.
+    Endpoints(Ec2Endpoints),
+    /// List VPCs.
+    Vpcs,
+    /// List subnets, and whether they're public or private.
+    Subnets,
+    /// List network interfaces.
+    #[structopt(alias = "eni")]
+    Enis(Ec2Enis),
+    /// Show VPN status.
+    Vpn(Ec2Vpn),
+    /// List, modify, attach, or detach EBS volumes.
+    #[structopt(alias = "volume")]
+    Volumes(Ec2Volumes),
+    /// List, create, delete, or copy EBS snapshots.
+    #[structopt(alias = "snapshot")]
+    Snapshots(Ec2Snapshots),
+    /// Manage Data Lifecycle Manager snapshot policies.
+    Dlm(Ec2Dlm),
+}
+
+#[cfg(feature = "eb")]
+#[derive(Debug, StructOpt)]
+struct EbDeploy {
+    environment_name: String,
+    /// Application version to deploy.
+    #[structopt(long)]
+    version_label: String,
+}
+
+#[cfg(feature = "eb")]
+#[derive(Debug, StructOpt)]
+struct EbEvents {
+    environment_name: String,
+    /// Keep waiting for new events.
+    #[structopt(long)]
+    follow: bool,
+}
+
+#[cfg(feature = "eb")]
+#[derive(Debug, StructOpt)]
+enum Eb {
+    /// List environments with their health and deployed version.
+    Environments,
+    /// Deploy an application version to an environment.
+    Deploy(EbDeploy),
+    /// Show an environment's recent events.
+    Events(EbEvents),
+}
+
+#[derive(Debug, StructOpt)]
+struct ElbSlo {
+    /// Application Load Balancer name or ARN.
+    load_balancer: String,
+    /// Period to report on, such as 7d or 24h.
+    #[structopt(long, default_value = "7d", parse(try_from_str = parse_duration))]
+    since: Duration,
+    /// Availability target as a percentage of successful requests.
+    #[structopt(long, default_value = "99.9")]
+    target: f64,
+}
+
+#[derive(Debug, StructOpt)]
+enum Elb {
+    /// Report availability and error budget from ALB metrics.
+    Slo(ElbSlo),
+}
+
+#[cfg(feature = "glacier")]
+#[derive(Debug, StructOpt)]
+struct GlacierInventoryGet {
+    vault_name: String,
+    job_id: String,
+    /// Write the inventory to a file instead of stdout.
+    #[structopt(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[cfg(feature = "glacier")]
+#[derive(Debug, StructOpt)]
+enum GlacierInventory {
+    /// Start an inventory retrieval job.
+    Start { vault_name: String },
+    /// List a vault's jobs.
+    Jobs { vault_name: String },
+    /// Download the inventory from a completed job.
+    Get(GlacierInventoryGet),
+}
+
+#[cfg(feature = "glacier")]
+#[derive(Debug, StructOpt)]
+enum Glacier {
+    /// List vaults.
+    Vaults,
+    /// Retrieve vault inventories.
+    Inventory(GlacierInventory),
+}
+
+#[derive(Debug, StructOpt)]
+struct IamAttachment {
+    /// Identity in the form role/<name>, user/<name>, or group/<name>.
+    #[structopt(parse(try_from_str = parse_iam_entity))]
+    entity: IamEntity,
+    policy_arn: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct IamCredentialReport {
+    /// Flag passwords and access keys older than this many days.
+    #[structopt(long, default_value = "90")]
+    max_age: i64,
+}
+
+#[derive(Debug, StructOpt)]
+struct IamWhoAssumed {
+    /// Role name or ARN.
+    role: String,
+    /// Show events newer than this duration, such as "1h" or "7d".
+    #[structopt(long, default_value = "24h")]
+    since: String,
+    /// Region to search CloudTrail in (defaults to the current
+    /// region). Calls to the global STS endpoint are recorded in
+    /// us-east-1.
+    #[structopt(long)]
+    region: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct IamRoles {
+    /// Only list roles not used within this duration, such as "90d".
+    #[structopt(long)]
+    unused: Option<String>,
+    /// Delete the listed roles, after confirming each one.
+    #[structopt(long)]
+    delete: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Iam {
+    /// Attach a managed policy to a role, user, or group.
+    Attach(IamAttachment),
+    /// Detach a managed policy from a role, user, or group.
+    Detach(IamAttachment),
+    /// List the roles, users, and groups a policy is attached to.
+    EntitiesForPolicy { policy_arn: String },
+    /// Show the credential report, highlighting users without MFA,
+    /// old passwords, and stale access keys.
+    CredentialReport(IamCredentialReport),
+    /// List who assumed a role, according to CloudTrail.
+    WhoAssumed(IamWhoAssumed),
+    /// List roles and when they were last used.
+    Roles(IamRoles),
+}
+
+#[derive(Debug, StructOpt)]
+enum ImageBuilder {
+    /// List image pipelines with the status of their last build.
+    Pipelines,
+    /// Start a pipeline, identified by name or ARN.
+    Run { pipeline: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct InspectorFindings {
+    /// Only list findings for this resource: an instance ID, a
+    /// resource ARN, or an ECR image such as repo:tag.
+    #[structopt(long)]
+    resource: Option<String>,
+    /// Exit with an error if there are findings of this severity or
+    /// higher, such as critical or high.
+    #[structopt(long)]
+    fail_on: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Inspector {
+    /// List active vulnerability findings grouped by CVE.
+    Findings(InspectorFindings),
+}
+
+#[cfg(feature = "lightsail")]
+#[derive(Debug, StructOpt)]
+enum Lightsail {
+    /// List instances.
+    #[structopt(alias = "list")]
+    Instances,
+    /// Show an instance's IP addresses.
+    Addr { instance_names: Vec<String> },
+    /// Start an instance.
+    Start { instance_names: Vec<String> },
+    /// Stop an instance.
+    Stop { instance_names: Vec<String> },
+}
+
+#[derive(Debug, StructOpt)]
+struct ListLogGroups {
+    prefix: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct RecentLogStreams {
+    log_group_name: String,
+    #[structopt(long, default_value = "10")]
+    limit: usize,
+}
+
+#[derive(Debug, StructOpt)]
+struct TailLogs {
+    log_group_name: String,
+    /// Show events newer than this duration, such as "10m" or "1h".
+    #[structopt(long, default_value = "10m")]
+    since: String,
+    /// Only show events matching a CloudWatch Logs filter pattern.
+    #[structopt(long)]
+    filter: Option<String>,
+    /// Keep waiting for new events.
+    #[structopt(long)]
+    follow: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct LogsInsights {
+    #[structopt(required = true)]
+    log_group_names: Vec<String>,
+    /// Query to run.
+    #[structopt(long)]
+    query: Option<String>,
+    /// Run a query saved with --save.
+    #[structopt(long)]
+    saved: Option<String>,
+    /// Save the query in the config file under this name.
+    #[structopt(long)]
+    save: Option<String>,
+    /// Query events newer than this duration, such as "10m" or "1h".
+    #[structopt(long, default_value = "1h")]
+    since: String,
+    /// Maximum number of results.
+    #[structopt(long)]
+    limit: Option<i64>,
+}
+
+#[derive(Debug, StructOpt)]
+struct LogsStats {
+    log_group_name: String,
+    /// CloudWatch Logs filter pattern to count.
+    #[structopt(long, default_value = "ERROR")]
+    pattern: String,
+    /// Count events newer than this duration, such as "10m" or "1h".
+    #[structopt(long, default_value = "1h")]
+    since: String,
+    /// Size of the time buckets, such as "1m" or "5m".
+    #[structopt(long, default_value = "5m")]
+    bucket: String,
+}
+
+#[derive(Debug, StructOpt)]
+enum Logs {
+    /// List CloudWatch Logs groups.
+    Groups(ListLogGroups),
+    /// List recent CloudWatch Logs streams.
+    RecentStreams(RecentLogStreams),
+    /// Show recent events in a log group.
+    Tail(TailLogs),
+    /// Run a CloudWatch Logs Insights query.
+    Insights(LogsInsights),
+    /// Count matching events per stream and over time.
+    Stats(LogsStats),
+}
+
+#[derive(Debug, StructOpt)]
+enum Mq {
+    /// List brokers with their engine, state, and endpoints.
+    Brokers,
+    /// Reboot a broker.
+    Reboot {
+        /// Broker name or ID.
+        broker: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum Msk {
+    /// List clusters with their state and broker endpoints.
+    Clusters,
+    /// Show the bootstrap broker string of a cluster.
+    Bootstrap {
+        /// Cluster name or ARN.
+        cluster: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum OpenSearch {
+    /// List domains with their version, nodes, storage, and health.
+    Domains,
+    /// Show the endpoint URL of a domain.
+    Endpoint {
+        domain: String,
+        /// Also copy the URL to the clipboard.
+        #[structopt(long)]
+        copy: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum Regions {
+    /// List regions and whether they are enabled for the account.
+    List,
+    /// Measure the latency to each enabled region.
+    Ping,
+}
+
+#[derive(Debug, StructOpt)]
+struct S3Buckets {
+    /// Show each bucket's region and creation date.
+    #[structopt(long)]
+    long: bool,
+    /// Also show each bucket's size and object count, from CloudWatch
+    /// storage metrics (implies --long).
+    #[structopt(long)]
+    size: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct S3ReplicationAdd {
+    /// Source bucket.
+    src: String,
+    /// Destination bucket.
+    dst: String,
+    /// Only replicate objects with this key prefix.
+    #[structopt(long)]
+    prefix: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum S3Replication {
+    /// Show a bucket's replication rules.
+    Get { bucket: String },
+    /// Replicate a bucket to another bucket, creating the IAM role
+    /// that S3 needs.
+    Add(S3ReplicationAdd),
+}
+
+#[derive(Debug, StructOpt)]
+struct S3LockStatus {
+    /// Object in the form <bucket>/<key>, or just a bucket to show its
+    /// default retention.
+    path: String,
+    #[structopt(long)]
+    version_id: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct S3LockSet {
+    /// Object in the form <bucket>/<key>.
+    path: String,
+    /// Retention mode: COMPLIANCE or GOVERNANCE.
+    #[structopt(long)]
+    mode: String,
+    /// Date in YYYY-MM-DD format, or a duration from now such as
+    /// "365d".
+    #[structopt(long)]
+    retain_until: String,
+    /// Allow shortening or removing GOVERNANCE retention.
+    #[structopt(long)]
+    bypass_governance: bool,
+    #[structopt(long)]
+    version_id: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct S3LockLegalHold {
+    /// Object in the form <bucket>/<key>.
+    path: String,
+    /// Either "on" or "off".
+    status: String,
+    #[structopt(long)]
+    version_id: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum S3Lock {
+    /// Show an object's retention and legal hold.
+    Status(S3LockStatus),
+    /// Set an object's retention.
+    Set(S3LockSet),
+    /// Turn an object's legal hold on or off.
+    LegalHold(S3LockLegalHold),
+}
+
+#[derive(Debug, StructOpt)]
+struct S3Cp {
+    /// Local file to upload.
+    src: PathBuf,
+    /// Destination in the form <bucket>/<key>, or <bucket>/<prefix>/
+    /// to keep the file name.
+    dst: String,
+    /// Continue an interrupted upload of a large file.
+    #[structopt(long)]
+    resume: bool,
+    /// Limit the average upload rate, such as "10MB/s".
+    #[structopt(long, parse(try_from_str = parse_rate))]
+    limit_rate: Option<u64>,
+}
+
+#[derive(Debug, StructOpt)]
+enum S3 {
+    /// List buckets.
+    Buckets(S3Buckets),
+    /// Show or set up bucket replication.
+    Replication(S3Replication),
+    /// Show or set Object Lock retention and legal holds.
+    Lock(S3Lock),
+    /// Upload a file.
+    Cp(S3Cp),
+}
+
+#[cfg(feature = "cost")]
+#[derive(Debug, StructOpt)]
+struct CostByTag {
+    /// Cost allocation tag key to group by.
+    tag: String,
+    /// Start of the report: "month-to-date", a duration such as
+    /// "30d", or a date in YYYY-MM-DD format.
+    #[structopt(long, default_value = "month-to-date")]
+    since: String,
+}
+
+#[cfg(feature = "apigw")]
+#[derive(Debug, StructOpt)]
+struct ApigwStage {
+    /// API ID or name.
+    api: String,
+    stage: String,
+    /// Also copy the URL to the clipboard.
+    #[structopt(long)]
+    copy: bool,
+}
+
+#[cfg(feature = "apigw")]
+#[derive(Debug, StructOpt)]
+struct ApigwLogs {
+    /// API ID or name.
+    api: String,
+    stage: String,
+    /// Show events newer than this duration, such as "10m" or "1h".
+    #[structopt(long, default_value = "10m")]
+    since: String,
+    /// Keep waiting for new events.
+    #[structopt(long)]
+    follow: bool,
+    /// Show the access log instead of the execution log (REST APIs
+    /// only; HTTP APIs only have access logs).
+    #[structopt(long)]
+    access: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct AiTranslate {
+    text: String,
+    /// Language to translate to, such as "fr".
+    #[structopt(long)]
+    to: String,
+    /// Language to translate from (detected by default).
+    #[structopt(long, default_value = "auto")]
+    from: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct AiSpeak {
+    text: String,
+    /// MP3 file to write.
+    #[structopt(long)]
+    out: PathBuf,
+    /// Polly voice to use.
+    #[structopt(long, default_value = "Joanna")]
+    voice: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct AiTranscribe {
+    /// Local audio file, or an S3 URI such as s3://bucket/file.wav.
+    file: String,
+    /// Bucket to upload local files to and write the transcript to
+    /// (defaults to the bucket of an S3 URI).
+    #[structopt(long)]
+    bucket: Option<String>,
+    /// Language of the audio, such as "en-US" (detected by default).
+    #[structopt(long)]
+    language: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct AcmRequest {
+    domain: String,
+    /// Additional domain name for the certificate. Can be repeated.
+    #[structopt(long = "san", number_of_values = 1)]
+    sans: Vec<String>,
+    /// Create the validation records in Route 53 and wait until the
+    /// certificate is issued.
+    #[structopt(long)]
+    validate_route53: bool,
+    /// Also copy the certificate ARN to the clipboard.
+    #[structopt(long)]
+    copy: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Acm {
+    /// Request a certificate validated with DNS.
+    Request(AcmRequest),
+}
+
+#[derive(Debug, StructOpt)]
+struct Advisor {
+    /// Only show checks in this category: cost, security,
+    /// fault-tolerance, performance, or service-limits.
+    #[structopt(long)]
+    category: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Ai {
+    /// Translate text with Amazon Translate.
+    Translate(AiTranslate),
+    /// Convert text to speech with Amazon Polly.
+    Speak(AiSpeak),
+    /// Transcribe an audio file with Amazon Transcribe.
+    Transcribe(AiTranscribe),
+}
+
+#[derive(Debug, StructOpt)]
+enum Amplify {
+    /// List apps.
+    Apps,
+    /// List builds of a branch with their status and commit.
+    Builds {
+        /// App name or ID.
+        app: String,
+        branch: String,
+    },
+    /// Start a build of a branch.
+    StartBuild {
+        /// App name or ID.
+        app: String,
+        branch: String,
+    },
+}
+
+#[cfg(feature = "apigw")]
+#[derive(Debug, StructOpt)]
+enum Apigw {
+    /// List REST, HTTP, and WebSocket APIs.
+    Apis,
+    /// Show an API's stages with their variables and logging settings.
+    Stages {
+        /// API ID or name.
+        api: String,
+    },
+    /// Show the URL for invoking an API stage.
+    InvokeUrl(ApigwStage),
+    /// Show a stage's execution or access log.
+    Logs(ApigwLogs),
+}
+
+#[derive(Debug, StructOpt)]
+struct AppConfigGet {
+    /// Application name or ID.
+    application: String,
+    /// Environment name or ID.
+    environment: String,
+    /// Configuration profile name or ID.
+    profile: String,
+}
+
+#[derive(Debug, StructOpt)]
+enum AppConfig {
+    /// Print the latest deployed configuration data.
+    Get(AppConfigGet),
+}
+
+#[derive(Debug, StructOpt)]
+struct BackupJobs {
+    /// Only show jobs in this state, such as FAILED or COMPLETED.
+    #[structopt(long)]
+    status: Option<String>,
+    /// Show jobs created within this duration, such as 24h or 7d.
+    #[structopt(long, default_value = "24h", parse(try_from_str = parse_duration))]
+    since: Duration,
+}
+
+#[derive(Debug, StructOpt)]
+enum Backup {
+    /// List backup plans with their rules and selections.
+    Plans,
+    /// List recent backup jobs.
+    Jobs(BackupJobs),
+    /// List recovery points of a resource.
+    RecoveryPoints { resource_arn: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct BedrockInvoke {
+    model_id: String,
+    /// Prompt to send to the model.
+    #[structopt(long)]
+    prompt: String,
+    /// Maximum number of tokens to generate.
+    #[structopt(long, default_value = "512")]
+    max_tokens: u32,
+    /// Print the response as it's generated.
+    #[structopt(long)]
+    stream: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Bedrock {
+    /// List foundation models.
+    Models {
+        /// Only list models from this provider, such as "anthropic".
+        #[structopt(long)]
+        provider: Option<String>,
+    },
+    /// Send a prompt to a model and print the response.
+    Invoke(BedrockInvoke),
+}
+
+#[derive(Debug, StructOpt)]
+struct CodeArtifactLogin {
+    domain: String,
+    repository: String,
+    /// Tool to configure: pip, npm, or cargo.
+    #[structopt(long)]
+    tool: String,
+    /// Account that owns the domain (defaults to the current account).
+    #[structopt(long)]
+    domain_owner: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum CodeArtifact {
+    /// Get an auth token and configure a tool to use a repository.
+    Login(CodeArtifactLogin),
+}
+
+#[derive(Debug, StructOpt)]
+struct CodeBuildStart {
+    project: String,
+    /// Wait for the build to finish.
+    #[structopt(long)]
+    wait: bool,
+    /// Stream the build logs until the build finishes.
+    #[structopt(long)]
+    logs: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct CodeBuildBuilds {
+    project: String,
+    /// Maximum number of builds to show.
+    #[structopt(long, default_value = "20")]
+    limit: usize,
+}
+
+#[derive(Debug, StructOpt)]
+enum CodeBuild {
+    /// List projects with the status of their last build.
+    Projects,
+    /// Start a build of a project.
+    Start(CodeBuildStart),
+    /// List recent builds of a project.
+    Builds(CodeBuildBuilds),
+}
+
+#[derive(Debug, StructOpt)]
+struct CodeDeployDeployments {
+    /// Only list deployments of this application.
+    #[structopt(long)]
+    app: Option<String>,
+    /// Maximum number of deployments to show.
+    #[structopt(long, default_value = "20")]
+    limit: usize,
+}
+
+#[derive(Debug, StructOpt)]
+enum CodeDeploy {
+    /// List recent deployments.
+    Deployments(CodeDeployDeployments),
+    /// Show the lifecycle events of each instance in a deployment.
+    Show { deployment_id: String },
+    /// Roll back a deployment to the previous revision.
+    Rollback { deployment_id: String },
+    /// Stop a deployment without rolling back.
+    Stop { deployment_id: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct CodePipelineApprove {
+    pipeline: String,
+    stage: String,
+    action: String,
+    /// Comment to record with the result. Defaults to "Approved with
+    /// claws", or "Rejected with claws" with --reject.
+    #[structopt(long)]
+    summary: Option<String>,
+    /// Reject instead of approving.
+    #[structopt(long)]
+    reject: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum CodePipeline {
+    /// List pipelines with the status of their last execution.
+    List,
+    /// Show the state of each stage and the revision it's running.
+    Show { pipeline: String },
+    /// Approve (or reject) a pending manual approval action.
+    Approve(CodePipelineApprove),
+}
+
+#[cfg(feature = "cost")]
+#[derive(Debug, StructOpt)]
+enum Cost {
+    /// Show each budget's limit, actual spend, and forecasted spend.
+    Budgets,
+    /// Show costs grouped by a cost allocation tag.
+    ByTag(CostByTag),
+}
+
+#[cfg(feature = "price")]
+#[derive(Debug, StructOpt)]
+struct PriceEc2 {
+    instance_type: String,
+    /// Region to get prices for (defaults to the current region).
+    #[structopt(long)]
+    region: Option<String>,
+    /// Operating system: linux, windows, rhel, or suse.
+    #[structopt(long, default_value = "linux")]
+    os: String,
+    /// Also show current spot prices.
+    #[structopt(long)]
+    spot: bool,
+}
+
+#[cfg(feature = "price")]
+#[derive(Debug, StructOpt)]
+struct PriceEbs {
+    /// Volume type, such as gp3 or io2.
+    volume_type: String,
+    /// Region to get prices for (defaults to the current region).
+    #[structopt(long)]
+    region: Option<String>,
+}
+
+#[cfg(feature = "price")]
+#[derive(Debug, StructOpt)]
+enum Price {
+    /// Show the price of an EC2 instance type.
+    Ec2(PriceEc2),
+    /// Show the price of an EBS volume type.
+    Ebs(PriceEbs),
+}
+
+#[derive(Debug, StructOpt)]
+struct RekognitionLabels {
+    /// Local image file, or an S3 URI such as s3://bucket/image.jpg.
+    image: String,
+    /// Only show labels with at least this confidence percentage.
+    #[structopt(long, default_value = "50")]
+    min_confidence: f64,
+}
+
+#[derive(Debug, StructOpt)]
+struct Route53Import {
+    /// Hosted zone name or ID.
+    zone: String,
+    /// Zone file in BIND format.
+    file: PathBuf,
+    /// Only show the records that would be created or updated.
+    #[structopt(long)]
+    dry_run: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Route53 {
+    /// List health checks with their status and failure reasons.
+    HealthChecks,
+    /// Print a hosted zone's records in BIND zone file format.
+    Export {
+        /// Hosted zone name or ID.
+        zone: String,
+    },
+    /// Create or update records from a BIND zone file.
+    Import(Route53Import),
+    /// Compare a record's answers from the zone's name servers and
+    /// public resolvers with Route 53, to check propagation.
+    Check {
+        name: String,
+        /// Record type, such as A or CNAME.
+        #[structopt(name = "type")]
+        type_: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum Rekognition {
+    /// Detect labels in an image.
+    Labels(RekognitionLabels),
+    /// Detect faces in an image.
+    Faces {
+        /// Local image file, or an S3 URI such as s3://bucket/image.jpg.
+        image: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct TextractAnalyze {
+    /// Local document, or an S3 URI such as s3://bucket/doc.pdf.
+    document: String,
+    /// Extract tables.
+    #[structopt(long)]
+    tables: bool,
+    /// Extract form key-value pairs.
+    #[structopt(long)]
+    forms: bool,
+    /// Bucket to upload local files over 5 MB to for asynchronous
+    /// analysis.
+    #[structopt(long)]
+    bucket: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Textract {
+    /// Extract text, and optionally tables and forms, from a document.
+    Analyze(TextractAnalyze),
+}
+
+#[derive(Debug, StructOpt)]
+enum SageMakerNotebooksCommand {
+    /// Start a notebook instance.
+    Start { name: String },
+    /// Stop a notebook instance.
+    Stop { name: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct SageMakerNotebooks {
+    #[structopt(subcommand)]
+    command: Option<SageMakerNotebooksCommand>,
+}
+
+#[derive(Debug, StructOpt)]
+struct SageMakerInvoke {
+    endpoint: String,
+    /// JSON file to send as the request body.
+    #[structopt(long)]
+    body: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+enum SageMaker {
+    /// List notebook instances, or start or stop one.
+    Notebooks(SageMakerNotebooks),
+    /// List endpoints with their instance type and status.
+    Endpoints,
+    /// Invoke an endpoint and print the response.
+    Invoke(SageMakerInvoke),
+}
+
+#[derive(Debug, StructOpt)]
+struct SchedulerList {
+    /// Only list schedules in this group.
+    #[structopt(long)]
+    group: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct SchedulerCreate {
+    /// Name of the schedule.
+    name: String,
+    /// Schedule expression, such as "at(2024-01-01T09:00:00)",
+    /// "rate(1 hour)", or "cron(0 9 ? * MON-FRI *)".
+    expression: String,
+    /// ARN of the target to invoke.
+    #[structopt(long)]
+    target_arn: String,
+    /// ARN of the role assumed to invoke the target.
+    #[structopt(long)]
+    role_arn: String,
+    /// JSON input to pass to the target.
+    #[structopt(long)]
+    input: Option<String>,
+    /// Schedule group (defaults to "default").
+    #[structopt(long)]
+    group: Option<String>,
+    /// Timezone of the expression, such as America/New_York.
+    #[structopt(long)]
+    timezone: Option<String>,
+    /// Delete the schedule after it fires for the last time.
+    #[structopt(long)]
+    delete_after_completion: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Scheduler {
+    /// List schedules with their next fire time.
+    List(SchedulerList),
+    /// Create a one-time or recurring schedule.
+    Create(SchedulerCreate),
+    /// Delete a schedule.
+    Delete {
+        name: String,
+        /// Schedule group (defaults to "default").
+        #[structopt(long)]
+        group: Option<String>,
+    },
+}
+
+#[cfg(feature = "ses")]
+#[derive(Debug, StructOpt)]
+enum SesSuppression {
+    /// List addresses on the account-level suppression list.
+    List,
+    /// Remove an address from the suppression list.
+    Remove { email: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct SecurityHubFindings {
+    /// Only list findings with this severity, such as CRITICAL or
+    /// HIGH. Can be repeated.
+    #[structopt(long = "severity", number_of_values = 1)]
+    severities: Vec<String>,
+    /// Only list findings with this workflow status, such as NEW or
+    /// NOTIFIED. Can be repeated.
+    #[structopt(long = "status", number_of_values = 1)]
+    statuses: Vec<String>,
+    /// Suppress the finding with this ID instead of listing.
+    #[structopt(long, conflicts_with = "resolve")]
+    suppress: Option<String>,
+    /// Resolve the finding with this ID instead of listing.
+    #[structopt(long)]
+    resolve: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+enum SecurityHub {
+    /// List active findings, or update a finding's workflow status.
+    Findings(SecurityHubFindings),
+}
+
+#[cfg(feature = "ses")]
+#[derive(Debug, StructOpt)]
+enum Ses {
+    /// Show daily sends, bounces, complaints, and rejects.
+    Stats,
+    /// Manage the suppression list.
+    Suppression(SesSuppression),
+}
+
+#[derive(Debug, StructOpt)]
+struct SsmDocuments {
+    /// Only show documents with this owner, such as "self" or
+    /// "amazon".
+    #[structopt(long)]
+    owner: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct SsmInventory {
+    /// Instance IDs or names to list (defaults to all managed
+    /// instances).
+    instances: Vec<String>,
+    /// Only show entries where FIELD starts with VALUE, such as
+    /// "name=openssl". Can be repeated.
+    #[structopt(
+        long = "filter",
+        number_of_values = 1,
+        parse(try_from_str = parse_key_value)
+    )]
+    filters: Vec<(String, String)>,
+    /// Inventory type to list.
+    #[structopt(long = "type", default_value = "AWS:Application")]
+    type_name: String,
+}
+
+#[derive(Debug, StructOpt)]
+enum SsmOpsItemsCommand {
+    /// Mark an OpsItem as resolved.
+    Resolve { ops_item_id: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct SsmOpsItems {
+    /// Only list OpsItems with this status, such as Open, InProgress,
+    /// or Resolved.
+    #[structopt(long)]
+    status: Option<String>,
+    #[structopt(subcommand)]
+    command: Option<SsmOpsItemsCommand>,
+}
+
+#[derive(Debug, StructOpt)]
+struct SsmRunDocument {
+    /// Document name.
+    name: String,
+    /// Instance ID or name to run the document on. Can be repeated.
+    #[structopt(long = "instance", required = true, number_of_values = 1)]
+    instances: Vec<String>,
+    /// Document parameter (KEY=VALUE). Can be repeated, including with
+    /// the same key for list parameters.
+    #[structopt(
+        long = "param",
+        number_of_values = 1,
+        parse(try_from_str = parse_key_value)
+    )]
+    params: Vec<(String, String)>,
+}
+
+#[derive(Debug, StructOpt)]
+struct SsoServe {
+    /// Profile to get credentials from. SSO profiles use the token
+    /// cached by "aws sso login".
+    #[structopt(long)]
+    profile: Option<String>,
+    /// Serve credentials for this role, assumed with the default
+    /// credentials.
+    #[structopt(long)]
+    role_arn: Option<String>,
+    /// Address to listen on.
+    #[structopt(long, default_value = "127.0.0.1:9911")]
+    listen: String,
+}
+
+#[derive(Debug, StructOpt)]
+enum Sso {
+    /// Serve refreshed credentials over an endpoint compatible with
+    /// the EC2 instance metadata service.
+    Serve(SsoServe),
+}
+
+#[derive(Debug, StructOpt)]
+enum Ssm {
+    /// Show the SSM agent and patch compliance status of instances.
+    Compliance,
+    /// List SSM documents.
+    Documents(SsmDocuments),
+    /// List software installed on instances.
+    Inventory(SsmInventory),
+    /// List OpsCenter operational items.
+    #[structopt(name = "opsitems")]
+    OpsItems(SsmOpsItems),
+    /// Run a command document on instances.
+    RunDocument(SsmRunDocument),
+}
+
+#[derive(Debug, StructOpt)]
+struct WafSampledRequests {
+    /// Web ACL name or ID.
+    acl: String,
+    /// Rule to sample requests of. By default, requests handled by
+    /// the web ACL's default action are sampled.
+    #[structopt(long)]
+    rule: Option<String>,
+    /// Sample requests within this duration, up to 3h.
+    #[structopt(long, default_value = "1h", parse(try_from_str = parse_duration))]
+    since: Duration,
+    /// Use CloudFront web ACLs instead of regional ones.
+    #[structopt(long)]
+    cloudfront: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Waf {
+    /// List web ACLs.
+    Acls {
+        /// List CloudFront web ACLs instead of regional ones.
+        #[structopt(long)]
+        cloudfront: bool,
+    },
+    /// List the rules of a web ACL in priority order.
+    Rules {
+        /// Web ACL name or ID.
+        acl: String,
+        /// Use CloudFront web ACLs instead of regional ones.
+        #[structopt(long)]
+        cloudfront: bool,
+    },
+    /// Show a sample of the requests matched by a rule.
+    SampledRequests(WafSampledRequests),
+}
+
+#[cfg(feature = "workspaces")]
+#[derive(Debug, StructOpt)]
+enum Workspaces {
+    /// List workspaces with their state, user, and bundle.
+    List,
+    /// Reboot a workspace.
+    Reboot { workspace_ids: Vec<String> },
+    /// Rebuild a workspace.
+    Rebuild { workspace_ids: Vec<String> },
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    setting = AppSettings::TrailingVarArg,
+    setting = AppSettings::AllowLeadingHyphen
+)]
+struct RunPreset {
+    /// Name of a [preset.<name>] section in ~/.config/claws/config.
+    preset: String,
+    /// Extra arguments to append to the preset's command.
+    #[structopt(allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(about = "AWS command-line tool")]
+struct Opt {
+    /// Don't pipe long output through $PAGER.
+    #[structopt(long)]
+    no_pager: bool,
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    Acm(Acm),
+    Advisor(Advisor),
+    Ai(Ai),
+    Amplify(Amplify),
+    #[cfg(feature = "apigw")]
+    Apigw(Apigw),
+    #[structopt(name = "appconfig")]
+    AppConfig(AppConfig),
+    Backup(Backup),
+    Bedrock(Bedrock),
+    #[structopt(name = "codeartifact")]
+    CodeArtifact(CodeArtifact),
+    #[structopt(name = "codebuild")]
+    CodeBuild(CodeBuild),
+    #[structopt(name = "codedeploy")]
+    CodeDeploy(CodeDeploy),
+    #[structopt(name = "codepipeline")]
+    CodePipeline(CodePipeline),
+    Config(Config),
+    #[cfg(feature = "cost")]
+    Cost(Cost),
+    Cw(Cw),
+    #[cfg(feature = "eb")]
+    Eb(Eb),
+    Ec2(Ec2),
+    Elb(Elb),
+    #[cfg(feature = "glacier")]
+    Glacier(Glacier),
+    Iam(Iam),
+    #[structopt(name = "imagebuilder")]
+    ImageBuilder(ImageBuilder),
+    Inspector(Inspector),
+    #[cfg(feature = "lightsail")]
+    Lightsail(Lightsail),
+    Logs(Logs),
+    Mq(Mq),
+    Msk(Msk),
+    #[structopt(name = "opensearch")]
+    OpenSearch(OpenSearch),
+    #[cfg(feature = "price")]
+    Price(Price),
+    Regions(Regions),
+    Rekognition(Rekognition),
+    Route53(Route53),
+    /// Run a preset from the config file.
+    Run(RunPreset),
+    S3(S3),
+    #[structopt(name = "sagemaker")]
+    SageMaker(SageMaker),
+    Scheduler(Scheduler),
+    #[structopt(name = "securityhub")]
+    SecurityHub(SecurityHub),
+    #[cfg(feature = "ses")]
+    Ses(Ses),
+    Sso(Sso),
+    Ssm(Ssm),
+    Textract(Textract),
+    Waf(Waf),
+    #[cfg(feature = "workspaces")]
+    Workspaces(Workspaces),
+}
+
+/// Split a command line into arguments. Arguments can be quoted with
+/// single or double quotes.
+#[throws]
+fn split_command_line(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.get_or_insert_with(String::new).push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => args.extend(current.take()),
+            None => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        throw!(anyhow!("unterminated quote in command: {}", line));
+    }
+    args.extend(current);
+    args
+}
+
+#[throws]
+fn run_preset(args: RunPreset) {
+    let section = format!("preset.{}", args.preset);
+    let preset = read_claws_config_section(&section)?;
+    let command = preset
+        .get("command")
+        .with_context(|| format!("preset not found: {}", args.preset))?;
+    let mut preset_args = split_command_line(command)?;
+    preset_args.extend(args.args);
+    let command = Command::from_iter(
+        std::iter::once("claws".to_string()).chain(preset_args),
+    );
+    if let Command::Run(_) = command {
+        throw!(anyhow!("presets can't run other presets"));
+    }
+    run(command)?;
 }
 
 #[throws]
@@ -332,26 +14125,909 @@ fn for_each<F: Fn(String) -> Result<()>>(
     }
 }
 
-fn main() -> Result<(), Error> {
-    match Command::from_args() {
-        Command::Ec2(Ec2::Instances) => ec2_list_instances(),
-        Command::Ec2(Ec2::Addr { instance_ids }) => {
-            for_each(ec2_show_addresses, instance_ids)
+/// Like `for_each`, but `instances` can be instance IDs or names,
+/// which are resolved first.
+#[throws]
+fn for_each_instance<F: Fn(String) -> Result<()>>(
+    func: F,
+    instances: Vec<String>,
+) {
+    for_each(func, ec2_resolve_instance_ids(instances)?)?;
+}
+
+fn run(command: Command) -> Result<(), Error> {
+    match command {
+        Command::Acm(Acm::Request(args)) => acm_request_certificate(args),
+        Command::Advisor(args) => advisor_checks(args),
+        Command::Ai(Ai::Translate(args)) => ai_translate(args),
+        Command::Ai(Ai::Speak(args)) => ai_speak(args),
+        Command::Ai(Ai::Transcribe(args)) => ai_transcribe(args),
+        Command::Amplify(Amplify::Apps) => amplify_apps(),
+        Command::Amplify(Amplify::Builds { app, branch }) => {
+            amplify_builds(app, branch)
+        }
+        Command::Amplify(Amplify::StartBuild { app, branch }) => {
+            amplify_start_build(app, branch)
+        }
+        #[cfg(feature = "apigw")]
+        Command::Apigw(Apigw::Apis) => apigw_list_apis(),
+        #[cfg(feature = "apigw")]
+        Command::Apigw(Apigw::Stages { api }) => apigw_list_stages(api),
+        #[cfg(feature = "apigw")]
+        Command::Apigw(Apigw::InvokeUrl(args)) => apigw_invoke_url(args),
+        #[cfg(feature = "apigw")]
+        Command::Apigw(Apigw::Logs(args)) => apigw_logs(args),
+        Command::AppConfig(AppConfig::Get(args)) => appconfig_get(args),
+        Command::Backup(Backup::Plans) => backup_plans(),
+        Command::Backup(Backup::Jobs(args)) => backup_jobs(args),
+        Command::Backup(Backup::RecoveryPoints { resource_arn }) => {
+            backup_recovery_points(resource_arn)
+        }
+        Command::Bedrock(Bedrock::Models { provider }) => {
+            bedrock_models(provider)
         }
-        Command::Ec2(Ec2::Start { instance_ids }) => {
-            for_each(ec2_start_instance, instance_ids)
+        Command::Bedrock(Bedrock::Invoke(args)) => bedrock_invoke(args),
+        Command::CodeArtifact(CodeArtifact::Login(args)) => {
+            codeartifact_login(args)
         }
-        Command::Ec2(Ec2::Stop { instance_ids }) => {
-            for_each(ec2_stop_instance, instance_ids)
+        Command::CodeBuild(CodeBuild::Projects) => codebuild_projects(),
+        Command::CodeBuild(CodeBuild::Start(args)) => codebuild_start(args),
+        Command::CodeBuild(CodeBuild::Builds(args)) => codebuild_builds(args),
+        Command::CodeDeploy(CodeDeploy::Deployments(args)) => {
+            codedeploy_deployments(args)
         }
-        Command::Ec2(Ec2::Terminate { instance_ids }) => {
-            for_each(ec2_terminate_instance, instance_ids)
+        Command::CodeDeploy(CodeDeploy::Show { deployment_id }) => {
+            codedeploy_show(deployment_id)
         }
-        Command::Ec2(Ec2::Reboot { instance_ids }) => {
-            for_each(ec2_reboot_instance, instance_ids)
+        Command::CodeDeploy(CodeDeploy::Rollback { deployment_id }) => {
+            codedeploy_rollback(deployment_id)
+        }
+        Command::CodeDeploy(CodeDeploy::Stop { deployment_id }) => {
+            codedeploy_stop(deployment_id, false)
+        }
+        Command::CodePipeline(CodePipeline::List) => codepipeline_list(),
+        Command::CodePipeline(CodePipeline::Show { pipeline }) => {
+            codepipeline_show(pipeline)
+        }
+        Command::CodePipeline(CodePipeline::Approve(args)) => {
+            codepipeline_approve(args)
+        }
+        Command::Config(Config::Rules(args)) => config_rules(args),
+        Command::Config(Config::Resources { rule }) => config_resources(rule),
+        #[cfg(feature = "cost")]
+        Command::Cost(Cost::Budgets) => cost_list_budgets(),
+        #[cfg(feature = "cost")]
+        Command::Cost(Cost::ByTag(args)) => cost_by_tag(args),
+        Command::Cw(Cw::ContributorInsights(CwContributorInsights::List)) => {
+            cw_list_insight_rules()
+        }
+        Command::Cw(Cw::ContributorInsights(
+            CwContributorInsights::Enable { rule_names },
+        )) => cw_set_insight_rules("EnableInsightRules", rule_names),
+        Command::Cw(Cw::ContributorInsights(
+            CwContributorInsights::Disable { rule_names },
+        )) => cw_set_insight_rules("DisableInsightRules", rule_names),
+        Command::Cw(Cw::AnomalyDetectors(CwAnomalyDetectors::List)) => {
+            cw_list_anomaly_detectors()
+        }
+        Command::Cw(Cw::AnomalyDetectors(CwAnomalyDetectors::Enable(args))) => {
+            cw_enable_anomaly_detector(args)
+        }
+        Command::Cw(Cw::AnomalyDetectors(CwAnomalyDetectors::Disable(
+            args,
+        ))) => cw_disable_anomaly_detector(args),
+        Command::Cw(Cw::Canaries) => cw_canaries(),
+        Command::Cw(Cw::Canary(CwCanary::Runs { name })) => {
+            cw_canary_runs(name)
+        }
+        #[cfg(feature = "eb")]
+        Command::Eb(Eb::Environments) => eb_list_environments(),
+        #[cfg(feature = "eb")]
+        Command::Eb(Eb::Deploy(args)) => eb_deploy(args),
+        #[cfg(feature = "eb")]
+        Command::Eb(Eb::Events(args)) => eb_events(args),
+        Command::Ec2(Ec2::Instances(args)) => ec2_list_instances(args),
+        Command::Ec2(Ec2::Azs(args)) => ec2_list_azs(args),
+        Command::Ec2(Ec2::Amis) => ec2_list_amis(),
+        Command::Ec2(Ec2::CreateImage(args)) => ec2_create_image(args),
+        Command::Ec2(Ec2::DeregisterImage(args)) => ec2_deregister_image(args),
+        Command::Ec2(Ec2::SecurityGroups(args)) => ec2_security_groups(args),
+        Command::Ec2(Ec2::Sg(Ec2Sg::Allow(args))) => ec2_sg_allow(args),
+        Command::Ec2(Ec2::Sg(Ec2Sg::Revoke(args))) => ec2_sg_revoke(args),
+        Command::Ec2(Ec2::KeyPairs(args)) => match args.command {
+            Some(Ec2KeyPairsCommand::Create { name, output }) => {
+                ec2_create_key_pair(name, output)
+            }
+            Some(Ec2KeyPairsCommand::Import { name, public_key }) => {
+                ec2_import_key_pair(name, public_key)
+            }
+            None => ec2_list_key_pairs(),
+        },
+        Command::Ec2(Ec2::Addr(args)) => ec2_addr(args),
+        Command::Ec2(Ec2::Ssh(args)) => ec2_ssh(args),
+        Command::Ec2(Ec2::Rightsize) => ec2_rightsize(),
+        Command::Ec2(Ec2::Reservations) => ec2_reservations(),
+        Command::Ec2(Ec2::Console(args)) => ec2_console(args),
+        Command::Ec2(Ec2::Screenshot(args)) => ec2_screenshot(args),
+        Command::Ec2(Ec2::Launch(args)) => ec2_launch(args, None),
+        Command::Ec2(Ec2::Spot(Ec2Spot::Requests)) => ec2_spot_requests(),
+        Command::Ec2(Ec2::Spot(Ec2Spot::Prices { instance_type, os })) => {
+            ec2_spot_prices(instance_type, os)
+        }
+        Command::Ec2(Ec2::Spot(Ec2Spot::Launch(args))) => ec2_spot_launch(args),
+        Command::Ec2(Ec2::Resize(args)) => ec2_resize(args),
+        Command::Ec2(Ec2::Start { instances }) => {
+            for_each_instance(ec2_start_instance, instances)
+        }
+        Command::Ec2(Ec2::Stop { instances }) => {
+            for_each_instance(ec2_stop_instance, instances)
+        }
+        Command::Ec2(Ec2::Terminate { instances }) => {
+            for_each_instance(ec2_terminate_instance, instances)
+        }
+        Command::Ec2(Ec2::Reboot { instances }) => {
+            for_each_instance(ec2_reboot_instance, instances)
+        }
+        Command::Ec2(Ec2::Wait(args)) => ec2_wait(args),
+        Command::Ec2(Ec2::Status { instances }) => ec2_status(instances),
+        Command::Ec2(Ec2::InstanceTypes(args)) => ec2_instance_types(args),
+        Command::Ec2(Ec2::Tag { args }) => ec2_tag(args),
+        Command::Ec2(Ec2::Untag { args }) => ec2_untag(args),
+        Command::Ec2(Ec2::RunCommand(args)) => ec2_run_command(args),
+        Command::Ec2(Ec2::Reachability(args)) => ec2_reachability(args),
+        Command::Ec2(Ec2::FlowLogs(Ec2FlowLogs::Enable(args))) => {
+            ec2_flow_logs_enable(args)
+        }
+        Command::Ec2(Ec2::FlowLogs(Ec2FlowLogs::Query(args))) => {
+            ec2_flow_logs_query(args)
+        }
+        Command::Ec2(Ec2::Tgw(Ec2Tgw::List)) => ec2_list_transit_gateways(),
+        Command::Ec2(Ec2::Tgw(Ec2Tgw::Attachments { transit_gateway_id })) => {
+            ec2_list_transit_gateway_attachments(transit_gateway_id)
+        }
+        Command::Ec2(Ec2::Peering(Ec2Peering::List)) => {
+            ec2_list_peering_connections()
+        }
+        Command::Ec2(Ec2::Endpoints(args)) => match args.command {
+            Some(Ec2EndpointsCommand::Create(args)) => {
+                ec2_create_vpc_endpoint(args)
+            }
+            None => ec2_list_vpc_endpoints(args.vpc),
+        },
+        Command::Ec2(Ec2::Vpcs) => ec2_list_vpcs(),
+        Command::Ec2(Ec2::Subnets) => ec2_list_subnets(),
+        Command::Ec2(Ec2::Enis(args)) => ec2_list_enis(args),
+        Command::Ec2(Ec2::Vpn(Ec2Vpn::List)) => ec2_list_vpn_connections(),
+        Command::Ec2(Ec2::Vpn(Ec2Vpn::Telemetry { vpn_connection_id })) => {
+            ec2_vpn_telemetry(vpn_connection_id)
+        }
+        Command::Ec2(Ec2::Volumes(args)) => match args.command {
+            Some(Ec2VolumesCommand::Modify(args)) => ec2_modify_volume(args),
+            Some(Ec2VolumesCommand::Attach(args)) => ec2_attach_volume(args),
+            Some(Ec2VolumesCommand::Detach { volume_id, force }) => {
+                ec2_detach_volume(volume_id, force)
+            }
+            None => ec2_volumes(args.sort_by_size),
+        },
+        Command::Ec2(Ec2::Snapshots(args)) => match args.command {
+            Some(Ec2SnapshotsCommand::Create {
+                volume_id,
+                description,
+            }) => ec2_create_snapshot(volume_id, description),
+            Some(Ec2SnapshotsCommand::Delete { snapshot_id }) => {
+                ec2_delete_snapshot(snapshot_id)
+            }
+            Some(Ec2SnapshotsCommand::Copy(args)) => ec2_copy_snapshot(args),
+            Some(Ec2SnapshotsCommand::EnableFsr(args)) => {
+                ec2_enable_fast_snapshot_restore(args)
+            }
+            None => ec2_list_snapshots(),
+        },
+        Command::Ec2(Ec2::Dlm(Ec2Dlm::List)) => ec2_dlm_list(),
+        Command::Ec2(Ec2::Dlm(Ec2Dlm::Create(args))) => ec2_dlm_create(args),
+        Command::Elb(Elb::Slo(args)) => elb_slo(args),
+        #[cfg(feature = "glacier")]
+        Command::Glacier(Glacier::Vaults) => glacier_list_vaults(),
+        #[cfg(feature = "glacier")]
+        Command::Glacier(Glacier::Inventory(GlacierInventory::Start {
+            vault_name,
+        })) => glacier_start_inventory(vault_name),
+        #[cfg(feature = "glacier")]
+        Command::Glacier(Glacier::Inventory(GlacierInventory::Jobs {
+            vault_name,
+        })) => glacier_list_jobs(vault_name),
+        #[cfg(feature = "glacier")]
+        Command::Glacier(Glacier::Inventory(GlacierInventory::Get(args))) => {
+            glacier_get_inventory(args)
+        }
+        Command::Iam(Iam::Attach(args)) => iam_attach(args),
+        Command::Iam(Iam::Detach(args)) => iam_detach(args),
+        Command::Iam(Iam::EntitiesForPolicy { policy_arn }) => {
+            iam_entities_for_policy(policy_arn)
+        }
+        Command::Iam(Iam::CredentialReport(args)) => {
+            iam_credential_report(args)
+        }
+        Command::Iam(Iam::WhoAssumed(args)) => iam_who_assumed(args),
+        Command::Iam(Iam::Roles(args)) => iam_roles(args),
+        Command::ImageBuilder(ImageBuilder::Pipelines) => {
+            imagebuilder_list_pipelines()
+        }
+        Command::ImageBuilder(ImageBuilder::Run { pipeline }) => {
+            imagebuilder_run(pipeline)
+        }
+        Command::Inspector(Inspector::Findings(args)) => {
+            inspector_findings(args)
+        }
+        #[cfg(feature = "lightsail")]
+        Command::Lightsail(Lightsail::Instances) => lightsail_list_instances(),
+        #[cfg(feature = "lightsail")]
+        Command::Lightsail(Lightsail::Addr { instance_names }) => {
+            for_each(lightsail_show_addresses, instance_names)
+        }
+        #[cfg(feature = "lightsail")]
+        Command::Lightsail(Lightsail::Start { instance_names }) => {
+            for_each(lightsail_start_instance, instance_names)
+        }
+        #[cfg(feature = "lightsail")]
+        Command::Lightsail(Lightsail::Stop { instance_names }) => {
+            for_each(lightsail_stop_instance, instance_names)
         }
         Command::Logs(Logs::Groups(args)) => logs_groups(args),
         Command::Logs(Logs::RecentStreams(args)) => logs_recent_streams(args),
-        Command::S3(S3::Buckets) => s3_list_buckets(),
+        Command::Logs(Logs::Tail(args)) => logs_tail(args),
+        Command::Logs(Logs::Insights(args)) => logs_insights(args),
+        Command::Logs(Logs::Stats(args)) => logs_stats(args),
+        Command::Mq(Mq::Brokers) => mq_brokers(),
+        Command::Mq(Mq::Reboot { broker }) => mq_reboot(broker),
+        Command::Msk(Msk::Clusters) => msk_clusters(),
+        Command::Msk(Msk::Bootstrap { cluster }) => msk_bootstrap(cluster),
+        Command::OpenSearch(OpenSearch::Domains) => opensearch_domains(),
+        Command::OpenSearch(OpenSearch::Endpoint { domain, copy }) => {
+            opensearch_endpoint(domain, copy)
+        }
+        #[cfg(feature = "price")]
+        Command::Price(Price::Ec2(args)) => price_ec2(args),
+        #[cfg(feature = "price")]
+        Command::Price(Price::Ebs(args)) => price_ebs(args),
+        Command::Rekognition(Rekognition::Labels(args)) => {
+            rekognition_labels(args)
+        }
+        Command::Rekognition(Rekognition::Faces { image }) => {
+            rekognition_faces(image)
+        }
+        Command::Route53(Route53::HealthChecks) => route53_health_checks(),
+        Command::Route53(Route53::Export { zone }) => route53_export(zone),
+        Command::Route53(Route53::Import(args)) => route53_import(args),
+        Command::Route53(Route53::Check { name, type_ }) => {
+            route53_check(name, type_)
+        }
+        Command::Regions(Regions::List) => regions_list(),
+        Command::Regions(Regions::Ping) => regions_ping(),
+        Command::Run(args) => run_preset(args),
+        Command::S3(S3::Buckets(args)) => s3_list_buckets(args),
+        Command::S3(S3::Replication(S3Replication::Get { bucket })) => {
+            s3_replication_get(bucket)
+        }
+        Command::S3(S3::Replication(S3Replication::Add(args))) => {
+            s3_replication_add(args)
+        }
+        Command::S3(S3::Lock(S3Lock::Status(args))) => s3_lock_status(args),
+        Command::S3(S3::Lock(S3Lock::Set(args))) => s3_lock_set(args),
+        Command::S3(S3::Lock(S3Lock::LegalHold(args))) => {
+            s3_lock_legal_hold(args)
+        }
+        Command::S3(S3::Cp(args)) => s3_cp(args),
+        Command::SageMaker(SageMaker::Notebooks(args)) => match args.command {
+            Some(SageMakerNotebooksCommand::Start { name }) => {
+                sagemaker_notebook_action("StartNotebookInstance", name)
+            }
+            Some(SageMakerNotebooksCommand::Stop { name }) => {
+                sagemaker_notebook_action("StopNotebookInstance", name)
+            }
+            None => sagemaker_notebooks(),
+        },
+        Command::SageMaker(SageMaker::Endpoints) => sagemaker_endpoints(),
+        Command::SageMaker(SageMaker::Invoke(args)) => sagemaker_invoke(args),
+        Command::Scheduler(Scheduler::List(args)) => scheduler_list(args),
+        Command::Scheduler(Scheduler::Create(args)) => scheduler_create(args),
+        Command::Scheduler(Scheduler::Delete { name, group }) => {
+            scheduler_delete(name, group)
+        }
+        Command::SecurityHub(SecurityHub::Findings(args)) => {
+            securityhub_findings(args)
+        }
+        #[cfg(feature = "ses")]
+        Command::Ses(Ses::Stats) => ses_stats(),
+        #[cfg(feature = "ses")]
+        Command::Ses(Ses::Suppression(SesSuppression::List)) => {
+            ses_list_suppressed()
+        }
+        #[cfg(feature = "ses")]
+        Command::Ses(Ses::Suppression(SesSuppression::Remove { email })) => {
+            ses_remove_suppressed(email)
+        }
+        Command::Sso(Sso::Serve(args)) => sso_serve(args),
+        Command::Ssm(Ssm::Compliance) => ssm_compliance(),
+        Command::Ssm(Ssm::Documents(args)) => ssm_list_documents(args),
+        Command::Ssm(Ssm::Inventory(args)) => ssm_inventory(args),
+        Command::Ssm(Ssm::OpsItems(args)) => match args.command {
+            Some(SsmOpsItemsCommand::Resolve { ops_item_id }) => {
+                ssm_resolve_ops_item(ops_item_id)
+            }
+            None => ssm_list_ops_items(args.status),
+        },
+        Command::Ssm(Ssm::RunDocument(args)) => ssm_run_document(args),
+        Command::Textract(Textract::Analyze(args)) => textract_analyze(args),
+        Command::Waf(Waf::Acls { cloudfront }) => waf_acls(cloudfront),
+        Command::Waf(Waf::Rules { acl, cloudfront }) => {
+            waf_rules(acl, cloudfront)
+        }
+        Command::Waf(Waf::SampledRequests(args)) => waf_sampled_requests(args),
+        #[cfg(feature = "workspaces")]
+        Command::Workspaces(Workspaces::List) => workspaces_list(),
+        #[cfg(feature = "workspaces")]
+        Command::Workspaces(Workspaces::Reboot { workspace_ids }) => {
+            for_each(workspaces_reboot, workspace_ids)
+        }
+        #[cfg(feature = "workspaces")]
+        Command::Workspaces(Workspaces::Rebuild { workspace_ids }) => {
+            for_each(workspaces_rebuild, workspace_ids)
+        }
+    }
+}
+
+impl Command {
+    /// Whether the command can print enough output to be worth
+    /// showing in a pager.
+    fn pages_output(&self) -> bool {
+        match self {
+            Command::Ec2(Ec2::Instances(_)) => true,
+            Command::Inspector(Inspector::Findings(_)) => true,
+            Command::Logs(Logs::Tail(args)) => !args.follow,
+            Command::Logs(Logs::Insights(_)) => true,
+            Command::SecurityHub(SecurityHub::Findings(args)) => {
+                args.suppress.is_none() && args.resolve.is_none()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Run claws again with its output piped through $PAGER (or "less"
+/// if unset). The output of this process can't be redirected, so a
+/// child process writes it instead.
+#[throws]
+fn run_with_pager(pager: &str) {
+    let mut pager = process::Command::new("sh")
+        .args(["-c", pager])
+        // Like git, quit if the output fits on one screen and don't
+        // clear the screen on exit
+        .env(
+            "LESS",
+            std::env::var("LESS").unwrap_or_else(|_| "FRX".into()),
+        )
+        .stdin(process::Stdio::piped())
+        .spawn()
+        .context("failed to run pager")?;
+    let stdin = pager.stdin.take().context("missing pager stdin")?;
+    let status = process::Command::new(std::env::current_exe()?)
+        .args(std::env::args_os().skip(1))
+        .env("CLAWS_NO_PAGER", "1")
+        .stdout(stdin)
+        .status()
+        .context("failed to run claws")?;
+    pager.wait().context("failed to wait for pager")?;
+    // Quitting the pager before the end of the output closes the
+    // pipe, which isn't an error
+    if !status.success() && status.signal() != Some(libc::SIGPIPE) {
+        process::exit(status.code().unwrap_or(1));
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let opt = Opt::from_args();
+    if opt.command.pages_output() {
+        // Rust ignores SIGPIPE, so printing to a pager (or a pipe to
+        // head) that has exited would panic. Commands that only print
+        // output can use the default behavior of exiting instead.
+        unsafe {
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+        }
+    }
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".into());
+    if !opt.no_pager
+        && opt.command.pages_output()
+        && std::env::var_os("CLAWS_NO_PAGER").is_none()
+        && !pager.is_empty()
+        && pager != "cat"
+        && std::io::stdout().is_terminal()
+    {
+        return run_with_pager(&pager);
+    }
+    run(opt.command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        let cases = [
+            ("30s", Duration::seconds(30)),
+            ("10m", Duration::minutes(10)),
+            ("1h", Duration::hours(1)),
+            ("7d", Duration::days(7)),
+            ("2w", Duration::weeks(2)),
+            ("0s", Duration::zero()),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_duration(s).unwrap(), expected, "{}", s);
+        }
+        for s in ["", "s", "10", "10y", "1.5h", "-", "10µ"] {
+            assert!(parse_duration(s).is_err(), "{}", s);
+        }
+    }
+
+    #[cfg(feature = "cost")]
+    #[test]
+    fn test_parse_cost_start() {
+        let today = Utc::now().date_naive();
+        let cases = [
+            ("2024-01-15", NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()),
+            ("month-to-date", today.with_day(1).unwrap()),
+            ("30d", today - Duration::days(30)),
+            ("2w", today - Duration::weeks(2)),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_cost_start(s).unwrap(), expected, "{}", s);
+        }
+        for s in ["", "2024-13-01", "yesterday", "30x"] {
+            assert!(parse_cost_start(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_flow_log_filter_pattern() {
+        let all = "version, account_id, interface_id, srcaddr, dstaddr, \
+                   srcport, dstport, protocol, packets, bytes, start, end, \
+                   action, log_status";
+        let cases = [
+            ("", format!("[{}]", all)),
+            (
+                "dstport=443 action=REJECT",
+                format!(
+                    "[{}]",
+                    all.replace("dstport,", "dstport=443,")
+                        .replace("action,", "action=\"REJECT\",")
+                ),
+            ),
+            (
+                "interface-id=eni-123 srcaddr=10.0.0.1",
+                format!(
+                    "[{}]",
+                    all.replace("interface_id,", "interface_id=\"eni-123\",")
+                        .replace("srcaddr,", "srcaddr=\"10.0.0.1\",")
+                ),
+            ),
+        ];
+        for (filter, expected) in cases {
+            assert_eq!(flow_log_filter_pattern(filter).unwrap(), expected);
+        }
+        for filter in ["port=443", "dstport", "action=ACCEPT bogus=1"] {
+            assert!(flow_log_filter_pattern(filter).is_err(), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_dlm_create_rule() {
+        let cases = [
+            (
+                "daily",
+                serde_json::json!({
+                    "Interval": 24,
+                    "IntervalUnit": "HOURS",
+                    "Times": ["03:00"],
+                }),
+            ),
+            (
+                "weekly",
+                serde_json::json!({ "CronExpression": "cron(0 3 ? * SUN *)" }),
+            ),
+            (
+                "12h",
+                serde_json::json!({ "Interval": 12, "IntervalUnit": "HOURS" }),
+            ),
+        ];
+        for (schedule, expected) in cases {
+            assert_eq!(dlm_create_rule(schedule).unwrap(), expected);
+        }
+        for schedule in ["", "monthly", "12", "h", "-1h", "1.5h"] {
+            assert!(dlm_create_rule(schedule).is_err(), "{}", schedule);
+        }
+    }
+
+    #[test]
+    fn test_split_command_line() {
+        let cases: &[(&str, &[&str])] = &[
+            ("", &[]),
+            ("ec2 instances", &["ec2", "instances"]),
+            ("  logs   tail  ", &["logs", "tail"]),
+            (
+                "logs insights --query 'fields @message | limit 5'",
+                &["logs", "insights", "--query", "fields @message | limit 5"],
+            ),
+            (r#"a "b 'c'" d"#, &["a", "b 'c'", "d"]),
+            ("a '' b", &["a", "", "b"]),
+            ("--tag=\"Name=web 1\"", &["--tag=Name=web 1"]),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(
+                split_command_line(line).unwrap(),
+                *expected,
+                "{}",
+                line
+            );
+        }
+        assert!(split_command_line("a 'b").is_err());
+        assert!(split_command_line("\"a").is_err());
+    }
+
+    #[test]
+    fn test_s3_resume_state() {
+        let src = Path::new("disk.img");
+        let state = serde_json::json!({
+            "upload_id": "abc",
+            "source": src,
+            "size": 100,
+            "modified": 5,
+            "part_size": 8,
+        });
+        assert_eq!(
+            s3_resume_state(&state, src, 100, 5).unwrap(),
+            ("abc".to_string(), 8)
+        );
+        // The file changed since the upload started
+        for (size, modified) in [(101, 5), (100, 6)] {
+            assert!(s3_resume_state(&state, src, size, modified).is_err());
+        }
+        let invalid = [
+            serde_json::json!({}),
+            serde_json::json!({"size": 100, "modified": 5, "part_size": 8}),
+            serde_json::json!({"upload_id": "abc", "size": 100, "modified": 5}),
+        ];
+        for state in invalid {
+            assert!(s3_resume_state(&state, src, 100, 5).is_err(), "{}", state);
+        }
+    }
+
+    #[test]
+    fn test_s3_part_size() {
+        let min = 8 * 1024 * 1024;
+        assert_eq!(s3_part_size(S3_MULTIPART_THRESHOLD), min);
+        assert_eq!(s3_part_size(10_000 * min), min);
+        assert_eq!(s3_part_size(10_000 * min + 1), min + 1);
+        for size in [S3_MULTIPART_THRESHOLD, 1 << 30, 1 << 40, 5 << 40] {
+            let part_size = s3_part_size(size);
+            assert!(part_size >= min, "{}", size);
+            assert!(size.div_ceil(part_size) <= S3_MAX_PARTS, "{}", size);
+        }
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        let cases = [
+            ("100", 100),
+            ("100B/s", 100),
+            ("500K", 500 * 1024),
+            ("500k/s", 500 * 1024),
+            ("10MB/s", 10 * 1024 * 1024),
+            ("1.5M", 3 * 512 * 1024),
+            ("2G", 2 * 1024 * 1024 * 1024),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_rate(s).unwrap(), expected, "{}", s);
+        }
+        for s in ["", "/s", "fast", "10T", "0", "-5M"] {
+            assert!(parse_rate(s).is_err(), "{}", s);
+        }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_cron_field() {
+        let minute_cases: &[(&str, Option<&[u32]>)] = &[
+            ("5", Some(&[5])),
+            ("1,3,2,3", Some(&[1, 2, 3])),
+            ("10-12", Some(&[10, 11, 12])),
+            ("*/15", Some(&[0, 15, 30, 45])),
+            ("5/15", Some(&[5, 20, 35, 50])),
+            ("0-10/5", Some(&[0, 5, 10])),
+            ("*/0", None),
+            ("L", None),
+            ("15W", None),
+        ];
+        for (field, expected) in minute_cases {
+            assert_eq!(
+                parse_cron_field(field, 0, 59, &[]).as_deref(),
+                *expected,
+                "{}",
+                field
+            );
+        }
+
+        let weekdays = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+        let weekday_cases: &[(&str, Option<&[u32]>)] = &[
+            ("?", Some(&[1, 2, 3, 4, 5, 6, 7])),
+            ("MON-FRI", Some(&[2, 3, 4, 5, 6])),
+            ("sun,sat", Some(&[1, 7])),
+            ("2#1", None),
+        ];
+        for (field, expected) in weekday_cases {
+            assert_eq!(
+                parse_cron_field(field, 1, 7, weekdays).as_deref(),
+                *expected,
+                "{}",
+                field
+            );
+        }
+    }
+
+    #[test]
+    fn test_scheduler_next_fire() {
+        let now = utc("2024-03-06T10:07:00Z");
+        let cases: &[(&str, Option<&str>, Option<&str>)] = &[
+            (
+                "at(2024-03-07T00:00:00)",
+                None,
+                Some("2024-03-07T00:00:00Z"),
+            ),
+            ("at(2024-03-05T00:00:00)", None, None),
+            // Rates are anchored to the start date
+            (
+                "rate(1 hour)",
+                Some("2024-03-01T00:30:00Z"),
+                Some("2024-03-06T10:30:00Z"),
+            ),
+            (
+                "rate(5 minutes)",
+                Some("2024-03-06T10:01:00Z"),
+                Some("2024-03-06T10:11:00Z"),
+            ),
+            (
+                "rate(2 days)",
+                Some("2024-03-10T00:00:00Z"),
+                Some("2024-03-10T00:00:00Z"),
+            ),
+            ("rate(1 hour)", None, None),
+            ("rate(1 week)", Some("2024-03-01T00:00:00Z"), None),
+            ("cron(*/15 * * * ? *)", None, Some("2024-03-06T10:15:00Z")),
+            ("cron(5/15 * * * ? *)", None, Some("2024-03-06T10:20:00Z")),
+            // 2024-03-06 is a Wednesday
+            (
+                "cron(0 9 ? * MON-FRI *)",
+                None,
+                Some("2024-03-07T09:00:00Z"),
+            ),
+            ("cron(0 9 ? * SAT *)", None, Some("2024-03-09T09:00:00Z")),
+            ("cron(30 8 1 JAN ? *)", None, Some("2025-01-01T08:30:00Z")),
+            ("cron(0 0 29 FEB ? *)", None, Some("2028-02-29T00:00:00Z")),
+            // Only the next five years are searched
+            ("cron(0 0 1 1 ? 2030)", None, None),
+            ("cron(0 0 1 1 ? 2020)", None, None),
+            ("cron(0 0 L * ? *)", None, None),
+            ("cron(0 0 * * ?)", None, None),
+            ("every(1 hour)", None, None),
+        ];
+        for (expression, start, expected) in cases {
+            assert_eq!(
+                scheduler_next_fire(expression, start.map(utc), now),
+                expected.map(utc),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_config_section() {
+        let dir = std::env::temp_dir()
+            .join(format!("claws-test-{}", std::process::id()));
+        let path = dir.join("config");
+        let cases: &[(Option<&str>, &str)] = &[
+            (None, "[preset.a]\ncommand = \"ec2 instances\"\n"),
+            (Some(""), "[preset.a]\ncommand = \"ec2 instances\"\n"),
+            (
+                Some("[preset.b]\ncommand = \"logs groups\"\n"),
+                "[preset.b]\ncommand = \"logs groups\"\n\n\
+                 [preset.a]\ncommand = \"ec2 instances\"\n",
+            ),
+            (
+                Some(
+                    "[preset.a]\ncommand = \"old\"\nfollow = \"true\"\n\n\
+                     [ preset.b ]\ncommand = \"logs groups\"\n",
+                ),
+                "[ preset.b ]\ncommand = \"logs groups\"\n\n\
+                 [preset.a]\ncommand = \"ec2 instances\"\n",
+            ),
+            (
+                Some("# comment\n\n[preset.a]\ncommand = \"old\"\n"),
+                "# comment\n\n[preset.a]\ncommand = \"ec2 instances\"\n",
+            ),
+        ];
+        for (existing, expected) in cases {
+            let _ = fs::remove_dir_all(&dir);
+            if let Some(existing) = existing {
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(&path, existing).unwrap();
+            }
+            write_config_section(
+                &path,
+                "preset.a",
+                &[("command", "ec2 instances")],
+            )
+            .unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), *expected);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Encode an event stream message with string headers. The
+    /// checksums are left as zero since they aren't verified.
+    fn event_stream_message(
+        headers: &[(&str, &str)],
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut encoded_headers = Vec::new();
+        for (name, value) in headers {
+            encoded_headers.push(name.len() as u8);
+            encoded_headers.extend(name.as_bytes());
+            encoded_headers.push(7);
+            encoded_headers.extend(&(value.len() as u16).to_be_bytes());
+            encoded_headers.extend(value.as_bytes());
+        }
+        let total_len = 12 + encoded_headers.len() + payload.len() + 4;
+        let mut message = Vec::new();
+        message.extend(&(total_len as u32).to_be_bytes());
+        message.extend(&(encoded_headers.len() as u32).to_be_bytes());
+        message.extend(&[0; 4]);
+        message.extend(encoded_headers);
+        message.extend(payload);
+        message.extend(&[0; 4]);
+        message
+    }
+
+    #[test]
+    fn test_parse_event_stream_message() {
+        let first = event_stream_message(
+            &[(":event-type", "chunk"), (":message-type", "event")],
+            b"{\"bytes\":\"aGk=\"}",
+        );
+        let second = event_stream_message(&[], b"");
+        let mut buf = [first.clone(), second.clone()].concat();
+
+        let (headers, payload) =
+            parse_event_stream_message(&mut buf).unwrap().unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[":event-type"], "chunk");
+        assert_eq!(headers[":message-type"], "event");
+        assert_eq!(payload, b"{\"bytes\":\"aGk=\"}");
+        assert_eq!(buf, second);
+
+        let (headers, payload) =
+            parse_event_stream_message(&mut buf).unwrap().unwrap();
+        assert!(headers.is_empty());
+        assert!(payload.is_empty());
+        assert!(buf.is_empty());
+
+        // Incomplete messages are left in the buffer
+        for len in [0, 4, 12, first.len() - 1] {
+            let mut buf = first[..len].to_vec();
+            assert!(parse_event_stream_message(&mut buf).unwrap().is_none());
+            assert_eq!(buf.len(), len);
+        }
+
+        // Non-string headers are skipped
+        let mut message = event_stream_message(&[], b"x");
+        let header = [4, b'f', b'l', b'a', b'g', 0, 3, b'n', b'u', b'm', 4];
+        let header = [&header[..], &42u32.to_be_bytes()].concat();
+        message.splice(12..12, header.iter().copied());
+        let total_len = message.len() as u32;
+        message[0..4].copy_from_slice(&total_len.to_be_bytes());
+        message[4..8].copy_from_slice(&(header.len() as u32).to_be_bytes());
+        let (headers, payload) =
+            parse_event_stream_message(&mut message).unwrap().unwrap();
+        assert!(headers.is_empty());
+        assert_eq!(payload, b"x");
+
+        // A header length past the end of the message is an error
+        let mut message = event_stream_message(&[], b"");
+        message[4..8].copy_from_slice(&100u32.to_be_bytes());
+        assert!(parse_event_stream_message(&mut message).is_err());
+    }
+
+    #[test]
+    fn test_parse_zone_file() {
+        let text = r#"
+$ORIGIN example.com.
+$TTL 3600
+@       IN SOA ns1.example.com. admin.example.com. (
+            2024010101 ; serial
+            7200 3600 1209600 3600 )
+@       IN NS  ns1.example.com.
+@          A   192.0.2.1
+           A   192.0.2.2
+www 60  IN CNAME @
+mail IN 120 MX 10 mx1
+        MX  20 mx2.example.net.
+txt        TXT "v=spf1 -all; ok" "two words"
+$ORIGIN sub.example.com.
+host       AAAA 2001:db8::1
+sub.example.com. NS ns1.sub
+"#;
+        let record_sets = parse_zone_file(text, "example.com.").unwrap();
+        let record_sets: Vec<_> = record_sets
+            .iter()
+            .map(|set| {
+                (
+                    set.name.as_str(),
+                    set.type_.as_str(),
+                    set.ttl,
+                    set.values.clone(),
+                )
+            })
+            .collect();
+        let expected: Vec<(&str, &str, i64, Vec<String>)> = vec![
+            (
+                "example.com.",
+                "A",
+                3600,
+                vec!["192.0.2.1".into(), "192.0.2.2".into()],
+            ),
+            ("www.example.com.", "CNAME", 60, vec!["example.com.".into()]),
+            (
+                "mail.example.com.",
+                "MX",
+                120,
+                vec![
+                    "10 mx1.example.com.".into(),
+                    "20 mx2.example.net.".into(),
+                ],
+            ),
+            (
+                "txt.example.com.",
+                "TXT",
+                3600,
+                vec![r#""v=spf1 -all; ok" "two words""#.into()],
+            ),
+            (
+                "host.sub.example.com.",
+                "AAAA",
+                3600,
+                vec!["2001:db8::1".into()],
+            ),
+            (
+                "sub.example.com.",
+                "NS",
+                3600,
+                vec!["ns1.sub.sub.example.com.".into()],
+            ),
+        ];
+        assert_eq!(record_sets, expected);
+
+        let cases = [
+            "$ORIGIN",
+            "$TTL",
+            "$TTL soon",
+            "www 300 IN",
+            "www 300 IN A 192.0.2.1\n$TTL",
+        ];
+        for text in cases {
+            assert!(parse_zone_file(text, "example.com.").is_err(), "{}", text);
+        }
     }
 }